@@ -0,0 +1,569 @@
+use neb::ram::types::Id;
+use neb::client::transaction::TxnError;
+use futures::prelude::*;
+
+use server::schema::ToSchemaId;
+use super::{Graph, GraphInner, GraphTransaction, EdgeDirection, edge_attr_from_schema};
+use super::edge::{EdgeType, EdgeError, EdgeAttributes};
+use super::vertex::ToVertexId;
+use super::registry;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+impl Graph {
+    pub fn connected_components<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> impl Future<Item = Result<Vec<Vec<Id>>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        self.inner.connected_components(vertex_schema, edge_schema)
+    }
+    pub fn pagerank<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, damping: f64, iterations: usize)
+        -> impl Future<Item = Result<Vec<(Id, f64)>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        self.inner.pagerank(vertex_schema, edge_schema, damping, iterations)
+    }
+    // Loading the adjacency stays sequential, only the rank updates use the rayon pool
+    #[cfg(feature = "rayon")]
+    pub fn pagerank_parallel<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, damping: f64, iterations: usize)
+        -> impl Future<Item = Result<Vec<(Id, f64)>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        self.inner.pagerank_parallel(vertex_schema, edge_schema, damping, iterations)
+    }
+    pub fn count_triangles<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> impl Future<Item = Result<u64, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        self.inner.count_triangles(vertex_schema, edge_schema)
+    }
+    pub fn louvain<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, resolution: f64)
+        -> impl Future<Item = Result<Vec<(Id, u64)>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        self.inner.louvain(vertex_schema, edge_schema, resolution)
+    }
+    pub fn degree_distribution<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, ed: EdgeDirection)
+        -> impl Future<Item = Result<Vec<(Id, usize)>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        self.inner.degree_distribution(vertex_schema, edge_schema, ed)
+    }
+    pub fn triangles_at<V, S>(&self, vertex: V, edge_schema: S)
+        -> impl Future<Item = Result<u64, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.triangles_at(vertex, edge_schema)
+    }
+}
+
+impl GraphInner {
+    // Whole-schema analytics run in a single transaction so they see one consistent view,
+    // which also means they conflict with concurrent writers on large graphs.
+    pub fn connected_components<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> impl Future<Item = Result<Vec<Vec<Id>>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.connected_components(vertex_schema_id, edge_schema_id)
+        })
+    }
+    pub fn pagerank<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, damping: f64, iterations: usize)
+        -> impl Future<Item = Result<Vec<(Id, f64)>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.pagerank(vertex_schema_id, edge_schema_id, damping, iterations)
+        })
+    }
+    #[cfg(feature = "rayon")]
+    pub fn pagerank_parallel<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, damping: f64, iterations: usize)
+        -> impl Future<Item = Result<Vec<(Id, f64)>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.pagerank_parallel(vertex_schema_id, edge_schema_id, damping, iterations)
+        })
+    }
+    pub fn count_triangles<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> impl Future<Item = Result<u64, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.count_triangles(vertex_schema_id, edge_schema_id)
+        })
+    }
+    pub fn louvain<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, resolution: f64)
+        -> impl Future<Item = Result<Vec<(Id, u64)>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.louvain(vertex_schema_id, edge_schema_id, resolution)
+        })
+    }
+    pub fn degree_distribution<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, ed: EdgeDirection)
+        -> impl Future<Item = Result<Vec<(Id, usize)>, EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.degree_distribution(vertex_schema_id, edge_schema_id, ed)
+        })
+    }
+    pub fn triangles_at<V, S>(&self, vertex: V, edge_schema: S)
+        -> impl Future<Item = Result<u64, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let vertex_id = vertex.to_id();
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.triangles_at(vertex_id, edge_schema_id)
+        })
+    }
+}
+
+fn schema_direction(edge_attr: &EdgeAttributes) -> EdgeDirection {
+    match edge_attr.edge_type { // every edge is seen from at least one endpoint
+        EdgeType::Directed => EdgeDirection::Outbound,
+        EdgeType::Undirected => EdgeDirection::Undirected
+    }
+}
+
+fn pagerank_ranks(out_neighbours: &Vec<Vec<usize>>, damping: f64, iterations: usize) -> Vec<f64> {
+    if out_neighbours.is_empty() {
+        return Vec::new();
+    }
+    let n = out_neighbours.len() as f64;
+    let mut ranks = vec![1f64 / n; out_neighbours.len()];
+    for _ in 0..iterations {
+        // rank of vertices without out edges is spread evenly over all vertices
+        let dangling: f64 = out_neighbours
+            .iter()
+            .zip(ranks.iter())
+            .filter(|&(neighbours, _)| neighbours.is_empty())
+            .map(|(_, rank)| *rank)
+            .sum();
+        let base = (1f64 - damping) / n + damping * dangling / n;
+        let mut next_ranks = vec![base; out_neighbours.len()];
+        for (i, neighbours) in out_neighbours.iter().enumerate() {
+            if neighbours.is_empty() { continue; }
+            let share = damping * ranks[i] / neighbours.len() as f64;
+            for &j in neighbours {
+                next_ranks[j] += share;
+            }
+        }
+        ranks = next_ranks;
+    }
+    ranks
+}
+
+// Same iteration as pagerank_ranks, but every vertex pulls the shares of its in neighbours
+// so the ranks can be computed independently. Summing in another order makes the results
+// differ from the sequential ones by rounding only.
+#[cfg(feature = "rayon")]
+fn pagerank_ranks_parallel(out_neighbours: &Vec<Vec<usize>>, damping: f64, iterations: usize) -> Vec<f64> {
+    use rayon::prelude::*;
+    if out_neighbours.is_empty() {
+        return Vec::new();
+    }
+    let mut in_neighbours = vec![Vec::new(); out_neighbours.len()];
+    for (i, neighbours) in out_neighbours.iter().enumerate() {
+        for &j in neighbours {
+            in_neighbours[j].push(i);
+        }
+    }
+    let n = out_neighbours.len() as f64;
+    let mut ranks = vec![1f64 / n; out_neighbours.len()];
+    let mut next_ranks = vec![0f64; out_neighbours.len()];
+    for _ in 0..iterations {
+        let dangling: f64 = out_neighbours
+            .par_iter()
+            .zip(ranks.par_iter())
+            .filter(|&(neighbours, _)| neighbours.is_empty())
+            .map(|(_, rank)| *rank)
+            .sum();
+        let base = (1f64 - damping) / n + damping * dangling / n;
+        {
+            let ranks = &ranks;
+            next_ranks
+                .par_iter_mut()
+                .zip(in_neighbours.par_iter())
+                .for_each(|(rank, sources)| {
+                    *rank = base + sources
+                        .iter()
+                        .map(|&i| damping * ranks[i] / out_neighbours[i].len() as f64)
+                        .sum::<f64>();
+                });
+        }
+        ::std::mem::swap(&mut ranks, &mut next_ranks);
+    }
+    ranks
+}
+
+fn find_root(parents: &mut Vec<usize>, index: usize) -> usize {
+    let mut root = index;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut current = index;
+    while parents[current] != root { // path compression
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
+
+fn union(parents: &mut Vec<usize>, a: usize, b: usize) {
+    let root_a = find_root(parents, a);
+    let root_b = find_root(parents, b);
+    if root_a != root_b {
+        parents[root_b] = root_a;
+    }
+}
+
+// Moves each node to the neighbouring community with the best modularity gain until no
+// node moves. Nodes and communities are visited in index order so results are deterministic.
+fn louvain_local_moves(adjacency: &Vec<BTreeMap<usize, f64>>, resolution: f64) -> Option<Vec<usize>> {
+    let degrees: Vec<f64> = adjacency.iter().map(|row| row.values().sum()).collect();
+    let total_weight: f64 = degrees.iter().sum();
+    if total_weight == 0f64 {
+        return None;
+    }
+    let mut communities: Vec<usize> = (0..adjacency.len()).collect();
+    let mut totals = degrees.clone();
+    let mut improved = false;
+    loop {
+        let mut moved = false;
+        for i in 0..adjacency.len() {
+            let old_community = communities[i];
+            let mut weights: BTreeMap<usize, f64> = BTreeMap::new();
+            for (&j, &weight) in &adjacency[i] {
+                if j != i {
+                    *weights.entry(communities[j]).or_insert(0f64) += weight;
+                }
+            }
+            totals[old_community] -= degrees[i];
+            let gain = |community: usize, totals: &Vec<f64>| {
+                weights.get(&community).cloned().unwrap_or(0f64)
+                    - resolution * totals[community] * degrees[i] / total_weight
+            };
+            let mut best_community = old_community;
+            let mut best_gain = gain(old_community, &totals);
+            for &community in weights.keys() {
+                let community_gain = gain(community, &totals);
+                if community_gain > best_gain + 1e-12 {
+                    best_community = community;
+                    best_gain = community_gain;
+                }
+            }
+            totals[best_community] += degrees[i];
+            communities[i] = best_community;
+            if best_community != old_community {
+                moved = true;
+            }
+        }
+        if !moved { break; }
+        improved = true;
+    }
+    if improved { Some(communities) } else { None }
+}
+
+// Community ids are numbered by first appearance
+fn renumber(communities: &[usize]) -> Vec<usize> {
+    let mut numbers = BTreeMap::new();
+    communities.iter().map(|community| {
+        let next = numbers.len();
+        *numbers.entry(*community).or_insert(next)
+    }).collect()
+}
+
+impl <'a> GraphTransaction<'a> {
+    // Neighbours ignoring edge direction, without the vertex itself
+    fn undirected_neighbours(&self, vertex_id: &Id, schema_id: u32, edge_attr: &EdgeAttributes)
+        -> Result<Result<BTreeSet<Id>, EdgeError>, TxnError>
+    {
+        let directions = match edge_attr.edge_type {
+            EdgeType::Directed => vec![EdgeDirection::Outbound, EdgeDirection::Inbound],
+            EdgeType::Undirected => vec![EdgeDirection::Undirected]
+        };
+        let mut neighbours = BTreeSet::new();
+        for ed in directions {
+            match self.adjacent_ids(vertex_id, schema_id, edge_attr, ed)? {
+                Ok(ids) => neighbours.extend(ids), Err(e) => return Ok(Err(e))
+            }
+        }
+        neighbours.remove(vertex_id);
+        Ok(Ok(neighbours))
+    }
+    pub fn connected_components<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> Result<Result<Vec<Vec<Id>>, EdgeError>, TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let (edge_schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let vertex_ids = match registry::vertex_ids(self.neb_txn, vertex_schema_id)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        let ed = schema_direction(&edge_attr);
+        let indices: BTreeMap<Id, usize> = vertex_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect();
+        let mut parents: Vec<usize> = (0..vertex_ids.len()).collect();
+        for (i, vertex_id) in vertex_ids.iter().enumerate() {
+            let neighbours = match self.adjacent_ids(vertex_id, edge_schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            for neighbour_id in neighbours {
+                if let Some(&j) = indices.get(&neighbour_id) {
+                    union(&mut parents, i, j);
+                }
+            }
+        }
+        let mut components: Vec<Vec<Id>> = Vec::new();
+        let mut component_of_root = BTreeMap::new();
+        for (i, vertex_id) in vertex_ids.iter().enumerate() {
+            let root = find_root(&mut parents, i);
+            let component = *component_of_root.entry(root).or_insert(components.len());
+            if component == components.len() {
+                components.push(Vec::new());
+            }
+            components[component].push(*vertex_id);
+        }
+        Ok(Ok(components))
+    }
+    pub fn pagerank<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, damping: f64, iterations: usize)
+        -> Result<Result<Vec<(Id, f64)>, EdgeError>, TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let (vertex_ids, out_neighbours) = match self.out_neighbour_indices(vertex_schema, edge_schema)? {
+            Ok(t) => t, Err(e) => return Ok(Err(e))
+        };
+        let ranks = pagerank_ranks(&out_neighbours, damping, iterations);
+        Ok(Ok(vertex_ids.into_iter().zip(ranks.into_iter()).collect()))
+    }
+    #[cfg(feature = "rayon")]
+    pub fn pagerank_parallel<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, damping: f64, iterations: usize)
+        -> Result<Result<Vec<(Id, f64)>, EdgeError>, TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let (vertex_ids, out_neighbours) = match self.out_neighbour_indices(vertex_schema, edge_schema)? {
+            Ok(t) => t, Err(e) => return Ok(Err(e))
+        };
+        let ranks = pagerank_ranks_parallel(&out_neighbours, damping, iterations);
+        Ok(Ok(vertex_ids.into_iter().zip(ranks.into_iter()).collect()))
+    }
+    // Vertices of the schema with the positions of their out neighbours in the same list
+    fn out_neighbour_indices<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> Result<Result<(Vec<Id>, Vec<Vec<usize>>), EdgeError>, TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let (edge_schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let vertex_ids = match registry::vertex_ids(self.neb_txn, vertex_schema_id)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        let ed = schema_direction(&edge_attr);
+        let indices: BTreeMap<Id, usize> = vertex_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect();
+        let mut out_neighbours = Vec::with_capacity(vertex_ids.len());
+        for vertex_id in &vertex_ids {
+            let neighbours = match self.adjacent_ids(vertex_id, edge_schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            let neighbours: Vec<usize> = neighbours
+                .iter()
+                .filter_map(|id| indices.get(id).cloned()) // edges leaving the vertex schema are ignored
+                .collect();
+            out_neighbours.push(neighbours);
+        }
+        Ok(Ok((vertex_ids, out_neighbours)))
+    }
+    pub fn count_triangles<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> Result<Result<u64, EdgeError>, TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let (edge_schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let vertex_ids = match registry::vertex_ids(self.neb_txn, vertex_schema_id)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        let members: BTreeSet<Id> = vertex_ids.iter().cloned().collect();
+        let mut neighbours = BTreeMap::new();
+        for vertex_id in &vertex_ids {
+            let adjacent = match self.undirected_neighbours(vertex_id, edge_schema_id, &edge_attr)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            let adjacent: BTreeSet<Id> = adjacent.intersection(&members).cloned().collect();
+            neighbours.insert(*vertex_id, adjacent);
+        }
+        // count each triangle u < v < w once, from its smallest edge
+        let mut triangles = 0u64;
+        for (u, u_neighbours) in &neighbours {
+            for v in u_neighbours.iter().filter(|v| *v > u) {
+                let v_neighbours = &neighbours[v];
+                triangles += u_neighbours
+                    .intersection(v_neighbours)
+                    .filter(|w| *w > v)
+                    .count() as u64;
+            }
+        }
+        Ok(Ok(triangles))
+    }
+    // Edge direction is ignored and every edge weighs one
+    pub fn louvain<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, resolution: f64)
+        -> Result<Result<Vec<(Id, u64)>, EdgeError>, TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let (edge_schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let vertex_ids = match registry::vertex_ids(self.neb_txn, vertex_schema_id)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        let ed = schema_direction(&edge_attr);
+        let indices: BTreeMap<Id, usize> = vertex_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect();
+        // self-loops count twice so a row always sums up to the node degree
+        let mut adjacency: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); vertex_ids.len()];
+        for (i, vertex_id) in vertex_ids.iter().enumerate() {
+            let neighbours = match self.adjacent_ids(vertex_id, edge_schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            for neighbour_id in neighbours {
+                let j = match indices.get(&neighbour_id) {
+                    Some(&j) => j, None => continue
+                };
+                // undirected edges are listed on both endpoints
+                if edge_attr.edge_type == EdgeType::Undirected && j < i { continue; }
+                *adjacency[i].entry(j).or_insert(0f64) += 1f64;
+                *adjacency[j].entry(i).or_insert(0f64) += 1f64;
+            }
+        }
+        let mut membership: Vec<usize> = (0..vertex_ids.len()).collect();
+        while let Some(communities) = louvain_local_moves(&adjacency, resolution) {
+            let communities = renumber(&communities);
+            let count = communities.iter().max().map(|c| c + 1).unwrap_or(0);
+            let mut aggregated = vec![BTreeMap::new(); count];
+            for (i, row) in adjacency.iter().enumerate() {
+                for (&j, &weight) in row {
+                    *aggregated[communities[i]].entry(communities[j]).or_insert(0f64) += weight;
+                }
+            }
+            for node in membership.iter_mut() {
+                *node = communities[*node];
+            }
+            adjacency = aggregated;
+        }
+        let membership = renumber(&membership);
+        Ok(Ok(vertex_ids.into_iter().zip(membership.into_iter().map(|c| c as u64)).collect()))
+    }
+    // Only id list lengths are read, in the order vertices were registered
+    pub fn degree_distribution<VS, ES>(&self, vertex_schema: VS, edge_schema: ES, ed: EdgeDirection)
+        -> Result<Result<Vec<(Id, usize)>, EdgeError>, TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        let vertex_ids = match registry::vertex_ids(self.neb_txn, vertex_schema_id)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        let mut degrees = Vec::with_capacity(vertex_ids.len());
+        for vertex_id in vertex_ids {
+            match self.degree(&vertex_id, edge_schema_id, ed)? {
+                Ok(degree) => degrees.push((vertex_id, degree)),
+                Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(degrees))
+    }
+    pub fn triangles_at<V, S>(&self, vertex: V, edge_schema: S)
+        -> Result<Result<u64, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let vertex_id = vertex.to_id();
+        let (edge_schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let neighbours = match self.undirected_neighbours(&vertex_id, edge_schema_id, &edge_attr)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(e))
+        };
+        let mut triangles = 0u64;
+        for a in &neighbours {
+            let a_neighbours = match self.undirected_neighbours(a, edge_schema_id, &edge_attr)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            triangles += neighbours
+                .range(*a..)
+                .filter(|b| *b != a && a_neighbours.contains(b))
+                .count() as u64;
+        }
+        Ok(Ok(triangles))
+    }
+    pub fn common_neighbours<V, S>(&self, a: V, b: V, schema: S, ed: EdgeDirection)
+        -> Result<Result<Vec<Id>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let a_neighbours = match self.adjacent_ids(&a.to_id(), schema_id, &edge_attr, ed)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(e))
+        };
+        let b_neighbours: BTreeSet<Id> = match self.adjacent_ids(&b.to_id(), schema_id, &edge_attr, ed)? {
+            Ok(ids) => ids.into_iter().collect(), Err(e) => return Ok(Err(e))
+        };
+        let mut seen = BTreeSet::new();
+        Ok(Ok(a_neighbours
+            .into_iter()
+            .filter(|id| b_neighbours.contains(id) && seen.insert(*id))
+            .collect()))
+    }
+    // Vertices without any neighbour are not similar to anything, including each other
+    pub fn jaccard<V, S>(&self, a: V, b: V, schema: S, ed: EdgeDirection)
+        -> Result<Result<f64, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let a_neighbours: BTreeSet<Id> = match self.adjacent_ids(&a.to_id(), schema_id, &edge_attr, ed)? {
+            Ok(ids) => ids.into_iter().collect(), Err(e) => return Ok(Err(e))
+        };
+        let b_neighbours: BTreeSet<Id> = match self.adjacent_ids(&b.to_id(), schema_id, &edge_attr, ed)? {
+            Ok(ids) => ids.into_iter().collect(), Err(e) => return Ok(Err(e))
+        };
+        let union = a_neighbours.union(&b_neighbours).count();
+        if union == 0 {
+            return Ok(Ok(0f64));
+        }
+        let intersection = a_neighbours.intersection(&b_neighbours).count();
+        Ok(Ok(intersection as f64 / union as f64))
+    }
+}
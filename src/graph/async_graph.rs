@@ -0,0 +1,86 @@
+use neb::ram::types::Id;
+use neb::dovahkiin::types::Map;
+use neb::client::transaction::TxnError;
+use server::schema::{ToSchemaId, ToVertexSchemaId, ToEdgeSchemaId};
+use query::Expr;
+use futures::prelude::*;
+use futures_cpupool::{CpuPool, CpuFuture};
+
+use super::{Graph, GraphTransaction, EdgeDirection, NewVertexError, ReadVertexError, LinkVerticesError, NeighbourhoodError};
+use super::vertex::{Vertex, ToVertexId, RemoveError};
+use super::edge::Edge;
+use super::id_list::IdListError;
+
+use std::sync::Arc;
+
+// Runs graph operations on a thread pool. Transaction bodies call neb synchronously,
+// so they block the pool thread instead of the thread polling the returned future.
+// Schemas and vertices are resolved to ids before the operation is sent to the pool.
+pub struct AsyncGraph {
+    graph: Arc<Graph>,
+    pool: CpuPool
+}
+
+impl AsyncGraph {
+    pub fn new(graph: &Arc<Graph>, pool: CpuPool) -> AsyncGraph {
+        AsyncGraph {
+            graph: graph.clone(),
+            pool: pool
+        }
+    }
+    pub fn graph(&self) -> &Arc<Graph> {
+        &self.graph
+    }
+    pub fn new_vertex<S>(&self, schema: S, data: Map) -> CpuFuture<Vertex, NewVertexError>
+        where S: ToVertexSchemaId
+    {
+        let graph = self.graph.clone();
+        let schema_id = schema.to_id(&graph.inner.schemas);
+        self.pool.spawn_fn(move || graph.new_vertex(schema_id, data).wait())
+    }
+    pub fn vertex_by<V>(&self, vertex: V) -> CpuFuture<Option<Vertex>, ReadVertexError>
+        where V: ToVertexId
+    {
+        let graph = self.graph.clone();
+        let id = vertex.to_id();
+        self.pool.spawn_fn(move || graph.vertex_by(&id).wait())
+    }
+    pub fn update_vertex<V, U>(&self, vertex: V, update: U) -> CpuFuture<Result<(), IdListError>, TxnError>
+        where V: ToVertexId, U: Fn(Vertex) -> Option<Vertex>, U: Send + 'static
+    {
+        let graph = self.graph.clone();
+        let id = vertex.to_id();
+        self.pool.spawn_fn(move || graph.update_vertex(&id, update).wait())
+    }
+    pub fn remove_vertex<V>(&self, vertex: V) -> CpuFuture<Result<(), RemoveError>, TxnError>
+        where V: ToVertexId
+    {
+        let graph = self.graph.clone();
+        let id = vertex.to_id();
+        self.pool.spawn_fn(move || graph.remove_vertex(&id).wait())
+    }
+    pub fn link<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> CpuFuture<Result<Edge, LinkVerticesError>, TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        let graph = self.graph.clone();
+        let (from_id, to_id): (Id, Id) = (from.to_id(), to.to_id());
+        let schema_id = schema.to_id(&graph.inner.schemas);
+        self.pool.spawn_fn(move || graph.link(&from_id, schema_id, &to_id, body).wait())
+    }
+    pub fn neighbourhoods<V, S, F>(&self, vertex: V, schema: S, direction: EdgeDirection, filter: Option<F>)
+        -> CpuFuture<Result<Vec<(Vertex, Edge)>, NeighbourhoodError>, TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId, F: Expr + Send + 'static
+    {
+        let graph = self.graph.clone();
+        let id = vertex.to_id();
+        let schema_id = schema.to_id(&graph.inner.schemas);
+        self.pool.spawn_fn(move || graph.neighbourhoods(&id, schema_id, direction, &filter).wait())
+    }
+    pub fn graph_transaction<TFN, TR>(&self, func: TFN) -> CpuFuture<TR, TxnError>
+        where TFN: Fn(&GraphTransaction) -> Result<TR, TxnError>, TFN: Send + 'static, TR: Send + 'static
+    {
+        let graph = self.graph.clone();
+        self.pool.spawn_fn(move || graph.graph_transaction(func).wait())
+    }
+}
@@ -9,6 +9,15 @@ use super::{TEdge, EdgeError};
 use super::super::id_list::IdList;
 use server::schema::{SchemaContainer, SchemaType};
 
+// The vertex cell is only read when some vertex schema customised its id list capacity
+fn list_capacity(txn: &Transaction, schemas: &Arc<SchemaContainer>, vertex_id: &Id)
+    -> Result<Option<usize>, TxnError>
+{
+    if !schemas.has_id_list_capacities() {
+        return Ok(None);
+    }
+    Ok(txn.read(vertex_id)?.and_then(|cell| schemas.id_list_capacity(cell.header.schema)))
+}
 
 pub trait BilateralEdge : TEdge {
 
@@ -53,17 +62,7 @@ pub trait BilateralEdge : TEdge {
             },
             SchemaType::Edge(edge_attrs) => {
                 if edge_attrs.edge_type == Self::edge_type() {
-                    if let (
-                        &Value::Id(e_a_id),
-                        &Value::Id(e_b_id)
-                    ) = (
-                        &trace_cell.data[Self::edge_a_field()],
-                        &trace_cell.data[Self::edge_b_field()]
-                    ) {
-                        a_id = e_a_id;
-                        b_id = e_b_id;
-                    }
-                    Some(trace_cell)
+                    return Ok(Ok(Self::from_edge_cell(trace_cell, schema_id)));
                 } else {
                     return Ok(Err(EdgeError::WrongEdgeType))
                 }
@@ -72,6 +71,21 @@ pub trait BilateralEdge : TEdge {
         };
         Ok(Ok(Self::build_edge(a_id, b_id, schema_id, edge_cell)))
     }
+    fn from_edge_cell(cell: Cell, schema_id: u32) -> Self::Edge {
+        let mut a_id = Id::unit_id();
+        let mut b_id = Id::unit_id();
+        if let (
+            &Value::Id(e_a_id),
+            &Value::Id(e_b_id)
+        ) = (
+            &cell.data[Self::edge_a_field()],
+            &cell.data[Self::edge_b_field()]
+        ) {
+            a_id = e_a_id;
+            b_id = e_b_id;
+        }
+        Self::build_edge(a_id, b_id, schema_id, Some(cell))
+    }
     fn link(
         vertex_a_id: &Id, vertex_b_id: &Id, body: Option<Map>,
         txn: &Transaction,
@@ -114,10 +128,12 @@ pub trait BilateralEdge : TEdge {
             }
         };
         match IdList::from_txn_and_container(txn, vertex_a_id, Self::vertex_a_field(), schema_id)
+            .with_capacity(list_capacity(txn, schemas, vertex_a_id)?)
             .add(&vertex_a_pointer)?.map_err(EdgeError::IdListError) {
             Err(e) => return Ok(Err(e)), _ => {}
         }
         match IdList::from_txn_and_container(txn, vertex_b_id, Self::vertex_b_field(), schema_id)
+            .with_capacity(list_capacity(txn, schemas, vertex_b_id)?)
             .add(&vertex_b_pointer)?.map_err(EdgeError::IdListError) {
             Err(e) => return Ok(Err(e)), _ => {}
         }
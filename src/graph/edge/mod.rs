@@ -14,7 +14,15 @@ use neb::dovahkiin::types::Value;
 use graph::edge::bilateral::BilateralEdge;
 use server::schema::{SchemaContainer, SchemaType};
 use super::id_list::IdListError;
+use super::EdgeBodyError;
+use utils::value;
+use graph::fields::{INBOUND_KEY_ID, OUTBOUND_KEY_ID, ToFieldId};
+use graph::EdgeDirection;
+use serde::{Serialize, Serializer};
+use serde_json::{self, Value as JsonValue};
 use std::sync::Arc;
+use std::error::Error;
+use std::fmt;
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
 pub enum EdgeType {
@@ -35,6 +43,28 @@ impl EdgeAttributes {
             has_body: has_body
         }
     }
+    // simple edges only live in the id lists of their endpoints
+    pub fn directed() -> EdgeAttributes {
+        EdgeAttributes::new(EdgeType::Directed, false)
+    }
+    pub fn undirected() -> EdgeAttributes {
+        EdgeAttributes::new(EdgeType::Undirected, false)
+    }
+    // undirected edges with a body, the only kind link_bilateral accepts
+    pub fn bilateral() -> EdgeAttributes {
+        EdgeAttributes::new(EdgeType::Undirected, true)
+    }
+    // edges with a body get a cell of their own holding the schema fields
+    pub fn with_body(mut self) -> EdgeAttributes {
+        self.has_body = true;
+        self
+    }
+}
+
+impl Default for EdgeAttributes {
+    fn default() -> EdgeAttributes {
+        EdgeAttributes::directed()
+    }
 }
 
 #[derive(Debug)]
@@ -42,12 +72,19 @@ pub enum EdgeError {
     WrongSchema,
     CannotFindSchema,
     CellNotFound,
+    EdgeNotFound,
     WrongVertexField,
     WrongEdgeType,
     IdListError(IdListError),
     SimpleEdgeShouldNotHaveBody,
     NormalEdgeShouldHaveBody,
-    FilterEvalError(String)
+    FieldMissing(u64),
+    FieldNotNumeric(u64),
+    InvalidWeight(u64),
+    FilterEvalError(String),
+    EndpointChanged(u64),
+    BodyRemoved,
+    InvalidBody(EdgeBodyError)
 }
 
 pub trait TEdge {
@@ -69,12 +106,124 @@ impl Edge {
             Edge::Undirected(mut e) => e.remove(txn),
         }
     }
+    pub fn schema_id(&self) -> u32 {
+        match self {
+            &Edge::Directed(ref e) => e.schema_id(),
+            &Edge::Undirected(ref e) => e.schema_id(),
+        }
+    }
+    pub fn edge_type(&self) -> EdgeType {
+        match self {
+            &Edge::Directed(_) => EdgeType::Directed,
+            &Edge::Undirected(_) => EdgeType::Undirected,
+        }
+    }
+    // (from, to) for directed edges
+    pub fn endpoints(&self) -> (Id, Id) {
+        match self {
+            &Edge::Directed(ref e) => (*e.vertex_a(), *e.vertex_b()),
+            &Edge::Undirected(ref e) => (*e.vertex_a(), *e.vertex_b()),
+        }
+    }
+    // The id list of vertex_id this edge is kept in, None when the vertex is not an endpoint.
+    // Self-loops are reported as outbound.
+    pub fn relation_to(&self, vertex_id: &Id) -> Option<EdgeDirection> {
+        let (a, b) = self.endpoints();
+        match self {
+            &Edge::Undirected(_) if a == *vertex_id || b == *vertex_id => Some(EdgeDirection::Undirected),
+            &Edge::Directed(_) if a == *vertex_id => Some(EdgeDirection::Outbound),
+            &Edge::Directed(_) if b == *vertex_id => Some(EdgeDirection::Inbound),
+            _ => None
+        }
+    }
+    // (to, from) for directed edges, walking against the edge. The edge itself is not
+    // flipped, removing it must still go through the stored orientation
+    pub fn reversed_endpoints(&self) -> (Id, Id) {
+        let (a, b) = self.endpoints();
+        (b, a)
+    }
+    // The directed edge seen from its other end, sharing the body cell. It is only a view,
+    // nothing is stored in that orientation, so it cannot be removed. Undirected edges have
+    // no orientation to flip and give None.
+    pub fn reversed(&self) -> Option<Edge> {
+        match self {
+            &Edge::Directed(ref e) => Some(Edge::Directed(directed::DirectedEdge::build_edge(
+                *e.vertex_b(), *e.vertex_a(), e.schema_id(), e.edge_cell().clone()
+            ))),
+            &Edge::Undirected(_) => None
+        }
+    }
+    // edges with a body are stored in their own cell, simple edges only live in the id lists
+    pub fn id(&self) -> Option<Id> {
+        self.get_data().as_ref().map(|cell| cell.id())
+    }
     pub fn get_data(&self) -> &Option<Cell> {
         match self {
             &Edge::Directed(ref e) => e.edge_cell(),
             &Edge::Undirected(ref e) => e.edge_cell(),
         }
     }
+    pub fn into_data(self) -> Option<Cell> {
+        match self {
+            Edge::Directed(e) => e.cell,
+            Edge::Undirected(e) => e.cell,
+        }
+    }
+    // weights are costs, negative or non finite ones would break shortest path searches
+    pub fn weight(&self, field: u64) -> Result<f64, EdgeError> {
+        let weight = match &self[field] {
+            &Value::Null => return Err(EdgeError::FieldMissing(field)),
+            value => value::to_f64(value).ok_or(EdgeError::FieldNotNumeric(field))?
+        };
+        if !weight.is_finite() || weight < 0f64 {
+            return Err(EdgeError::InvalidWeight(field));
+        }
+        Ok(weight.abs()) // only turns -0.0 into 0.0
+    }
+    pub fn get_str<K>(&self, field: K) -> Option<&str> where K: ToFieldId {
+        value::as_str(&self[field.to_field_id()])
+    }
+    pub fn get_i64<K>(&self, field: K) -> Option<i64> where K: ToFieldId {
+        value::to_i64(&self[field.to_field_id()])
+    }
+    pub fn get_f64<K>(&self, field: K) -> Option<f64> where K: ToFieldId {
+        value::to_f64(&self[field.to_field_id()])
+    }
+    pub fn get_id<K>(&self, field: K) -> Option<Id> where K: ToFieldId {
+        value::as_id(&self[field.to_field_id()])
+    }
+    pub fn to_json(&self) -> JsonValue {
+        let mut object = serde_json::Map::new();
+        let hidden = match self {
+            &Edge::Directed(ref e) => {
+                object.insert("type".to_string(), JsonValue::from("directed"));
+                object.insert("schema".to_string(), JsonValue::from(e.schema_id()));
+                object.insert("from".to_string(), value::id_to_json(e.vertex_a()));
+                object.insert("to".to_string(), value::id_to_json(e.vertex_b()));
+                [*INBOUND_KEY_ID, *OUTBOUND_KEY_ID]
+            },
+            &Edge::Undirected(ref e) => {
+                object.insert("type".to_string(), JsonValue::from("undirected"));
+                object.insert("schema".to_string(), JsonValue::from(e.schema_id()));
+                object.insert("vertices".to_string(), JsonValue::Array(vec![
+                    value::id_to_json(e.vertex_a()), value::id_to_json(e.vertex_b())
+                ]));
+                [*undirectd::EDGE_VERTEX_A_ID, *undirectd::EDGE_VERTEX_B_ID]
+            }
+        };
+        let body = match self.get_data() {
+            &Some(ref cell) => {
+                object.insert("id".to_string(), value::id_to_json(&cell.id()));
+                match &cell.data {
+                    &Value::Map(ref map) => value::map_to_json(map, &hidden),
+                    other => value::to_json(other)
+                }
+            },
+            &None => JsonValue::Null
+        };
+        object.insert("body".to_string(), body);
+        JsonValue::Object(object)
+    }
     pub fn one_opposite_id_vertex_id(&self, vertex_id: &Id) -> Option<&Id> {
         match self {
             &Edge::Directed(ref e) => e.oppisite_vertex_id(vertex_id),
@@ -139,4 +288,87 @@ pub fn from_id(
         Some(_) => return Ok(Err(EdgeError::WrongSchema)),
         None => return Ok(Err(EdgeError::CannotFindSchema))
     }
-}
\ No newline at end of file
+}
+
+pub fn opposite_id(
+    vertex_id: &Id, list_item: &Id, edge_attr: &EdgeAttributes, txn: &Transaction
+) -> Result<Option<Id>, TxnError> {
+    if !edge_attr.has_body {
+        return Ok(Some(*list_item)); // simple edges keep the opposite vertex id in the id list
+    }
+    let endpoint_fields = match edge_attr.edge_type {
+        EdgeType::Directed => vec![
+            directed::DirectedEdge::edge_a_field(), directed::DirectedEdge::edge_b_field()
+        ],
+        EdgeType::Undirected => vec![
+            undirectd::UndirectedEdge::edge_a_field(), undirectd::UndirectedEdge::edge_b_field()
+        ]
+    };
+    if let Some(fields) = txn.read_selected(list_item, &endpoint_fields)? {
+        if let (Some(&Value::Id(a_id)), Some(&Value::Id(b_id))) = (fields.get(0), fields.get(1)) {
+            if a_id == *vertex_id {
+                return Ok(Some(b_id));
+            } else if b_id == *vertex_id {
+                return Ok(Some(a_id));
+            }
+        }
+    }
+    Ok(None)
+}
+
+pub fn from_cell(cell: Cell, schemas: &Arc<SchemaContainer>) -> Result<Edge, EdgeError> {
+    let schema_id = cell.header.schema;
+    match schemas.schema_type(schema_id) {
+        Some(SchemaType::Edge(ea)) => {
+            match ea.edge_type {
+                EdgeType::Directed => Ok(Edge::Directed(
+                    directed::DirectedEdge::from_edge_cell(cell, schema_id)
+                )),
+                EdgeType::Undirected => Ok(Edge::Undirected(
+                    undirectd::UndirectedEdge::from_edge_cell(cell, schema_id)
+                ))
+            }
+        },
+        Some(_) => Err(EdgeError::WrongSchema),
+        None => Err(EdgeError::CannotFindSchema)
+    }
+}
+
+impl fmt::Display for EdgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &EdgeError::WrongSchema => write!(f, "edge cell belongs to another schema"),
+            &EdgeError::CannotFindSchema => write!(f, "cannot find edge schema"),
+            &EdgeError::CellNotFound => write!(f, "edge cell not found"),
+            &EdgeError::EdgeNotFound => write!(f, "edge not found"),
+            &EdgeError::WrongVertexField => write!(f, "wrong vertex field for edge"),
+            &EdgeError::WrongEdgeType => write!(f, "wrong edge type"),
+            &EdgeError::IdListError(ref e) => write!(f, "id list error: {}", e),
+            &EdgeError::SimpleEdgeShouldNotHaveBody => write!(f, "simple edge should not have body"),
+            &EdgeError::NormalEdgeShouldHaveBody => write!(f, "normal edge should have body"),
+            &EdgeError::FieldMissing(field) => write!(f, "edge field {} is missing", field),
+            &EdgeError::FieldNotNumeric(field) => write!(f, "edge field {} is not numeric", field),
+            &EdgeError::InvalidWeight(field) => write!(f, "edge field {} is not a finite, non negative weight", field),
+            &EdgeError::FilterEvalError(ref e) => write!(f, "cannot evaluate edge filter: {}", e),
+            &EdgeError::EndpointChanged(field) => write!(f, "edge endpoint field {} cannot be changed", field),
+            &EdgeError::BodyRemoved => write!(f, "edge body was removed or is not a map"),
+            &EdgeError::InvalidBody(ref e) => write!(f, "invalid edge body: {}", e)
+        }
+    }
+}
+
+impl Error for EdgeError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &EdgeError::IdListError(ref e) => Some(e),
+            &EdgeError::InvalidBody(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl Serialize for Edge {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        self.to_json().serialize(serializer)
+    }
+}
@@ -27,7 +27,7 @@ pub struct UndirectedEdge {
     vertex_a_id: Id,
     vertex_b_id: Id,
     schema_id: u32,
-    cell: Option<Cell>,
+    pub cell: Option<Cell>,
 }
 
 impl TEdge for UndirectedEdge {
@@ -1,4 +1,5 @@
 use neb::ram::schema::Field;
+use graph::EdgeDirection;
 use neb::ram::types::{TypeId, Id, key_hash};
 
 pub const INBOUND_KEY: &'static str = "_inbound";
@@ -17,4 +18,39 @@ lazy_static! {
     pub static ref INBOUND_KEY_ID: u64 = key_hash(&*INBOUND_NAME);
     pub static ref OUTBOUND_KEY_ID: u64 = key_hash(&*OUTBOUND_NAME);
     pub static ref UNDIRECTED_KEY_ID: u64 = key_hash(&*UNDIRECTED_NAME);
-}
\ No newline at end of file
+}
+pub trait ToFieldId {
+    fn to_field_id(&self) -> u64;
+}
+
+impl ToFieldId for u64 {
+    fn to_field_id(&self) -> u64 {
+        *self
+    }
+}
+
+impl <'a> ToFieldId for &'a str {
+    fn to_field_id(&self) -> u64 {
+        key_hash(&self.to_string())
+    }
+}
+
+pub const ALL_DIRECTIONS: [EdgeDirection; 3] =
+    [EdgeDirection::Outbound, EdgeDirection::Inbound, EdgeDirection::Undirected];
+
+pub fn vertex_template(directions: &[EdgeDirection]) -> Vec<Field> {
+    VERTEX_TEMPLATE
+        .iter()
+        .filter(|field| directions.iter().any(|ed| ed.as_field() == key_hash(&field.name)))
+        .cloned()
+        .collect()
+}
+
+// Edge list key ids a vertex schema reserved fields for
+pub fn vertex_list_keys(fields: &[Field]) -> Vec<u64> {
+    ALL_DIRECTIONS
+        .iter()
+        .map(|ed| ed.as_field())
+        .filter(|key_id| fields.iter().any(|field| key_hash(&field.name) == *key_id))
+        .collect()
+}
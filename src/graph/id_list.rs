@@ -4,6 +4,8 @@ use neb::ram::types::{TypeId, Id, Map, Value, id_io, u32_io, key_hash};
 use neb::client::transaction::{Transaction, TxnError};
 
 use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
 
 use utils::transaction::set_map_by_key_id;
 
@@ -18,7 +20,8 @@ pub const ID_TYPE_ID_LIST_KEY: &'static str = "_type_list";
 pub enum IdListError {
     ContainerCellNotFound,
     FormatError,
-    Unexpected
+    Unexpected,
+    RegistryIncomplete(u32)
 }
 
 pub static ID_LIST_SCHEMA_ID: u32 = 100;
@@ -51,7 +54,8 @@ pub struct IdList<'a> {
     pub txn: &'a Transaction,
     container_id: Id,
     field_id: u64,
-    schema_id: u32
+    schema_id: u32,
+    capacity: usize
 }
 
 fn empty_list_segment(container_id: &Id, field_id: u64, schema_id: u32, level: usize) -> (Id, Value) {
@@ -85,9 +89,9 @@ fn count_cell_list(seg: &Cell) -> Result<usize, IdListError> {
 
 fn val_is_id(val: &Value, id: &Id) -> bool {
     if let &Value::Id(ref val_id) = val {
-        return val_id != id;
+        return val_id == id;
     } else {
-        return true;
+        return false;
     }
 }
 
@@ -109,9 +113,17 @@ impl<'a> IdList <'a> {
             txn: txn,
             container_id: *container_id,
             field_id: field_id,
-            schema_id: schema_id
+            schema_id: schema_id,
+            capacity: *LIST_CAPACITY
         }
     }
+    // ids per segment for new additions, never more than a cell can hold
+    pub fn with_capacity(mut self, capacity: Option<usize>) -> IdList<'a> {
+        if let Some(capacity) = capacity {
+            self.capacity = capacity.max(1).min(*LIST_CAPACITY);
+        }
+        self
+    }
     pub fn cell_types(txn: &Transaction, container_id: &Id, field_id: u64) -> Result<Option<(Id, Vec<u32>)>, TxnError> {
         if let Some(fields) = txn.read_selected(container_id, &vec![field_id])? {
             if let Some(&Value::Id(id)) = fields.get(0) {
@@ -191,24 +203,28 @@ impl<'a> IdList <'a> {
             }
         }
     }
-    pub fn iter(&mut self) -> Result<Result<IdListIterator, IdListError>, TxnError> {
+    pub fn iter(&mut self) -> Result<Result<IdListIterator<'a>, IdListError>, TxnError> {
         let list_root_id = match self.get_root_list_id(false)? {
             Err(e) => return Ok(Err(e)), Ok(id) => id
         };
-        let mut segments = IdListSegmentIterator::new(self.txn, list_root_id);
-        let first_seg = segments.next();
-        Ok(Ok(IdListIterator {
-            segments: segments,
-            current_seg: first_seg,
-            current_pos: 0,
-        }))
+        Ok(Ok(IdListIterator::from_head(self.txn, list_root_id)))
     }
     pub fn all(&mut self) -> Result<Result<Vec<Id>, IdListError>, TxnError> {
         Ok(self.iter()?.map(|l| l.collect()))
     }
+    pub fn all_reversed(&mut self) -> Result<Result<Vec<Id>, IdListError>, TxnError> {
+        // segments are singly linked, so the list is collected before reversing
+        Ok(self.all()?.map(|mut ids| { ids.reverse(); ids }))
+    }
     pub fn count(&mut self) -> Result<Result<usize, IdListError>, TxnError> {
         Ok(self.iter()?.map(|l| l.count()))
     }
+    pub fn contains(&mut self, target: &Id) -> Result<Result<bool, IdListError>, TxnError> {
+        Ok(self.iter()?.map(|mut l| l.any(|id| id == *target)))
+    }
+    pub fn page(&mut self, offset: usize, limit: usize) -> Result<Result<Vec<Id>, IdListError>, TxnError> {
+        Ok(self.iter()?.map(|l| l.skip(offset).take(limit).collect()))
+    }
     pub fn add(&mut self, id: &Id) -> Result<Result<(), IdListError>, TxnError> {
         let list_root_id = self.get_root_list_id(true)?;
         let mut list_level = 0;
@@ -231,7 +247,7 @@ impl<'a> IdList <'a> {
         };
         if match count_cell_list(&mut last_seg) {
             Ok(c) => c, Err(e) => return Ok(Err(e))
-        } >= *LIST_CAPACITY { // create new segment to prevent cell overflow
+        } >= self.capacity { // create new segment to prevent cell overflow
             list_level += 1;
             let (next_seg_id, next_seg_value) = empty_list_segment(&self.container_id, self.field_id, self.schema_id, list_level);
             let next_seg_cell = Cell::new_with_id(ID_LIST_SCHEMA_ID, &next_seg_id, next_seg_value);
@@ -288,7 +304,7 @@ impl<'a> IdList <'a> {
                     } else {
                         return Ok(Err(IdListError::FormatError));
                     }
-                    self.txn.update(&seg);
+                    self.txn.update(&seg)?;
                     if !all { break; }
                 },
                 None => return Ok(Err(IdListError::Unexpected))
@@ -378,6 +394,16 @@ pub struct IdListIterator<'a> {
 }
 
 impl <'a> IdListIterator <'a> {
+    // walks the segment chain starting at head_id, a unit id gives an empty iterator
+    pub fn from_head(txn: &'a Transaction, head_id: Id) -> IdListIterator<'a> {
+        let mut segments = IdListSegmentIterator::new(txn, head_id);
+        let first_seg = segments.next();
+        IdListIterator {
+            segments: segments,
+            current_seg: first_seg,
+            current_pos: 0,
+        }
+    }
     pub fn next_seg(&mut self) {
         self.current_seg = self.segments.next();
         self.current_pos = 0;
@@ -440,8 +466,9 @@ impl <'a> Iterator for IdListIterator<'a> {
         };
     }
     fn count(self) -> usize where Self: Sized {
+        let consumed = self.current_pos as usize; // ids already yielded from the current segment
         let mut count = self.get_curr_seg_list()
-            .map(|l| l.len())
+            .map(|l| l.len().saturating_sub(consumed))
             .unwrap_or(0);
         for seg in self.segments {
             if let &Value::Map(ref map) = &seg.data {
@@ -453,3 +480,17 @@ impl <'a> Iterator for IdListIterator<'a> {
         return count;
     }
 }
+
+impl fmt::Display for IdListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &IdListError::ContainerCellNotFound => write!(f, "id list container cell not found"),
+            &IdListError::FormatError => write!(f, "id list cell has unexpected format"),
+            &IdListError::Unexpected => write!(f, "unexpected id list state"),
+            &IdListError::RegistryIncomplete(schema_id) =>
+                write!(f, "vertex registry of schema {} may be incomplete, backfill it first", schema_id)
+        }
+    }
+}
+
+impl Error for IdListError {}
@@ -0,0 +1,221 @@
+use neb::ram::schema::Field;
+use neb::ram::cell::Cell;
+use neb::ram::types::{TypeId, Id, Map, Value, key_hash};
+use neb::client::transaction::{Transaction, TxnError};
+
+use super::id_list::{IdListIterator, IdListError, ID_LIST_SCHEMA_ID, LIST_CAPACITY, NEXT_KEY_ID, LIST_KEY_ID};
+
+pub const HEAD_KEY: &'static str = "_head";
+pub const TAIL_KEY: &'static str = "_tail";
+pub const LEVEL_KEY: &'static str = "_level";
+pub const SEGMENT_KEY: &'static str = "_segment";
+
+pub static ID_SET_SCHEMA_ID: u32 = 160;
+pub static ID_SET_MEMBER_SCHEMA_ID: u32 = 170;
+
+lazy_static! {
+    pub static ref ID_SET: Field = Field::new("*", TypeId::Map as u32, false, false, Some(vec![
+        Field::new(&String::from(HEAD_KEY), TypeId::Id as u32, false, false, None),
+        Field::new(&String::from(TAIL_KEY), TypeId::Id as u32, false, false, None),
+        Field::new(&String::from(LEVEL_KEY), TypeId::U64 as u32, false, false, None)
+    ]));
+    pub static ref ID_SET_MEMBER: Field = Field::new("*", TypeId::Map as u32, false, false, Some(vec![
+        Field::new(&String::from(SEGMENT_KEY), TypeId::Id as u32, false, false, None)
+    ]));
+    pub static ref HEAD_KEY_ID: u64 = key_hash(&String::from(HEAD_KEY));
+    pub static ref TAIL_KEY_ID: u64 = key_hash(&String::from(TAIL_KEY));
+    pub static ref LEVEL_KEY_ID: u64 = key_hash(&String::from(LEVEL_KEY));
+    pub static ref SEGMENT_KEY_ID: u64 = key_hash(&String::from(SEGMENT_KEY));
+}
+
+// A set of ids in the segments of an id list, with a tail pointer in the set cell and one
+// member cell per id that records the segment holding it. Adding appends to the tail and
+// removing edits the recorded segment, so neither walks the list. Segments emptied by
+// removals stay in the chain until the set is cleared.
+pub struct IdSet<'a> {
+    pub txn: &'a Transaction,
+    set_id: Id,
+    capacity: usize
+}
+
+fn segment_id(set_id: &Id, level: u64) -> Id {
+    Id::new(set_id.higher, key_hash(&format!("IDSET-{},{}-{}", set_id.higher, set_id.lower, level)))
+}
+
+fn member_id(set_id: &Id, member: &Id) -> Id {
+    let str_id = format!("IDSET-MEMBER-{},{}-{},{}", set_id.higher, set_id.lower, member.higher, member.lower);
+    Id::new(set_id.higher, key_hash(&str_id))
+}
+
+fn empty_segment(id: &Id) -> Cell {
+    let mut map = Map::new();
+    map.insert_key_id(*NEXT_KEY_ID, Value::Id(Id::unit_id()));
+    map.insert_key_id(*LIST_KEY_ID, Value::Array(Vec::<Value>::new()));
+    Cell::new_with_id(ID_LIST_SCHEMA_ID, id, Value::Map(map))
+}
+
+fn segment_list(seg: &mut Cell) -> Result<&mut Vec<Value>, IdListError> {
+    if let &mut Value::Map(ref mut map) = &mut seg.data {
+        if let &mut Value::Array(ref mut array) = map.get_mut_by_key_id(*LIST_KEY_ID) {
+            return Ok(array);
+        }
+    }
+    Err(IdListError::FormatError)
+}
+
+fn set_field(cell: &mut Cell, key_id: u64, value: Value) -> Result<(), IdListError> {
+    if let &mut Value::Map(ref mut map) = &mut cell.data {
+        map.insert_key_id(key_id, value);
+        Ok(())
+    } else {
+        Err(IdListError::FormatError)
+    }
+}
+
+impl <'a> IdSet<'a> {
+    pub fn new(txn: &'a Transaction, set_id: &Id) -> IdSet<'a> {
+        IdSet {
+            txn: txn,
+            set_id: *set_id,
+            capacity: *LIST_CAPACITY
+        }
+    }
+    // ids per segment for new segments, never more than a cell can hold
+    pub fn with_capacity(mut self, capacity: usize) -> IdSet<'a> {
+        self.capacity = capacity.max(1).min(*LIST_CAPACITY);
+        self
+    }
+    pub fn exists(&self) -> Result<bool, TxnError> {
+        Ok(self.txn.read_selected(&self.set_id, &vec![*HEAD_KEY_ID])?.is_some())
+    }
+    pub fn contains(&self, member: &Id) -> Result<bool, TxnError> {
+        Ok(self.txn.read_selected(&member_id(&self.set_id, member), &vec![*SEGMENT_KEY_ID])?.is_some())
+    }
+    // Answers false when the id was already in the set
+    pub fn add(&self, member: &Id) -> Result<Result<bool, IdListError>, TxnError> {
+        if self.contains(member)? {
+            return Ok(Ok(false));
+        }
+        let (mut set_cell, mut tail) = match self.txn.read(&self.set_id)? {
+            Some(set_cell) => {
+                let tail_id = match set_cell.data[*TAIL_KEY_ID] {
+                    Value::Id(id) => id, _ => return Ok(Err(IdListError::FormatError))
+                };
+                match self.txn.read(&tail_id)? {
+                    Some(tail) => (Some(set_cell), tail),
+                    None => return Ok(Err(IdListError::Unexpected))
+                }
+            },
+            None => (None, empty_segment(&segment_id(&self.set_id, 0)))
+        };
+        let tail_len = match segment_list(&mut tail) {
+            Ok(list) => list.len(), Err(e) => return Ok(Err(e))
+        };
+        // the set and its tail are read and checked before anything is written
+        match set_cell {
+            None => {
+                let mut map = Map::new();
+                map.insert_key_id(*HEAD_KEY_ID, Value::Id(tail.id()));
+                map.insert_key_id(*TAIL_KEY_ID, Value::Id(tail.id()));
+                map.insert_key_id(*LEVEL_KEY_ID, Value::U64(0));
+                self.txn.write(&Cell::new_with_id(ID_SET_SCHEMA_ID, &self.set_id, Value::Map(map)))?;
+                self.txn.write(&tail)?;
+            },
+            Some(ref mut set_cell) => if tail_len >= self.capacity {
+                let level = match set_cell.data[*LEVEL_KEY_ID] {
+                    Value::U64(level) => level + 1, _ => return Ok(Err(IdListError::FormatError))
+                };
+                let next = empty_segment(&segment_id(&self.set_id, level));
+                if let Err(e) = set_field(&mut tail, *NEXT_KEY_ID, Value::Id(next.id())) {
+                    return Ok(Err(e));
+                }
+                self.txn.write(&next)?;
+                self.txn.update(&tail)?;
+                if let Err(e) = set_field(set_cell, *TAIL_KEY_ID, Value::Id(next.id())) {
+                    return Ok(Err(e));
+                }
+                if let Err(e) = set_field(set_cell, *LEVEL_KEY_ID, Value::U64(level)) {
+                    return Ok(Err(e));
+                }
+                self.txn.update(set_cell)?;
+                tail = next;
+            }
+        }
+        let tail_id = tail.id();
+        match segment_list(&mut tail) {
+            Ok(list) => list.push(Value::Id(*member)), Err(e) => return Ok(Err(e))
+        }
+        self.txn.update(&tail)?;
+        let mut member_map = Map::new();
+        member_map.insert_key_id(*SEGMENT_KEY_ID, Value::Id(tail_id));
+        self.txn.write(&Cell::new_with_id(ID_SET_MEMBER_SCHEMA_ID, &member_id(&self.set_id, member), Value::Map(member_map)))?;
+        Ok(Ok(true))
+    }
+    // Answers false when the id was not in the set
+    pub fn remove(&self, member: &Id) -> Result<Result<bool, IdListError>, TxnError> {
+        let member_cell_id = member_id(&self.set_id, member);
+        let seg_id = match self.txn.read_selected(&member_cell_id, &vec![*SEGMENT_KEY_ID])? {
+            Some(fields) => match fields.get(0) {
+                Some(&Value::Id(id)) => id, _ => return Ok(Err(IdListError::FormatError))
+            },
+            None => return Ok(Ok(false))
+        };
+        let mut seg = match self.txn.read(&seg_id)? {
+            Some(seg) => seg, None => return Ok(Err(IdListError::Unexpected))
+        };
+        match segment_list(&mut seg) {
+            Ok(list) => match list.iter().position(|v| if let &Value::Id(ref id) = v { id == member } else { false }) {
+                Some(pos) => { list.remove(pos); },
+                None => return Ok(Err(IdListError::Unexpected))
+            },
+            Err(e) => return Ok(Err(e))
+        }
+        self.txn.update(&seg)?;
+        self.txn.remove(&member_cell_id)?;
+        Ok(Ok(true))
+    }
+    pub fn iter(&self) -> Result<Result<IdListIterator<'a>, IdListError>, TxnError> {
+        let head_id = match self.txn.read_selected(&self.set_id, &vec![*HEAD_KEY_ID])? {
+            Some(fields) => match fields.get(0) {
+                Some(&Value::Id(id)) => id, _ => return Ok(Err(IdListError::FormatError))
+            },
+            None => Id::unit_id() // nothing was ever added
+        };
+        Ok(Ok(IdListIterator::from_head(self.txn, head_id)))
+    }
+    pub fn all(&self) -> Result<Result<Vec<Id>, IdListError>, TxnError> {
+        Ok(self.iter()?.map(|ids| ids.collect()))
+    }
+    pub fn count(&self) -> Result<Result<usize, IdListError>, TxnError> {
+        Ok(self.iter()?.map(|ids| ids.count()))
+    }
+    // Removes the set cell, its segments and the member cells of its ids
+    pub fn clear(&self) -> Result<Result<(), IdListError>, TxnError> {
+        let mut seg_id = match self.txn.read_selected(&self.set_id, &vec![*HEAD_KEY_ID])? {
+            Some(fields) => match fields.get(0) {
+                Some(&Value::Id(id)) => id, _ => return Ok(Err(IdListError::FormatError))
+            },
+            None => return Ok(Ok(()))
+        };
+        while !seg_id.is_unit_id() {
+            let mut seg = match self.txn.read(&seg_id)? {
+                Some(seg) => seg, None => return Ok(Err(IdListError::Unexpected))
+            };
+            let next_id = match seg.data[*NEXT_KEY_ID] {
+                Value::Id(id) => id, _ => return Ok(Err(IdListError::FormatError))
+            };
+            let members = match segment_list(&mut seg) {
+                Ok(list) => list.clone(), Err(e) => return Ok(Err(e))
+            };
+            for member in members {
+                if let Value::Id(ref member) = member {
+                    self.txn.remove(&member_id(&self.set_id, member))?;
+                }
+            }
+            self.txn.remove(&seg_id)?;
+            seg_id = next_id;
+        }
+        self.txn.remove(&self.set_id)?;
+        Ok(Ok(()))
+    }
+}
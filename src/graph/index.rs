@@ -0,0 +1,252 @@
+use neb::ram::schema::Field;
+use neb::ram::types::{TypeId, Id, Map, Value, key_hash};
+use neb::ram::cell::Cell;
+use neb::client::transaction::{Transaction, TxnError};
+use server::schema::{SchemaContainer, SchemaError, SchemaType, ToSchemaId};
+use futures::prelude::*;
+
+use super::{Graph, GraphInner, GraphTransaction, ReadVertexError};
+use super::id_list::IdListError;
+use super::id_set::IdSet;
+use super::registry;
+
+use std::sync::Arc;
+use std::error::Error;
+use std::fmt;
+
+pub const INDEXED_KEY: &'static str = "_indexed";
+
+pub static INDEX_ENTRY_SCHEMA_ID: u32 = 210;
+
+// vertices indexed per transaction while an index is built over existing vertices
+const BUILD_BATCH_SIZE: usize = 256;
+
+lazy_static! {
+    pub static ref INDEX_ENTRY: Field = Field::new("*", TypeId::Map as u32, false, false, Some(vec![
+        Field::new(&String::from(INDEXED_KEY), TypeId::Id as u32, false, false, None)
+    ]));
+    pub static ref INDEXED_KEY_ID: u64 = key_hash(&String::from(INDEXED_KEY));
+}
+
+// Every indexed value has an entry, the id set of vertices with that value
+fn entry_id(schema_id: u32, field: u64, value: &Value) -> Id {
+    Cell::encode_cell_key(
+        INDEX_ENTRY_SCHEMA_ID,
+        &Value::Array(vec![Value::U32(schema_id), Value::U64(field), value.clone()])
+    )
+}
+
+// Written once every existing vertex is indexed, lookups are refused until then
+fn built_id(schema_id: u32, field: u64) -> Id {
+    Cell::encode_cell_key(
+        INDEX_ENTRY_SCHEMA_ID,
+        &Value::Array(vec![Value::U32(schema_id), Value::U64(field)])
+    )
+}
+
+#[derive(Debug)]
+pub enum IndexError {
+    SchemaError(SchemaError),
+    IdListError(IdListError),
+    TxnError(TxnError)
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &IndexError::SchemaError(ref e) => write!(f, "schema error: {}", e),
+            &IndexError::IdListError(ref e) => write!(f, "cannot index existing vertices: {}", e),
+            // neb's TxnError only implements Debug
+            &IndexError::TxnError(ref e) => write!(f, "transaction error: {:?}", e)
+        }
+    }
+}
+
+impl Error for IndexError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &IndexError::SchemaError(ref e) => Some(e),
+            &IndexError::IdListError(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+fn entry_existed(txn: &Transaction, entry_id: &Id) -> Result<bool, TxnError> {
+    Ok(txn.read_selected(entry_id, &vec![*INDEXED_KEY_ID])?.is_some())
+}
+
+fn entry_vertices<'a>(txn: &'a Transaction, schema_id: u32, field: u64, value: &Value) -> IdSet<'a> {
+    IdSet::new(txn, &entry_id(schema_id, field, value))
+}
+
+fn add_entry(txn: &Transaction, schema_id: u32, field: u64, value: &Value, vertex_id: &Id)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    if let &Value::Null = value {
+        return Ok(Ok(())); // absent fields are not indexed
+    }
+    Ok(entry_vertices(txn, schema_id, field, value).add(vertex_id)?.map(|_| ()))
+}
+
+fn remove_entry(txn: &Transaction, schema_id: u32, field: u64, value: &Value, vertex_id: &Id)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    Ok(entry_vertices(txn, schema_id, field, value).remove(vertex_id)?.map(|_| ()))
+}
+
+pub fn lookup(txn: &Transaction, schema_id: u32, field: u64, value: &Value)
+    -> Result<Result<Vec<Id>, IdListError>, TxnError>
+{
+    entry_vertices(txn, schema_id, field, value).all()
+}
+
+fn mark_built(txn: &Transaction, schema_id: u32, field: u64) -> Result<(), TxnError> {
+    let built_id = built_id(schema_id, field);
+    if !entry_existed(txn, &built_id)? {
+        let mut data = Map::new();
+        data.insert_key_id(*INDEXED_KEY_ID, Value::Id(Id::unit_id()));
+        txn.write(&Cell::new_with_id(INDEX_ENTRY_SCHEMA_ID, &built_id, Value::Map(data)))?;
+    }
+    Ok(())
+}
+
+pub fn index_field(txn: &Transaction, cell: &Cell, field: u64)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    add_entry(txn, cell.header.schema, field, &cell.data[field], &cell.id())
+}
+
+pub fn index_cell(txn: &Transaction, schemas: &Arc<SchemaContainer>, cell: &Cell)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    for field in schemas.indexed_fields(cell.header.schema) {
+        match index_field(txn, cell, field)? {
+            Ok(()) => {}, Err(e) => return Ok(Err(e))
+        }
+    }
+    Ok(Ok(()))
+}
+
+pub fn unindex_cell(txn: &Transaction, schemas: &Arc<SchemaContainer>, cell: &Cell)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    for field in schemas.indexed_fields(cell.header.schema) {
+        match remove_entry(txn, cell.header.schema, field, &cell.data[field], &cell.id())? {
+            Ok(()) => {}, Err(e) => return Ok(Err(e))
+        }
+    }
+    Ok(Ok(()))
+}
+
+// Moves the vertex between entries only for the fields whose value changed
+pub fn reindex_cell(txn: &Transaction, schemas: &Arc<SchemaContainer>, old: &Cell, new: &Cell)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    for field in schemas.indexed_fields(new.header.schema) {
+        let (old_value, new_value) = (&old.data[field], &new.data[field]);
+        if entry_id(old.header.schema, field, old_value) == entry_id(new.header.schema, field, new_value) {
+            continue; // same entry, the value did not change
+        }
+        match remove_entry(txn, old.header.schema, field, old_value, &old.id())? {
+            Ok(()) => {}, Err(e) => return Ok(Err(e))
+        }
+        match add_entry(txn, new.header.schema, field, new_value, &new.id())? {
+            Ok(()) => {}, Err(e) => return Ok(Err(e))
+        }
+    }
+    Ok(Ok(()))
+}
+
+impl Graph {
+    pub fn create_index<S>(&self, schema: S, field: u64)
+        -> impl Future<Item = (), Error = IndexError> where S: ToSchemaId
+    {
+        GraphInner::create_index(self.inner.clone(), schema, field)
+    }
+    pub fn find_by_index<S>(&self, schema: S, field: u64, value: &Value)
+        -> impl Future<Item = Result<Vec<Id>, ReadVertexError>, Error = TxnError> where S: ToSchemaId
+    {
+        self.inner.find_by_index(schema, field, value)
+    }
+}
+
+impl GraphInner {
+    // The index is registered before existing vertices are indexed, so vertices written
+    // meanwhile are indexed by their own transactions. Existing vertices are indexed in
+    // batches and the index only answers lookups after the last batch committed. When the
+    // build fails the index stays registered but unbuilt, calling again resumes it.
+    pub fn create_index<S>(this: Arc<Self>, schema: S, field: u64)
+        -> impl Future<Item = (), Error = IndexError> where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&this.schemas);
+        async_block! {
+            match this.schemas.schema_type(schema_id) {
+                Some(SchemaType::Vertex) => {},
+                Some(t) => return Err(IndexError::SchemaError(SchemaError::SchemaTypeMismatch(t))),
+                None => return Err(IndexError::SchemaError(SchemaError::SchemaNotFound))
+            }
+            let field_exists = this.schemas.get_neb_schema(schema_id)
+                .and_then(|schema| schema.fields.sub_fields.clone())
+                .map(|fields| fields.iter().any(|f| key_hash(&f.name) == field))
+                .unwrap_or(false);
+            if !field_exists {
+                return Err(IndexError::SchemaError(SchemaError::FieldNotFound(field)));
+            }
+            this.schemas.add_index(schema_id, field).map_err(IndexError::SchemaError)?;
+            let vertex_ids = match await!(this.graph_transaction(move |txn| registry::vertex_ids(txn.neb_txn, schema_id))) {
+                Ok(Ok(ids)) => ids,
+                Ok(Err(e)) => return Err(IndexError::IdListError(e)),
+                Err(e) => return Err(IndexError::TxnError(e))
+            };
+            for batch in vertex_ids.chunks(BUILD_BATCH_SIZE) {
+                let batch = batch.to_vec();
+                match await!(this.graph_transaction(move |txn| txn.index_vertices(field, &batch))) {
+                    Ok(Ok(())) => {},
+                    Ok(Err(e)) => return Err(IndexError::IdListError(e)),
+                    Err(e) => return Err(IndexError::TxnError(e))
+                }
+            }
+            await!(this.graph_transaction(move |txn| mark_built(txn.neb_txn, schema_id, field)))
+                .map_err(IndexError::TxnError)
+        }
+    }
+    pub fn find_by_index<S>(&self, schema: S, field: u64, value: &Value)
+        -> impl Future<Item = Result<Vec<Id>, ReadVertexError>, Error = TxnError> where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        let value = value.clone();
+        self.graph_transaction(move |txn| {
+            txn.find_by_index(schema_id, field, &value)
+        })
+    }
+}
+
+impl <'a> GraphTransaction<'a> {
+    // vertices removed since they were listed are skipped
+    fn index_vertices(&self, field: u64, vertex_ids: &[Id])
+        -> Result<Result<(), IdListError>, TxnError>
+    {
+        for vertex_id in vertex_ids {
+            self.check_deadline()?;
+            if let Some(cell) = self.neb_txn.read(vertex_id)? {
+                match index_field(self.neb_txn, &cell, field)? {
+                    Ok(()) => {}, Err(e) => return Ok(Err(e))
+                }
+            }
+        }
+        Ok(Ok(()))
+    }
+    pub fn find_by_index<S>(&self, schema: S, field: u64, value: &Value)
+        -> Result<Result<Vec<Id>, ReadVertexError>, TxnError> where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        if !self.schemas.indexed_fields(schema_id).contains(&field) {
+            return Ok(Err(ReadVertexError::FieldNotIndexed(field)));
+        }
+        if !entry_existed(self.neb_txn, &built_id(schema_id, field))? {
+            return Ok(Err(ReadVertexError::IndexNotBuilt(field)));
+        }
+        Ok(lookup(self.neb_txn, schema_id, field, value)?.map_err(ReadVertexError::IdListError))
+    }
+}
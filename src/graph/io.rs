@@ -0,0 +1,321 @@
+use neb::ram::types::{Id, Map, TypeId, key_hash};
+use neb::ram::cell::Cell;
+use neb::client::transaction::TxnError;
+use futures::prelude::*;
+use csv;
+
+use server::schema::ToSchemaId;
+use super::{Graph, GraphInner, GraphTransaction, EdgeDirection, NewVertexError, LinkVerticesError, edge_attr_from_schema};
+use super::registry;
+use super::edge::{EdgeType, EdgeError};
+use utils::value;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::io::Read;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum ImportError {
+    CsvError(csv::Error),
+    ColumnNotFound(String),
+    InvalidValue { line: u64, column: String },
+    RowError { line: u64, error: NewVertexError },
+    LinkError { line: u64, error: LinkVerticesError },
+    ColumnOutOfRange { line: u64, column: usize },
+    TxnError(TxnError)
+}
+
+#[derive(Debug)]
+pub struct EdgeImportReport {
+    pub linked: usize,
+    pub missing_vertices: Vec<(u64, String)> // line and key of vertices that do not exist
+}
+
+impl Graph {
+    pub fn export_dot<S>(&self, vertices: &[Id], edge_schema: S)
+        -> impl Future<Item = Result<String, EdgeError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        self.inner.export_dot(vertices, edge_schema)
+    }
+    pub fn export_csr<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> impl Future<Item = Result<(Vec<Id>, Vec<usize>, Vec<u32>), EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        self.inner.export_csr(vertex_schema, edge_schema)
+    }
+    pub fn import_vertices_csv<S, R>(&self, schema: S, reader: R, field_map: &[(String, u64)], batch_size: usize)
+        -> impl Future<Item = usize, Error = ImportError>
+        where S: ToSchemaId, R: Read
+    {
+        GraphInner::import_vertices_csv(self.inner.clone(), schema, reader, field_map, batch_size)
+    }
+    pub fn import_edges_csv<S, KS, R>(&self, schema: S, reader: R, from_col: usize, to_col: usize, key_schema: KS, batch_size: usize)
+        -> impl Future<Item = EdgeImportReport, Error = ImportError>
+        where S: ToSchemaId, KS: ToSchemaId, R: Read
+    {
+        GraphInner::import_edges_csv(self.inner.clone(), schema, reader, from_col, to_col, key_schema, batch_size)
+    }
+}
+
+impl GraphInner {
+    pub fn export_dot<S>(&self, vertices: &[Id], edge_schema: S)
+        -> impl Future<Item = Result<String, EdgeError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        let vertices = vertices.to_vec();
+        let schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.export_dot(&vertices, schema_id)
+        })
+    }
+    pub fn export_csr<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> impl Future<Item = Result<(Vec<Id>, Vec<usize>, Vec<u32>), EdgeError>, Error = TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.export_csr(vertex_schema_id, edge_schema_id)
+        })
+    }
+    // Rows are parsed up front, then written in transactions of `batch_size` rows.
+    // A failing row aborts its whole batch; batches before it stay committed.
+    pub fn import_vertices_csv<S, R>(this: Arc<Self>, schema: S, reader: R, field_map: &[(String, u64)], batch_size: usize)
+        -> impl Future<Item = usize, Error = ImportError>
+        where S: ToSchemaId, R: Read
+    {
+        let schema_id = schema.to_id(&this.schemas);
+        let rows = this.parse_csv_rows(schema_id, reader, field_map);
+        let batch_size = batch_size.max(1);
+        async_block! {
+            let rows = rows?;
+            let mut created = 0;
+            for batch in rows.chunks(batch_size) {
+                let batch = batch.to_vec();
+                let failed_row = Rc::new(RefCell::new(None));
+                let failed_row_ref = failed_row.clone();
+                let written = await!(this.graph_transaction(move |txn| {
+                    for &(line, ref data) in &batch {
+                        if let Err(e) = txn.new_vertex(schema_id, data.clone())? {
+                            *failed_row_ref.borrow_mut() = Some((line, e));
+                            return Err(TxnError::Aborted(None));
+                        }
+                    }
+                    Ok(batch.len())
+                }));
+                match written {
+                    Ok(count) => created += count,
+                    Err(e) => return Err(match failed_row.borrow_mut().take() {
+                        Some((line, error)) => ImportError::RowError { line, error },
+                        None => ImportError::TxnError(e)
+                    })
+                }
+            }
+            Ok(created)
+        }
+    }
+    // Endpoints are looked up by key in `key_schema`; rows naming missing vertices are reported, not linked.
+    pub fn import_edges_csv<S, KS, R>(this: Arc<Self>, schema: S, reader: R, from_col: usize, to_col: usize, key_schema: KS, batch_size: usize)
+        -> impl Future<Item = EdgeImportReport, Error = ImportError>
+        where S: ToSchemaId, KS: ToSchemaId, R: Read
+    {
+        let schema_id = schema.to_id(&this.schemas);
+        let key_schema_id = key_schema.to_id(&this.schemas);
+        let rows = this.parse_csv_edge_rows(key_schema_id, reader, from_col, to_col);
+        let batch_size = batch_size.max(1);
+        async_block! {
+            let rows = rows?;
+            let mut report = EdgeImportReport { linked: 0, missing_vertices: Vec::new() };
+            for batch in rows.chunks(batch_size) {
+                let batch = batch.to_vec();
+                let failed_row = Rc::new(RefCell::new(None));
+                let failed_row_ref = failed_row.clone();
+                let written = await!(this.graph_transaction(move |txn| {
+                    let mut linked = 0;
+                    let mut missing = Vec::new();
+                    for &(line, ref from_key, from_id, ref to_key, to_id) in &batch {
+                        let from_exists = txn.vertex_exists(&from_id)?;
+                        let to_exists = txn.vertex_exists(&to_id)?;
+                        if !from_exists { missing.push((line, from_key.clone())); }
+                        if !to_exists { missing.push((line, to_key.clone())); }
+                        if !from_exists || !to_exists { continue; }
+                        if let Err(e) = txn.link(&from_id, schema_id, &to_id, None)? {
+                            *failed_row_ref.borrow_mut() = Some((line, e));
+                            return Err(TxnError::Aborted(None));
+                        }
+                        linked += 1;
+                    }
+                    Ok((linked, missing))
+                }));
+                match written {
+                    Ok((linked, mut missing)) => {
+                        report.linked += linked;
+                        report.missing_vertices.append(&mut missing);
+                    },
+                    Err(e) => return Err(match failed_row.borrow_mut().take() {
+                        Some((line, error)) => ImportError::LinkError { line, error },
+                        None => ImportError::TxnError(e)
+                    })
+                }
+            }
+            Ok(report)
+        }
+    }
+    fn parse_csv_edge_rows<R>(&self, key_schema_id: u32, reader: R, from_col: usize, to_col: usize)
+        -> Result<Vec<(u64, String, Id, String, Id)>, ImportError> where R: Read
+    {
+        let key_type = self.schemas.get_neb_schema(key_schema_id)
+            .and_then(|schema| {
+                let key_name = match schema.str_key_field {
+                    Some(ref path) => path.first().cloned(),
+                    None => None
+                };
+                match (key_name, schema.fields.sub_fields.as_ref()) {
+                    (Some(name), Some(fields)) => fields.iter()
+                        .find(|field| field.name == name)
+                        .map(|field| field.type_id),
+                    _ => None
+                }
+            })
+            .unwrap_or(TypeId::String as u32);
+        let mut reader = csv::Reader::from_reader(reader);
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(ImportError::CsvError)?;
+            let line = record.position().map(|pos| pos.line()).unwrap_or(0);
+            let endpoint = |column: usize| -> Result<(String, Id), ImportError> {
+                let text = record.get(column)
+                    .ok_or(ImportError::ColumnOutOfRange { line, column })?;
+                let key = value::parse_typed(text, key_type)
+                    .ok_or(ImportError::InvalidValue { line, column: column.to_string() })?;
+                Ok((text.to_string(), Cell::encode_cell_key(key_schema_id, &key)))
+            };
+            let (from_key, from_id) = endpoint(from_col)?;
+            let (to_key, to_id) = endpoint(to_col)?;
+            rows.push((line, from_key, from_id, to_key, to_id));
+        }
+        Ok(rows)
+    }
+    fn parse_csv_rows<R>(&self, schema_id: u32, reader: R, field_map: &[(String, u64)])
+        -> Result<Vec<(u64, Map)>, ImportError> where R: Read
+    {
+        let field_types: Vec<Option<u32>> = {
+            let neb_schema = self.schemas.get_neb_schema(schema_id);
+            let schema_fields = neb_schema.as_ref().and_then(|schema| schema.fields.sub_fields.as_ref());
+            field_map.iter().map(|&(_, field_id)| {
+                schema_fields.and_then(|fields| {
+                    fields.iter()
+                        .find(|field| key_hash(&field.name) == field_id)
+                        .map(|field| field.type_id)
+                })
+            }).collect()
+        };
+        let mut reader = csv::Reader::from_reader(reader);
+        let columns = {
+            let headers = reader.headers().map_err(ImportError::CsvError)?;
+            let mut columns = Vec::with_capacity(field_map.len());
+            for &(ref column, _) in field_map {
+                match headers.iter().position(|header| header == column) {
+                    Some(index) => columns.push(index),
+                    None => return Err(ImportError::ColumnNotFound(column.clone()))
+                }
+            }
+            columns
+        };
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(ImportError::CsvError)?;
+            let line = record.position().map(|pos| pos.line()).unwrap_or(0);
+            let mut data = Map::new();
+            for (i, &(ref column, field_id)) in field_map.iter().enumerate() {
+                let text = record.get(columns[i]).unwrap_or("");
+                let parsed = match field_types[i] {
+                    Some(type_id) => value::parse_typed(text, type_id),
+                    None => value::parse_typed(text, TypeId::String as u32) // dynamic fields are kept as text
+                };
+                match parsed {
+                    Some(v) => { data.insert_key_id(field_id, v); },
+                    None => return Err(ImportError::InvalidValue { line, column: column.clone() })
+                }
+            }
+            rows.push((line, data));
+        }
+        Ok(rows)
+    }
+}
+
+fn dot_node(id: &Id) -> String {
+    format!("\"{}-{}\"", id.higher, id.lower)
+}
+
+impl <'a> GraphTransaction<'a> {
+    pub fn export_dot<S>(&self, vertices: &[Id], edge_schema: S)
+        -> Result<Result<String, EdgeError>, TxnError>
+        where S: ToSchemaId
+    {
+        let (schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let (graph_kind, arrow, ed) = match edge_attr.edge_type {
+            EdgeType::Directed => ("digraph", "->", EdgeDirection::Outbound),
+            EdgeType::Undirected => ("graph", "--", EdgeDirection::Undirected)
+        };
+        let members: BTreeSet<Id> = vertices.iter().cloned().collect();
+        let mut dot = format!("{} {{\n", graph_kind);
+        for vertex_id in &members {
+            dot.push_str(&format!("    {};\n", dot_node(vertex_id)));
+        }
+        for vertex_id in &members {
+            let neighbours = match self.adjacent_ids(vertex_id, schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            for neighbour_id in neighbours {
+                if !members.contains(&neighbour_id) { continue; }
+                // undirected edges are listed on both endpoints, emit them once
+                if edge_attr.edge_type == EdgeType::Undirected && neighbour_id < *vertex_id { continue; }
+                dot.push_str(&format!("    {} {} {};\n", dot_node(vertex_id), arrow, dot_node(&neighbour_id)));
+            }
+        }
+        dot.push_str("}\n");
+        Ok(Ok(dot))
+    }
+    // Vertices are sorted by id so the column indices stay meaningful across exports.
+    // Undirected edges appear in the rows of both endpoints, edges leaving the schema are dropped.
+    pub fn export_csr<VS, ES>(&self, vertex_schema: VS, edge_schema: ES)
+        -> Result<Result<(Vec<Id>, Vec<usize>, Vec<u32>), EdgeError>, TxnError>
+        where VS: ToSchemaId, ES: ToSchemaId
+    {
+        let vertex_schema_id = vertex_schema.to_id(&self.schemas);
+        let (edge_schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut vertex_ids = match registry::vertex_ids(self.neb_txn, vertex_schema_id)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        vertex_ids.sort();
+        vertex_ids.dedup();
+        let indices: BTreeMap<Id, u32> = vertex_ids.iter().enumerate()
+            .map(|(index, id)| (*id, index as u32))
+            .collect();
+        let ed = match edge_attr.edge_type {
+            EdgeType::Directed => EdgeDirection::Outbound,
+            EdgeType::Undirected => EdgeDirection::Undirected
+        };
+        let mut offsets = Vec::with_capacity(vertex_ids.len() + 1);
+        let mut columns = Vec::new();
+        offsets.push(0);
+        for vertex_id in &vertex_ids {
+            let neighbours = match self.adjacent_ids(vertex_id, edge_schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            let mut row: Vec<u32> = neighbours.iter().filter_map(|id| indices.get(id).cloned()).collect();
+            row.sort();
+            columns.append(&mut row);
+            offsets.push(columns.len());
+        }
+        Ok(Ok((vertex_ids, offsets, columns)))
+    }
+}
@@ -0,0 +1,170 @@
+use neb::ram::types::{Id, Value};
+use neb::ram::cell::{Cell, ReadError};
+use neb::client::transaction::{Transaction, TxnError};
+use server::schema::ToSchemaId;
+use futures::prelude::*;
+
+use super::{Graph, GraphInner, GraphTransaction, ReadVertexError};
+use super::vertex::ToVertexId;
+use super::id_list::IdListError;
+use super::id_set::IdSet;
+
+// label ids are encoded under this schema, their sets are id set cells
+pub static LABEL_SCHEMA_ID: u32 = 220;
+
+// Every label has an id set of labelled vertices per vertex schema.
+// Every labelled vertex has an id set of its labels, so its labels are cleared
+// when it is removed and carried over when it is merged or rekeyed.
+fn label_id(label: &str) -> Id {
+    Cell::encode_cell_key(LABEL_SCHEMA_ID, &Value::String(label.to_string()))
+}
+
+fn labelled_id(label_id: &Id, schema_id: u32) -> Id {
+    Cell::encode_cell_key(LABEL_SCHEMA_ID, &Value::Array(vec![Value::Id(*label_id), Value::U32(schema_id)]))
+}
+
+fn vertex_labels_id(vertex_id: &Id) -> Id {
+    Cell::encode_cell_key(LABEL_SCHEMA_ID, &Value::Id(*vertex_id))
+}
+
+fn labelled_vertices<'a>(txn: &'a Transaction, label_id: &Id, schema_id: u32) -> IdSet<'a> {
+    IdSet::new(txn, &labelled_id(label_id, schema_id))
+}
+
+fn vertex_labels<'a>(txn: &'a Transaction, vertex_id: &Id) -> IdSet<'a> {
+    IdSet::new(txn, &vertex_labels_id(vertex_id))
+}
+
+fn label_vertex(txn: &Transaction, label_id: &Id, vertex_id: &Id, schema_id: u32)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    match labelled_vertices(txn, label_id, schema_id).add(vertex_id)? {
+        Ok(_) => {}, Err(e) => return Ok(Err(e))
+    }
+    Ok(vertex_labels(txn, vertex_id).add(label_id)?.map(|_| ()))
+}
+
+fn unlabel_vertex(txn: &Transaction, label_id: &Id, vertex_id: &Id, schema_id: u32)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    match labelled_vertices(txn, label_id, schema_id).remove(vertex_id)? {
+        Ok(_) => {}, Err(e) => return Ok(Err(e))
+    }
+    Ok(vertex_labels(txn, vertex_id).remove(label_id)?.map(|_| ()))
+}
+
+// For vertices being removed, takes the vertex off every label and drops its label set
+pub fn clear_labels(txn: &Transaction, vertex_id: &Id, schema_id: u32)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    let labels = vertex_labels(txn, vertex_id);
+    let label_ids = match labels.all()? {
+        Ok(ids) => ids, Err(e) => return Ok(Err(e))
+    };
+    for label_id in &label_ids {
+        match labelled_vertices(txn, label_id, schema_id).remove(vertex_id)? {
+            Ok(_) => {}, Err(e) => return Ok(Err(e))
+        }
+    }
+    labels.clear()
+}
+
+// Puts to under every label of from, for vertices merged or rekeyed into another one
+pub fn copy_labels(txn: &Transaction, from: &Id, to: &Id, to_schema_id: u32)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    let label_ids = match vertex_labels(txn, from).all()? {
+        Ok(ids) => ids, Err(e) => return Ok(Err(e))
+    };
+    for label_id in &label_ids {
+        match label_vertex(txn, label_id, to, to_schema_id)? {
+            Ok(()) => {}, Err(e) => return Ok(Err(e))
+        }
+    }
+    Ok(Ok(()))
+}
+
+fn vertex_schema(txn: &Transaction, vertex_id: &Id) -> Result<Result<u32, ReadVertexError>, TxnError> {
+    match txn.read(vertex_id)? {
+        Some(cell) => Ok(Ok(cell.header.schema)),
+        None => Ok(Err(ReadVertexError::ReadError(ReadError::CellDoesNotExisted)))
+    }
+}
+
+impl Graph {
+    pub fn add_label<V>(&self, vertex: V, label: &str)
+        -> impl Future<Item = Result<(), ReadVertexError>, Error = TxnError> where V: ToVertexId
+    {
+        self.inner.add_label(vertex, label)
+    }
+    pub fn remove_label<V>(&self, vertex: V, label: &str)
+        -> impl Future<Item = Result<(), ReadVertexError>, Error = TxnError> where V: ToVertexId
+    {
+        self.inner.remove_label(vertex, label)
+    }
+    pub fn find_by_label<S>(&self, schema: S, label: &str)
+        -> impl Future<Item = Result<Vec<Id>, ReadVertexError>, Error = TxnError> where S: ToSchemaId
+    {
+        self.inner.find_by_label(schema, label)
+    }
+}
+
+impl GraphInner {
+    pub fn add_label<V>(&self, vertex: V, label: &str)
+        -> impl Future<Item = Result<(), ReadVertexError>, Error = TxnError> where V: ToVertexId
+    {
+        let vertex_id = vertex.to_id();
+        let label = label.to_string();
+        self.graph_transaction(move |txn| {
+            txn.add_label(&vertex_id, &label)
+        })
+    }
+    pub fn remove_label<V>(&self, vertex: V, label: &str)
+        -> impl Future<Item = Result<(), ReadVertexError>, Error = TxnError> where V: ToVertexId
+    {
+        let vertex_id = vertex.to_id();
+        let label = label.to_string();
+        self.graph_transaction(move |txn| {
+            txn.remove_label(&vertex_id, &label)
+        })
+    }
+    pub fn find_by_label<S>(&self, schema: S, label: &str)
+        -> impl Future<Item = Result<Vec<Id>, ReadVertexError>, Error = TxnError> where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        let label = label.to_string();
+        self.graph_transaction(move |txn| {
+            txn.find_by_label(schema_id, &label)
+        })
+    }
+}
+
+impl <'a> GraphTransaction<'a> {
+    pub fn add_label<V>(&self, vertex: V, label: &str)
+        -> Result<Result<(), ReadVertexError>, TxnError> where V: ToVertexId
+    {
+        let vertex_id = vertex.to_id();
+        let schema_id = match vertex_schema(self.neb_txn, &vertex_id)? {
+            Ok(id) => id, Err(e) => return Ok(Err(e))
+        };
+        Ok(label_vertex(self.neb_txn, &label_id(label), &vertex_id, schema_id)?
+            .map_err(ReadVertexError::IdListError))
+    }
+    pub fn remove_label<V>(&self, vertex: V, label: &str)
+        -> Result<Result<(), ReadVertexError>, TxnError> where V: ToVertexId
+    {
+        let vertex_id = vertex.to_id();
+        let schema_id = match vertex_schema(self.neb_txn, &vertex_id)? {
+            Ok(id) => id, Err(e) => return Ok(Err(e))
+        };
+        Ok(unlabel_vertex(self.neb_txn, &label_id(label), &vertex_id, schema_id)?
+            .map_err(ReadVertexError::IdListError))
+    }
+    pub fn find_by_label<S>(&self, schema: S, label: &str)
+        -> Result<Result<Vec<Id>, ReadVertexError>, TxnError> where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        Ok(labelled_vertices(self.neb_txn, &label_id(label), schema_id).all()?
+            .map_err(ReadVertexError::IdListError))
+    }
+}
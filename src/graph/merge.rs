@@ -0,0 +1,276 @@
+use neb::ram::types::Id;
+use neb::ram::cell::Cell;
+use neb::dovahkiin::types::{Map, Value};
+use neb::client::transaction::TxnError;
+use futures::prelude::*;
+
+use server::schema::ToSchemaId;
+use super::{Graph, GraphInner, GraphTransaction, EdgeDirection, NewVertexError, LinkVerticesError};
+use super::{edge_attr_from_schema, vertex_to_cell_for_write};
+use super::edge::{EdgeType, EdgeError};
+use super::edge::undirectd::{EDGE_VERTEX_A_ID, EDGE_VERTEX_B_ID};
+use super::fields::{INBOUND_KEY_ID, OUTBOUND_KEY_ID, UNDIRECTED_KEY_ID, ALL_DIRECTIONS};
+use super::id_list::{IdList, IdListError};
+use super::labels;
+use super::vertex::{self, Vertex, ToVertexId, RemoveError};
+use serde::Serialize;
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum MergeError {
+    VertexNotFound(Id),
+    KeyTaken(Id),
+    SchemaMismatch,
+    NewVertexError(NewVertexError),
+    EdgeError(EdgeError),
+    LinkError(LinkVerticesError),
+    IdListError(IdListError),
+    RemoveError(RemoveError)
+}
+
+impl Graph {
+    pub fn merge_vertices<V>(&self, keep: V, drop: V)
+        -> impl Future<Item = Result<(), MergeError>, Error = TxnError>
+        where V: ToVertexId
+    {
+        self.inner.merge_vertices(keep, drop)
+    }
+    pub fn rekey_vertex<V, S, K>(&self, vertex: V, new_schema: S, new_key: &K)
+        -> impl Future<Item = Result<Id, MergeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId, K: Serialize
+    {
+        self.inner.rekey_vertex(vertex, new_schema, new_key)
+    }
+}
+
+impl GraphInner {
+    pub fn merge_vertices<V>(&self, keep: V, drop: V)
+        -> impl Future<Item = Result<(), MergeError>, Error = TxnError>
+        where V: ToVertexId
+    {
+        let keep_id = keep.to_id();
+        let drop_id = drop.to_id();
+        self.graph_transaction(move |txn| {
+            txn.merge_vertices(keep_id, drop_id)
+        })
+    }
+    pub fn rekey_vertex<V, S, K>(&self, vertex: V, new_schema: S, new_key: &K)
+        -> impl Future<Item = Result<Id, MergeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId, K: Serialize
+    {
+        let old_id = vertex.to_id();
+        let new_schema_id = new_schema.to_id(&self.schemas);
+        let new_id = Cell::encode_cell_key(new_schema_id, new_key);
+        self.graph_transaction(move |txn| {
+            txn.move_vertex(old_id, new_schema_id, new_id)
+        })
+    }
+}
+
+impl <'a> GraphTransaction<'a> {
+    // Edges of every schema drop has are moved to keep. When keep already has the same edge
+    // the two bodies are merged like the vertices, edges between the two vertices are
+    // discarded instead of becoming self-loops.
+    // Fields only drop has a value for are copied onto keep, then drop is removed.
+    pub fn merge_vertices<V>(&self, keep: V, drop: V)
+        -> Result<Result<(), MergeError>, TxnError>
+        where V: ToVertexId
+    {
+        let keep_id = keep.to_id();
+        let drop_id = drop.to_id();
+        if keep_id == drop_id {
+            return Ok(Ok(()));
+        }
+        let drop_cell = match self.neb_txn.read(&drop_id)? {
+            Some(cell) => cell, None => return Ok(Err(MergeError::VertexNotFound(drop_id)))
+        };
+        match self.neb_txn.read(&keep_id)? {
+            Some(ref cell) if cell.header.schema != drop_cell.header.schema =>
+                return Ok(Err(MergeError::SchemaMismatch)),
+            Some(_) => {},
+            None => return Ok(Err(MergeError::VertexNotFound(keep_id)))
+        }
+        for schema_id in self.edge_schemas_of(&drop_id)? {
+            match self.repoint_edges(&keep_id, &drop_id, schema_id)? {
+                Ok(()) => {}, Err(e) => return Ok(Err(e))
+            }
+        }
+        // relinking may have created id lists on keep, so its cell is read again
+        let mut keep_cell = match self.neb_txn.read(&keep_id)? {
+            Some(cell) => cell, None => return Ok(Err(MergeError::VertexNotFound(keep_id)))
+        };
+        if let (&mut Value::Map(ref mut keep_map), &Value::Map(ref drop_map)) = (&mut keep_cell.data, &drop_cell.data) {
+            fill_missing(keep_map, drop_map, &[*INBOUND_KEY_ID, *OUTBOUND_KEY_ID, *UNDIRECTED_KEY_ID]);
+        }
+        match vertex::txn_update_cell(self.neb_txn, &self.schemas, &keep_cell)? {
+            Ok(()) => {}, Err(e) => return Ok(Err(MergeError::IdListError(e)))
+        }
+        match labels::copy_labels(self.neb_txn, &drop_id, &keep_id, keep_cell.header.schema)? {
+            Ok(()) => {}, Err(e) => return Ok(Err(MergeError::IdListError(e)))
+        }
+        Ok(self.remove_vertex(&drop_id)?.map_err(MergeError::RemoveError))
+    }
+    // The data is copied as is, a key field stored in it is not rewritten to match the new key.
+    // Edges of every schema in the vertex's edge lists are moved to the new id.
+    pub fn rekey_vertex<V, S, K>(&self, vertex: V, new_schema: S, new_key: &K)
+        -> Result<Result<Id, MergeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId, K: Serialize
+    {
+        let new_schema_id = new_schema.to_id(&self.schemas);
+        let new_id = Cell::encode_cell_key(new_schema_id, new_key);
+        self.move_vertex(vertex.to_id(), new_schema_id, new_id)
+    }
+    fn move_vertex(&self, old_id: Id, new_schema_id: u32, new_id: Id)
+        -> Result<Result<Id, MergeError>, TxnError>
+    {
+        let old_cell = match self.neb_txn.read(&old_id)? {
+            Some(cell) => cell, None => return Ok(Err(MergeError::VertexNotFound(old_id)))
+        };
+        if new_id == old_id {
+            return Ok(Ok(old_id));
+        }
+        if self.neb_txn.read(&new_id)?.is_some() {
+            return Ok(Err(MergeError::KeyTaken(new_id)));
+        }
+        let mut data = match old_cell.data {
+            Value::Map(map) => map,
+            _ => return Ok(Err(MergeError::NewVertexError(NewVertexError::DataNotMap)))
+        };
+        // edge lists are reset for the new schema, the edges are moved over below
+        for ed in ALL_DIRECTIONS.iter() {
+            data.map.remove(&ed.as_field());
+        }
+        let cell = match vertex_to_cell_for_write(&self.schemas, Vertex::new(new_schema_id, data)) {
+            Ok(cell) => Cell::new_with_id(new_schema_id, &new_id, cell.data),
+            Err(e) => return Ok(Err(MergeError::NewVertexError(e)))
+        };
+        match self.write_new_vertex_cell(cell)? {
+            Ok(_) => {}, Err(e) => return Ok(Err(MergeError::NewVertexError(e)))
+        }
+        for schema_id in self.edge_schemas_of(&old_id)? {
+            match self.repoint_edges(&new_id, &old_id, schema_id)? {
+                Ok(()) => {}, Err(e) => return Ok(Err(e))
+            }
+        }
+        match labels::copy_labels(self.neb_txn, &old_id, &new_id, new_schema_id)? {
+            Ok(()) => {}, Err(e) => return Ok(Err(MergeError::IdListError(e)))
+        }
+        Ok(self.remove_vertex(&old_id)?.map(|()| new_id).map_err(MergeError::RemoveError))
+    }
+    fn repoint_edges(&self, keep_id: &Id, drop_id: &Id, schema_id: u32)
+        -> Result<Result<(), MergeError>, TxnError>
+    {
+        let edge_attr = match edge_attr_from_schema(schema_id, &self.schemas) {
+            Ok((_, edge_attr)) => edge_attr, Err(e) => return Ok(Err(MergeError::EdgeError(e)))
+        };
+        let directions = match edge_attr.edge_type {
+            EdgeType::Directed => vec![EdgeDirection::Outbound, EdgeDirection::Inbound],
+            EdgeType::Undirected => vec![EdgeDirection::Undirected]
+        };
+        for ed in directions {
+            // collect first, removing edges shrinks the list being walked
+            let edges = match self.neighbourhood_iter(drop_id, schema_id, ed)? {
+                Ok(iter) => iter.collect::<Vec<_>>(), Err(e) => return Ok(Err(MergeError::EdgeError(e)))
+            };
+            for edge in edges {
+                let edge = match edge? {
+                    Ok(edge) => edge, Err(e) => return Ok(Err(MergeError::EdgeError(e)))
+                };
+                let opposite_id = match edge.one_opposite_id_vertex_id(drop_id) {
+                    Some(id) => *id,
+                    None => return Ok(Err(MergeError::EdgeError(EdgeError::WrongVertexField)))
+                };
+                // the endpoints stored in the body are set again by the new link
+                let body = edge.get_data().as_ref().and_then(|cell| match &cell.data {
+                    &Value::Map(ref map) => {
+                        let mut map = map.clone();
+                        for key_id in &[*INBOUND_KEY_ID, *OUTBOUND_KEY_ID, *EDGE_VERTEX_A_ID, *EDGE_VERTEX_B_ID] {
+                            map.map.remove(key_id);
+                        }
+                        Some(map)
+                    },
+                    _ => None
+                });
+                match edge.remove(self.neb_txn)? {
+                    Ok(()) => {}, Err(e) => return Ok(Err(MergeError::EdgeError(e)))
+                }
+                if opposite_id == *keep_id { continue; }
+                // self-loops of drop become self-loops of keep
+                let opposite_id = if opposite_id == *drop_id { *keep_id } else { opposite_id };
+                let (from_id, to_id) = match ed {
+                    EdgeDirection::Inbound => (opposite_id, *keep_id),
+                    _ => (*keep_id, opposite_id)
+                };
+                let existing = match self.find_edge(&from_id, schema_id, &to_id)? {
+                    Ok(existing) => existing, Err(e) => return Ok(Err(MergeError::EdgeError(e)))
+                };
+                match (existing, body) {
+                    (None, body) => match self.link(&from_id, schema_id, &to_id, body)? {
+                        Ok(_) => {}, Err(e) => return Ok(Err(MergeError::LinkError(e)))
+                    },
+                    (Some(existing), Some(body)) => {
+                        // the edge keep already has wins, drop's body only fills its missing fields
+                        if let Some(mut cell) = existing.into_data() {
+                            if let Value::Map(ref mut keep_body) = cell.data {
+                                fill_missing(keep_body, &body, &[]);
+                            }
+                            self.neb_txn.update(&cell)?;
+                        }
+                    },
+                    (Some(_), None) => {}
+                }
+            }
+        }
+        Ok(Ok(()))
+    }
+    // schemas of the edges in any of the vertex's edge lists
+    fn edge_schemas_of(&self, id: &Id) -> Result<Vec<u32>, TxnError> {
+        let mut schema_ids = BTreeSet::new();
+        for ed in ALL_DIRECTIONS.iter() {
+            if let Some((_, types)) = IdList::cell_types(self.neb_txn, id, ed.as_field())? {
+                schema_ids.extend(types);
+            }
+        }
+        Ok(schema_ids.into_iter().collect())
+    }
+}
+
+fn fill_missing(target: &mut Map, source: &Map, skipped: &[u64]) {
+    for (key_id, value) in source.map.iter() {
+        if skipped.contains(key_id) { continue; }
+        if let &Value::Null = target.get_by_key_id(*key_id) {
+            target.insert_key_id(*key_id, value.clone());
+        }
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &MergeError::VertexNotFound(ref id) => write!(f, "vertex {:?} not found", id),
+            &MergeError::KeyTaken(ref id) => write!(f, "vertex {:?} already exists", id),
+            &MergeError::SchemaMismatch => write!(f, "merged vertices belong to different schemas"),
+            &MergeError::NewVertexError(ref e) => write!(f, "cannot write vertex: {}", e),
+            &MergeError::EdgeError(ref e) => write!(f, "edge error: {}", e),
+            &MergeError::LinkError(ref e) => write!(f, "link error: {}", e),
+            &MergeError::IdListError(ref e) => write!(f, "id list error: {}", e),
+            &MergeError::RemoveError(ref e) => write!(f, "remove error: {}", e)
+        }
+    }
+}
+
+impl Error for MergeError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &MergeError::NewVertexError(ref e) => Some(e),
+            &MergeError::EdgeError(ref e) => Some(e),
+            &MergeError::LinkError(ref e) => Some(e),
+            &MergeError::IdListError(ref e) => Some(e),
+            &MergeError::RemoveError(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use neb::ram::schema::Field;
+use neb::ram::types::{TypeId, Id, Map, Value, key_hash};
+use neb::ram::cell::Cell;
+use neb::client::transaction::TxnError;
+use futures::prelude::*;
+
+use super::{Graph, GraphInner, GraphTransaction, ReadVertexError};
+
+use std::error::Error;
+use std::fmt;
+
+pub static META_SCHEMA_ID: u32 = 230;
+
+lazy_static! {
+    // no declared fields, the schema is dynamic
+    pub static ref META: Field = Field::new("*", TypeId::Map as u32, false, false, Some(vec![]));
+    static ref META_CELL_ID: Id = Cell::encode_cell_key(META_SCHEMA_ID, &Value::String(String::from("graph")));
+}
+
+#[derive(Debug)]
+pub enum MetaError {
+    FormatError
+}
+
+// All metadata lives in one cell of a dynamic schema, each value under the key id of its
+// key, so values of any type, maps and arrays included, come back as they were set.
+pub fn empty_meta_cell() -> Cell {
+    Cell::new_with_id(META_SCHEMA_ID, &*META_CELL_ID, Value::Map(Map::new()))
+}
+
+impl Graph {
+    // Setting a key to Null removes it
+    pub fn set_meta(&self, key: &str, value: Value)
+        -> impl Future<Item = Result<(), MetaError>, Error = TxnError>
+    {
+        self.inner.set_meta(key, value)
+    }
+    pub fn get_meta(&self, key: &str)
+        -> impl Future<Item = Result<Option<Value>, ReadVertexError>, Error = TxnError>
+    {
+        self.inner.get_meta(key)
+    }
+}
+
+impl GraphInner {
+    pub fn set_meta(&self, key: &str, value: Value)
+        -> impl Future<Item = Result<(), MetaError>, Error = TxnError>
+    {
+        let key = key.to_string();
+        self.graph_transaction(move |txn| {
+            txn.set_meta(&key, value.clone())
+        })
+    }
+    pub fn get_meta(&self, key: &str)
+        -> impl Future<Item = Result<Option<Value>, ReadVertexError>, Error = TxnError>
+    {
+        let key = key.to_string();
+        self.graph_transaction(move |txn| {
+            txn.get_meta(&key)
+        })
+    }
+}
+
+impl <'a> GraphTransaction<'a> {
+    pub fn set_meta(&self, key: &str, value: Value)
+        -> Result<Result<(), MetaError>, TxnError>
+    {
+        // the cell is created with the base schemas, it is only missing if that write failed
+        let (mut cell, existed) = match self.neb_txn.read(&*META_CELL_ID)? {
+            Some(cell) => (cell, true),
+            None => (empty_meta_cell(), false)
+        };
+        let key_id = key_hash(&key.to_string());
+        if let &mut Value::Map(ref mut entries) = &mut cell.data {
+            match value {
+                Value::Null => { entries.map.remove(&key_id); },
+                value => entries.insert_key_id(key_id, value)
+            }
+        } else {
+            return Ok(Err(MetaError::FormatError));
+        }
+        if existed {
+            self.neb_txn.update(&cell)?;
+        } else {
+            self.neb_txn.write(&cell)?;
+        }
+        Ok(Ok(()))
+    }
+    pub fn get_meta(&self, key: &str)
+        -> Result<Result<Option<Value>, ReadVertexError>, TxnError>
+    {
+        let cell = match self.neb_txn.read(&*META_CELL_ID)? {
+            Some(cell) => cell, None => return Ok(Ok(None))
+        };
+        Ok(Ok(match cell.data[key_hash(&key.to_string())] {
+            Value::Null => None,
+            ref value => Some(value.clone())
+        }))
+    }
+}
+
+impl fmt::Display for MetaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &MetaError::FormatError => write!(f, "metadata cell is malformed")
+        }
+    }
+}
+
+impl Error for MetaError {}
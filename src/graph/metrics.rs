@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::cell::Cell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GraphMetrics {
+    pub vertices_created: usize,
+    pub edges_linked: usize,
+    pub transactions_aborted: usize,
+    pub read_errors: usize
+}
+
+// Counters are only touched when the metrics feature is enabled
+#[derive(Default)]
+pub struct MetricsCounters {
+    vertices_created: AtomicUsize,
+    edges_linked: AtomicUsize,
+    transactions_aborted: AtomicUsize,
+    read_errors: AtomicUsize
+}
+
+// Writes of one transaction attempt, added to the counters only when it commits
+#[derive(Default)]
+pub struct PendingMetrics {
+    vertices_created: Cell<usize>,
+    edges_linked: Cell<usize>
+}
+
+#[cfg(feature = "metrics")]
+fn add(counter: &AtomicUsize, n: usize) {
+    counter.fetch_add(n, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "metrics"))]
+fn add(_counter: &AtomicUsize, _n: usize) {}
+
+impl MetricsCounters {
+    pub fn snapshot(&self) -> GraphMetrics {
+        GraphMetrics {
+            vertices_created: self.vertices_created.load(Ordering::Relaxed),
+            edges_linked: self.edges_linked.load(Ordering::Relaxed),
+            transactions_aborted: self.transactions_aborted.load(Ordering::Relaxed),
+            read_errors: self.read_errors.load(Ordering::Relaxed)
+        }
+    }
+    pub fn on_commit(&self, pending: &PendingMetrics) {
+        add(&self.vertices_created, pending.vertices_created.get());
+        add(&self.edges_linked, pending.edges_linked.get());
+    }
+    pub fn on_abort(&self) {
+        add(&self.transactions_aborted, 1);
+    }
+    pub fn on_read_error(&self) {
+        add(&self.read_errors, 1);
+    }
+}
+
+impl PendingMetrics {
+    pub fn vertex_created(&self) {
+        self.vertices_created.set(self.vertices_created.get() + 1);
+    }
+    pub fn edge_linked(&self) {
+        self.edges_linked.set(self.edges_linked.get() + 1);
+    }
+    // the transaction closure may run more than once, only its last run commits
+    pub fn reset(&self) {
+        self.vertices_created.set(0);
+        self.edges_linked.set(0);
+    }
+}
@@ -9,19 +9,44 @@ use bifrost::raft::state_machine::master::ExecError;
 use bifrost::rpc::RPCError;
 
 use server::schema::{MorpheusSchema, SchemaType, SchemaContainer, SchemaError, ToSchemaId};
+use server::schema::{VertexSchemaId, EdgeSchemaId, ToVertexSchemaId, ToEdgeSchemaId, validate_schema};
 use graph::vertex::{Vertex, ToVertexId};
+use graph::vertex::typed::TypedVertex;
 use graph::edge::bilateral::BilateralEdge;
 use graph::edge::{EdgeAttributes, EdgeError};
 use query::{Tester, Expr, parse_optional_expr};
+use utils::trace::{traced, result_outcome, txn_outcome, nested_outcome};
+use utils::value;
 use futures::prelude::*;
+use serde::Serialize;
 use futures::future;
 
 use std::sync::Arc;
+use std::rc::Rc;
+use std::cell;
+use std::time::{Duration, Instant};
+use std::error::Error;
+use std::fmt;
 
+#[macro_use]
 pub mod vertex;
 pub mod edge;
 pub mod fields;
+pub mod traversal;
+pub mod analytics;
+pub mod scan;
+pub mod read_only;
+pub mod io;
+pub mod metrics;
+pub mod subgraph;
+pub mod merge;
+pub mod meta;
+pub mod async_graph;
 mod id_list;
+mod id_set;
+mod registry;
+mod index;
+mod labels;
 
 #[derive(Debug)]
 pub enum NewVertexError {
@@ -29,14 +54,25 @@ pub enum NewVertexError {
     SchemaNotVertex(SchemaType),
     CannotGenerateCellByData,
     DataNotMap,
+    KeyMismatch,
+    MissingRequiredField(u64),
+    FieldTypeMismatch { field: u64, expected: u32, got: u32 },
+    ReservedFieldConflict(u64),
+    VertexExisted(Id),
     RPCError(RPCError),
-    WriteError(WriteError)
+    WriteError(WriteError),
+    IdListError(id_list::IdListError),
+    TxnError(TxnError),
+    BatchItemError(usize, Box<NewVertexError>)
 }
 
 #[derive(Debug)]
 pub enum ReadVertexError {
     RPCError(RPCError),
     ReadError(ReadError),
+    IdListError(id_list::IdListError),
+    FieldNotIndexed(u64),
+    IndexNotBuilt(u64),
 }
 
 #[derive(Debug)]
@@ -45,9 +81,25 @@ pub enum LinkVerticesError {
     SchemaNotEdge,
     BodyRequired,
     BodyShouldNotExisted,
+    NotBilateralEdge,
+    SelfLoopNotAllowed,
+    EndpointNotFound(Id),
+    InvalidBody(EdgeBodyError),
     EdgeError(edge::EdgeError),
 }
 
+#[derive(Debug)]
+pub enum EdgeBodyError {
+    MissingRequiredField(u64),
+    FieldTypeMismatch { field: u64, expected: u32, got: u32 },
+    ReservedFieldConflict(u64)
+}
+
+#[derive(Debug)]
+pub enum TxnTimeoutError {
+    TimedOut
+}
+
 #[derive(Debug)]
 pub enum NeighbourhoodError {
     EdgeError(edge::EdgeError),
@@ -56,13 +108,162 @@ pub enum NeighbourhoodError {
     FilterEvalError(String)
 }
 
+// neb and bifrost errors do not implement Error, so they are only formatted, not exposed as sources
+
+impl fmt::Display for NewVertexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &NewVertexError::SchemaNotFound => write!(f, "vertex schema not found"),
+            &NewVertexError::SchemaNotVertex(ref t) => write!(f, "schema is not a vertex schema but {:?}", t),
+            &NewVertexError::CannotGenerateCellByData => write!(f, "cannot generate cell from data"),
+            &NewVertexError::DataNotMap => write!(f, "vertex data is not a map"),
+            &NewVertexError::KeyMismatch => write!(f, "vertex data does not match the given key"),
+            &NewVertexError::MissingRequiredField(field) => write!(f, "required field {} is missing", field),
+            &NewVertexError::FieldTypeMismatch { field, expected, got } =>
+                write!(f, "field {} expects type {} but got {}", field, expected, got),
+            &NewVertexError::ReservedFieldConflict(field) => write!(f, "field {} is reserved for edge lists", field),
+            &NewVertexError::VertexExisted(ref id) => write!(f, "vertex {:?} already exists", id),
+            &NewVertexError::RPCError(ref e) => write!(f, "rpc error: {:?}", e),
+            &NewVertexError::WriteError(ref e) => write!(f, "write error: {:?}", e),
+            &NewVertexError::IdListError(ref e) => write!(f, "id list error: {}", e),
+            &NewVertexError::TxnError(ref e) => write!(f, "transaction error: {:?}", e),
+            &NewVertexError::BatchItemError(index, ref e) => write!(f, "batch item {} failed: {}", index, e)
+        }
+    }
+}
+
+impl Error for NewVertexError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &NewVertexError::IdListError(ref e) => Some(e),
+            &NewVertexError::BatchItemError(_, ref e) => Some(&**e),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ReadVertexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ReadVertexError::RPCError(ref e) => write!(f, "rpc error: {:?}", e),
+            &ReadVertexError::ReadError(ref e) => write!(f, "read error: {:?}", e),
+            &ReadVertexError::IdListError(ref e) => write!(f, "id list error: {}", e),
+            &ReadVertexError::FieldNotIndexed(field) => write!(f, "field {} is not indexed", field),
+            &ReadVertexError::IndexNotBuilt(field) =>
+                write!(f, "index of field {} is still being built over existing vertices", field)
+        }
+    }
+}
+
+impl Error for ReadVertexError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &ReadVertexError::IdListError(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for LinkVerticesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &LinkVerticesError::EdgeSchemaNotFound => write!(f, "edge schema not found"),
+            &LinkVerticesError::SchemaNotEdge => write!(f, "schema is not an edge schema"),
+            &LinkVerticesError::BodyRequired => write!(f, "edge body required"),
+            &LinkVerticesError::BodyShouldNotExisted => write!(f, "edge body should not exist"),
+            &LinkVerticesError::NotBilateralEdge => write!(f, "edge schema is not bilateral"),
+            &LinkVerticesError::SelfLoopNotAllowed => write!(f, "cannot link a vertex to itself"),
+            &LinkVerticesError::EndpointNotFound(ref id) => write!(f, "endpoint vertex {:?} not found", id),
+            &LinkVerticesError::InvalidBody(ref e) => write!(f, "invalid edge body: {}", e),
+            &LinkVerticesError::EdgeError(ref e) => write!(f, "edge error: {}", e)
+        }
+    }
+}
+
+impl fmt::Display for EdgeBodyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &EdgeBodyError::MissingRequiredField(field) => write!(f, "required field {} is missing", field),
+            &EdgeBodyError::FieldTypeMismatch { field, expected, got } =>
+                write!(f, "field {} expects type {} but got {}", field, expected, got),
+            &EdgeBodyError::ReservedFieldConflict(field) => write!(f, "field {} is reserved for edge endpoints", field)
+        }
+    }
+}
+
+impl Error for EdgeBodyError {}
+
+impl Error for LinkVerticesError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &LinkVerticesError::InvalidBody(ref e) => Some(e),
+            &LinkVerticesError::EdgeError(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for TxnTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &TxnTimeoutError::TimedOut => write!(f, "transaction ran past its deadline")
+        }
+    }
+}
+
+impl Error for TxnTimeoutError {}
+
+impl fmt::Display for NeighbourhoodError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &NeighbourhoodError::EdgeError(ref e) => write!(f, "edge error: {}", e),
+            &NeighbourhoodError::VertexNotFound(ref id) => write!(f, "vertex {:?} not found", id),
+            &NeighbourhoodError::CannotFindOppositeId(ref id) => write!(f, "cannot find opposite id of {:?}", id),
+            &NeighbourhoodError::FilterEvalError(ref e) => write!(f, "cannot evaluate filter: {}", e)
+        }
+    }
+}
+
+impl Error for NeighbourhoodError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &NeighbourhoodError::EdgeError(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GraphOpError<E> {
+    TxnError(TxnError),
+    OpError(E)
+}
+
+impl <E> fmt::Display for GraphOpError<E> where E: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &GraphOpError::TxnError(ref e) => write!(f, "transaction error: {:?}", e),
+            &GraphOpError::OpError(ref e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl <E> Error for GraphOpError<E> where E: Error + 'static {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &GraphOpError::OpError(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum CellType {
     Vertex,
     Edge(edge::EdgeType)
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EdgeDirection {
     Inbound,
     Outbound,
@@ -77,6 +278,13 @@ impl EdgeDirection {
             &EdgeDirection::Undirected => *fields::UNDIRECTED_KEY_ID,
         }
     }
+    pub fn reversed(&self) -> EdgeDirection {
+        match self {
+            &EdgeDirection::Inbound => EdgeDirection::Outbound,
+            &EdgeDirection::Outbound => EdgeDirection::Inbound,
+            &EdgeDirection::Undirected => EdgeDirection::Undirected,
+        }
+    }
 }
 
 fn vertex_to_cell_for_write(schemas: &Arc<SchemaContainer>, vertex: Vertex) -> Result<Cell, NewVertexError> {
@@ -98,22 +306,119 @@ fn vertex_to_cell_for_write(schemas: &Arc<SchemaContainer>, vertex: Vertex) -> R
             _ => return Err(NewVertexError::DataNotMap)
         }
     };
-    data.insert_key_id(*fields::INBOUND_KEY_ID, Value::Id(Id::unit_id()));
-    data.insert_key_id(*fields::OUTBOUND_KEY_ID, Value::Id(Id::unit_id()));
-    data.insert_key_id(*fields::UNDIRECTED_KEY_ID, Value::Id(Id::unit_id()));
+    // edge list keys are set up below, user data under them would be lost
+    for ed in fields::ALL_DIRECTIONS.iter() {
+        match data.get_by_key_id(ed.as_field()) {
+            &Value::Null => {},
+            _ => return Err(NewVertexError::ReservedFieldConflict(ed.as_field()))
+        }
+    }
+    if let Some(ref schema_fields) = neb_schema.fields.sub_fields {
+        for key_id in fields::vertex_list_keys(schema_fields) {
+            data.insert_key_id(key_id, Value::Id(Id::unit_id()));
+        }
+    }
+    for (field_name, default) in schemas.field_defaults(schema_id) {
+        let field_id = key_hash(&field_name);
+        if let &Value::Null = data.get_by_key_id(field_id) {
+            data.insert_key_id(field_id, default);
+        }
+    }
+    if let Some(ref schema_fields) = neb_schema.fields.sub_fields {
+        for field in schema_fields.iter().filter(|field| !field.nullable) {
+            let field_id = key_hash(&field.name);
+            if let &Value::Null = data.get_by_key_id(field_id) {
+                return Err(NewVertexError::MissingRequiredField(field_id));
+            }
+        }
+        // only scalar fields are checked, nested maps and arrays are left to Cell::new
+        for field in schema_fields.iter().filter(|field| !field.is_array && field.sub_fields.is_none()) {
+            let field_id = key_hash(&field.name);
+            if let Some(got) = value::type_id_of(data.get_by_key_id(field_id)) {
+                if got != field.type_id {
+                    return Err(NewVertexError::FieldTypeMismatch { field: field_id, expected: field.type_id, got });
+                }
+            }
+        }
+    }
     match Cell::new(&neb_schema, Value::Map(data)) {
         Some(cell) => Ok(cell),
         None => return Err(NewVertexError::CannotGenerateCellByData)
     }
 }
 
+// Same checks new vertices get. Endpoint fields are filled in by the link itself,
+// so the body may not set them and they are not counted as missing.
+fn edge_endpoint_ids(edge_type: edge::EdgeType) -> Vec<u64> {
+    let endpoint_fields = match edge_type {
+        edge::EdgeType::Directed => &*edge::directed::EDGE_TEMPLATE,
+        edge::EdgeType::Undirected => &*edge::undirectd::EDGE_TEMPLATE
+    };
+    endpoint_fields.iter().map(|field| key_hash(&field.name)).collect()
+}
+
+fn validate_edge_body(schemas: &Arc<SchemaContainer>, schema_id: u32, edge_type: edge::EdgeType, body: &Map)
+    -> Result<(), EdgeBodyError>
+{
+    let endpoint_ids = edge_endpoint_ids(edge_type);
+    for &field_id in &endpoint_ids {
+        match body.get_by_key_id(field_id) {
+            &Value::Null => {},
+            _ => return Err(EdgeBodyError::ReservedFieldConflict(field_id))
+        }
+    }
+    let neb_schema = match schemas.get_neb_schema(schema_id) {
+        Some(schema) => schema, None => return Ok(())
+    };
+    if let Some(ref schema_fields) = neb_schema.fields.sub_fields {
+        let body_fields = schema_fields.iter().filter(|field| !endpoint_ids.contains(&key_hash(&field.name)));
+        for field in body_fields {
+            let field_id = key_hash(&field.name);
+            let field_value = body.get_by_key_id(field_id);
+            if let &Value::Null = field_value {
+                if !field.nullable { return Err(EdgeBodyError::MissingRequiredField(field_id)); }
+                continue;
+            }
+            if field.is_array || field.sub_fields.is_some() { continue; }
+            if let Some(got) = value::type_id_of(field_value) {
+                if got != field.type_id {
+                    return Err(EdgeBodyError::FieldTypeMismatch { field: field_id, expected: field.type_id, got });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VertexGroupOptions {
+    // ids per edge list segment, larger segments mean fewer cells to chase on high degree
+    // vertices but larger rewrites on every link. None keeps the largest size a cell can hold
+    pub id_list_capacity: Option<usize>
+}
+
+// neb keeps a single copy of every cell and serves all reads from it, so there is no
+// cheaper stale read to opt into yet and both levels read the same way for now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadConsistency {
+    Strong,
+    Eventual
+}
+
+impl Default for ReadConsistency {
+    fn default() -> Self {
+        ReadConsistency::Strong
+    }
+}
+
 pub struct Graph {
     inner: Arc<GraphInner>
 }
 
 pub struct GraphInner {
     schemas: Arc<SchemaContainer>,
-    neb_client: Arc<NebClient>
+    neb_client: Arc<NebClient>,
+    metrics: Arc<metrics::MetricsCounters>
 }
 
 impl Graph {
@@ -129,97 +434,314 @@ impl Graph {
                 Ok(Graph { inner: Arc::new(inner) })
             })
     }
-    fn check_base_schema(schemas: &Arc<SchemaContainer>, schema_id: u32, schema_name: & 'static str, fields: &'static Field)
+    fn check_base_schema(schemas: &Arc<SchemaContainer>, schema_id: u32, schema_name: & 'static str, fields: &'static Field, is_dynamic: bool)
         -> impl Future<Item = (), Error = ExecError>
     {
-        GraphInner::check_base_schema(schemas.clone(), schema_id, schema_name, fields)
+        GraphInner::check_base_schema(schemas.clone(), schema_id, schema_name, fields, is_dynamic)
     }
     fn check_base_schemas(schemas: &Arc<SchemaContainer>)
         -> impl Future<Item = (), Error = ExecError>
     {
         GraphInner::check_base_schemas(schemas.clone())
     }
+    // The schema type has to be set, new_vertex_group and new_edge_group would set it
+    pub fn validate_schema(&self, schema: &MorpheusSchema) -> Result<(), SchemaError> {
+        validate_schema(schema)
+    }
     pub fn new_vertex_group(&self, schema: MorpheusSchema)
-        -> impl Future<Item = u32, Error = SchemaError>
+        -> impl Future<Item = VertexSchemaId, Error = SchemaError>
+    {
+        self.inner.new_vertex_group(schema).map(VertexSchemaId)
+    }
+    pub fn new_vertex_group_with_options(&self, schema: MorpheusSchema, options: VertexGroupOptions)
+        -> impl Future<Item = VertexSchemaId, Error = SchemaError>
     {
-        self.inner.new_vertex_group(schema)
+        self.inner.new_vertex_group_with_options(schema, options).map(VertexSchemaId)
     }
     pub fn new_edge_group(&self, schema: MorpheusSchema, edge_attrs: edge::EdgeAttributes)
-        -> impl Future<Item = u32, Error = SchemaError>
+        -> impl Future<Item = EdgeSchemaId, Error = SchemaError>
+    {
+        self.inner.new_edge_group(schema, edge_attrs).map(EdgeSchemaId)
+    }
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> metrics::GraphMetrics {
+        self.inner.metrics.snapshot()
+    }
+    pub fn list_schemas(&self) -> Vec<(u32, String, SchemaType)> {
+        self.inner.schemas.list_schemas()
+    }
+    pub fn refresh_schema_cache(&self) {
+        self.inner.schemas.clear_neb_schema_cache()
+    }
+    pub fn field_id(name: &str) -> u64 {
+        key_hash(&name.to_string())
+    }
+    // parts are encoded as one length prefixed sequence, so ["a", "bc"] and ["ab", "c"] differ
+    pub fn encode_compound_key<K>(schema_id: u32, parts: &[K]) -> Id where K: Serialize {
+        Cell::encode_cell_key(schema_id, &parts)
+    }
+    pub fn field_id_in_schema(&self, schema_id: u32, name: &str) -> Option<u64> {
+        let schema = self.inner.schemas.get_neb_schema(schema_id)?;
+        let fields = schema.fields.sub_fields.as_ref()?;
+        fields.iter()
+            .find(|field| field.name == name)
+            .map(|field| key_hash(&field.name))
+    }
+    pub fn drop_vertex_group<S>(&self, schema: S)
+        -> impl Future<Item = (), Error = SchemaError> where S: ToVertexSchemaId
+    {
+        GraphInner::drop_vertex_group(self.inner.clone(), schema)
+    }
+    pub fn drop_edge_group<S>(&self, schema: S)
+        -> impl Future<Item = (), Error = SchemaError> where S: ToEdgeSchemaId
     {
-        self.inner.new_edge_group(schema, edge_attrs)
+        GraphInner::drop_edge_group(self.inner.clone(), schema)
     }
     pub fn new_vertex<S>(&self, schema: S, data: Map)
         -> impl Future<Item = Vertex, Error = NewVertexError>
-        where S: ToSchemaId
+        where S: ToVertexSchemaId
+    {
+        let schema_id = schema.to_id(&self.inner.schemas);
+        let created = GraphInner::new_vertex(self.inner.clone(), schema_id, data);
+        traced("new_vertex", Some(schema_id), created, result_outcome)
+    }
+    // The id is encoded from the key, so writing the same key again lands on the same vertex
+    pub fn new_vertex_with_key<S, K>(&self, schema: S, key: &K, data: Map, overwrite: bool)
+        -> impl Future<Item = Vertex, Error = NewVertexError>
+        where S: ToVertexSchemaId, K: Serialize
     {
-        GraphInner::new_vertex(self.inner.clone(), schema, data)
+        self.inner.new_vertex_with_key(schema, key, data, overwrite)
     }
+    pub fn new_vertex_typed<T>(&self, vertex: &T)
+        -> impl Future<Item = Vertex, Error = NewVertexError>
+        where T: TypedVertex
+    {
+        self.new_vertex(T::schema_name(), vertex.to_map())
+    }
+    // All or nothing: the first failing item aborts the batch and is reported with its index
+    pub fn new_vertices<S>(&self, items: Vec<(S, Map)>)
+        -> impl Future<Item = Vec<Vertex>, Error = NewVertexError>
+        where S: ToVertexSchemaId
+    {
+        self.inner.new_vertices(items)
+    }
+    // Removing a vertex that does not exist is not an error, so retried cleanups are safe.
+    // Its edges of every edge schema are removed with it, found by the schema lists of its
+    // edge fields, so there is no need to name the edge schemas.
     pub fn remove_vertex<V>(&self, vertex: V)
-        -> impl Future<Item = (), Error = TxnError> where V: ToVertexId
+        -> impl Future<Item = Result<(), vertex::RemoveError>, Error = TxnError> where V: ToVertexId
     {
         self.inner.remove_vertex(vertex)
     }
+    // The *_retrying variants rerun the whole operation when the transaction is aborted,
+    // up to max_retries times. Errors of the operation itself are returned without a retry.
+    pub fn remove_vertex_retrying<V>(&self, vertex: V, max_retries: usize)
+        -> impl Future<Item = Result<(), vertex::RemoveError>, Error = TxnError> where V: ToVertexId
+    {
+        let id = vertex.to_id();
+        self.graph_transaction_retry(max_retries, move |txn| match txn.remove_vertex(id)? {
+            Err(vertex::RemoveError::NotFound) => Ok(Ok(())),
+            res => Ok(res)
+        })
+    }
     pub fn remove_vertex_by_key<K, S>(&self, schema: S, key: K)
-        -> impl Future<Item = (), Error = TxnError>
-        where K: ToValue, S: ToSchemaId
+        -> impl Future<Item = Result<(), vertex::RemoveError>, Error = TxnError>
+        where K: ToValue, S: ToVertexSchemaId
     {
         self.inner.remove_vertex_by_key(schema, key)
     }
     pub fn update_vertex<V, U>(&self, vertex: V, update: U)
-        -> impl Future<Item = (), Error = TxnError>
+        -> impl Future<Item = Result<(), id_list::IdListError>, Error = TxnError>
         where V: ToVertexId, U: Fn(Vertex) -> Option<Vertex>, U: 'static
     {
         self.inner.update_vertex(vertex, update)
     }
     pub fn update_vertex_by_key<K, U, S>(&self, schema: S, key: K, update: U)
-        -> impl Future<Item = (), Error = TxnError>
-        where K: ToValue, S: ToSchemaId, U: Fn(Vertex) -> Option<Vertex>, U: 'static
+        -> impl Future<Item = Result<(), id_list::IdListError>, Error = TxnError>
+        where K: ToValue, S: ToVertexSchemaId, U: Fn(Vertex) -> Option<Vertex>, U: 'static
     {
         self.inner.update_vertex_by_key(schema, key, update)
     }
+    pub fn upsert_vertex<K, S, U>(&self, schema: S, key: K, data: Map, update: U)
+        -> impl Future<Item = Result<Vertex, NewVertexError>, Error = TxnError>
+        where K: ToValue, S: ToVertexSchemaId, U: Fn(Vertex) -> Option<Vertex>, U: 'static
+    {
+        self.inner.upsert_vertex(schema, key, data, update)
+    }
+
+    pub fn update_edge<S, U>(&self, edge_id: &Id, schema: S, update: U)
+        -> impl Future<Item = Result<(), EdgeError>, Error = TxnError>
+        where S: ToEdgeSchemaId, U: Fn(edge::Edge) -> Option<edge::Edge>, U: 'static
+    {
+        self.inner.update_edge(edge_id, schema, update)
+    }
 
     pub fn vertex_by<V>(&self, vertex: V)
         -> impl Future<Item = Option<Vertex>, Error = ReadVertexError>
         where V: ToVertexId
     {
-        GraphInner::vertex_by(self.inner.clone(), vertex)
+        let read = GraphInner::vertex_by(self.inner.clone(), vertex);
+        traced("read_vertex", None, read, result_outcome)
+    }
+    pub fn vertex_by_with_consistency<V>(&self, vertex: V, consistency: ReadConsistency)
+        -> impl Future<Item = Option<Vertex>, Error = ReadVertexError>
+        where V: ToVertexId
+    {
+        match consistency {
+            ReadConsistency::Strong | ReadConsistency::Eventual => self.vertex_by(vertex)
+        }
+    }
+
+    pub fn read_vertices(&self, ids: &[Id])
+        -> impl Future<Item = Vec<Option<Vertex>>, Error = ReadVertexError>
+    {
+        GraphInner::read_vertices(self.inner.clone(), ids)
+    }
+
+    // Escape hatch to neb: reads any cell, vertex or not, without Morpheus interpreting it
+    pub fn read_cell_raw(&self, id: &Id)
+        -> impl Future<Item = Option<Cell>, Error = ReadVertexError>
+    {
+        GraphInner::read_cell_raw(self.inner.clone(), id)
     }
 
     pub fn vertex_by_key<K, S>(&self, schema: S, key: K)
         -> impl Future<Item = Option<Vertex>, Error = ReadVertexError>
-        where K: ToValue, S: ToSchemaId
+        where K: ToValue, S: ToVertexSchemaId
     {
         GraphInner::vertex_by_key(self.inner.clone(), schema, key)
     }
+    // The key is hashed the same way as keys given to vertex_by_key
+    pub fn get_vertex_by_value<S>(&self, schema: S, key: &Value)
+        -> impl Future<Item = Option<Vertex>, Error = ReadVertexError>
+        where S: ToVertexSchemaId
+    {
+        let id = Cell::encode_cell_key(schema.to_id(&self.inner.schemas), key);
+        GraphInner::vertex_by(self.inner.clone(), id)
+    }
 
+    // Reads in the closure see the writes made earlier in it, nothing is visible to
+    // other transactions until the closure returns and the transaction commits
+    // The closure runs on the thread polling the future and its neb_txn calls block until
+    // their rpc returns, so a transaction holds the executor thread for its whole body.
+    // async_graph::AsyncGraph runs them on a thread pool instead
     pub fn graph_transaction<TFN, TR>(&self, func: TFN)
         -> impl Future<Item = TR, Error = TxnError>
         where TFN: Fn(&GraphTransaction) -> Result<TR, TxnError>, TR: 'static, TFN: 'static
     {
         self.inner.graph_transaction(func)
     }
+    pub fn graph_transaction_retry<TFN, TR>(&self, max_retries: usize, func: TFN)
+        -> impl Future<Item = TR, Error = TxnError>
+        where TFN: Fn(&GraphTransaction) -> Result<TR, TxnError>, TR: 'static, TFN: 'static
+    {
+        GraphInner::graph_transaction_retry(self.inner.clone(), max_retries, func)
+    }
+    // the deadline is checked before each edge list walk and each of its items, and once per
+    // vertex in registry, index and schema scans, so a single slow read can still overrun it
+    pub fn graph_transaction_timeout<TFN, TR>(&self, timeout: Duration, func: TFN)
+        -> impl Future<Item = Result<TR, TxnTimeoutError>, Error = TxnError>
+        where TFN: Fn(&GraphTransaction) -> Result<TR, TxnError>, TR: 'static, TFN: 'static
+    {
+        let deadline = Deadline { at: Instant::now() + timeout, expired: Rc::new(cell::Cell::new(false)) };
+        let expired = deadline.expired.clone();
+        // aborts for any other reason, even ones that happen after the deadline, stay aborts
+        self.inner.graph_transaction_until(Some(deadline), func)
+            .then(move |result| match result {
+                Err(TxnError::Aborted(_)) if expired.get() => Ok(Err(TxnTimeoutError::TimedOut)),
+                result => result.map(Ok)
+            })
+    }
     pub fn link<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
         -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
-        where V: ToVertexId, S: ToSchemaId
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        let schema_id = schema.to_id(&self.inner.schemas);
+        let linked = self.inner.link(from, schema_id, to, body);
+        traced("link", Some(schema_id), linked, nested_outcome)
+    }
+    pub fn link_retrying<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>, max_retries: usize)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.inner.schemas);
+        self.graph_transaction_retry(max_retries, move |txn| {
+            txn.link(from_id, schema_id, to_id, body.clone())
+        })
+    }
+    pub fn link_unique<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        self.inner.link_unique(from, schema, to, body)
+    }
+    pub fn link_no_self_loop<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        self.inner.link_no_self_loop(from, schema, to, body)
+    }
+    pub fn link_checked<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        self.inner.link_checked(from, schema, to, body)
+    }
+    pub fn link_bilateral<V, S>(&self, a: V, schema: S, b: V, body: Map)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        self.inner.link_bilateral(a, schema, b, body)
+    }
+    pub fn unlink<V, S>(&self, from: V, schema: S, to: V)
+        -> impl Future<Item = Result<(), EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        self.inner.unlink(from, schema, to)
+    }
+    pub fn unlink_retrying<V, S>(&self, from: V, schema: S, to: V, max_retries: usize)
+        -> impl Future<Item = Result<(), EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.inner.schemas);
+        self.graph_transaction_retry(max_retries, move |txn| {
+            txn.unlink(from_id, schema_id, to_id)
+        })
+    }
+    pub fn are_linked<V, S>(&self, from: V, schema: S, to: V, direction: EdgeDirection)
+        -> impl Future<Item = Result<bool, edge::EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId
     {
-        self.inner.link(from, schema, to, body)
+        self.inner.are_linked(from, schema, to, direction)
     }
     pub fn degree<V, S>(&self, vertex: V, schema: S, direction: EdgeDirection)
         -> impl Future<Item = Result<usize, edge::EdgeError>, Error = TxnError>
-        where V: ToVertexId, S: ToSchemaId
+        where V: ToVertexId, S: ToEdgeSchemaId
     {
         self.inner.degree(vertex, schema, direction)
     }
     pub fn neighbourhoods<V, S, F>(&self, vertex: V, schema: S, direction: EdgeDirection, filter: &Option<F>)
         -> impl Future<Item = Result<Vec<(Vertex, edge::Edge)>, NeighbourhoodError>, Error = TxnError>
-        where V: ToVertexId, S: ToSchemaId, F: Expr
+        where V: ToVertexId, S: ToEdgeSchemaId, F: Expr
+    {
+        let schema_id = schema.to_id(&self.inner.schemas);
+        traced("neighbourhoods", Some(schema_id),
+               GraphInner::neighbourhoods(self.inner.clone(), vertex, schema_id, direction, filter),
+               nested_outcome)
+    }
+    pub fn neighbourhoods_skip_missing<V, S, F>(&self, vertex: V, schema: S, direction: EdgeDirection, filter: &Option<F>)
+        -> impl Future<Item = Result<(Vec<(Vertex, edge::Edge)>, Vec<Id>), NeighbourhoodError>, Error = TxnError>
+        where V: ToVertexId, S: ToEdgeSchemaId, F: Expr
     {
-        GraphInner::neighbourhoods(self.inner.clone(), vertex, schema, direction, filter)
+        GraphInner::neighbourhoods_skip_missing(self.inner.clone(), vertex, schema, direction, filter)
     }
     pub fn edges<V, S, F>(&self, vertex: V, schema: S, direction: EdgeDirection, filter: &Option<F>)
         -> impl Future<Item = Result<Vec<edge::Edge>, EdgeError>, Error = TxnError>
-        where V: ToVertexId, S: ToSchemaId, F: Expr
+        where V: ToVertexId, S: ToEdgeSchemaId, F: Expr
     {
         GraphInner::edges(self.inner.clone(), vertex, schema, direction, filter)
     }
@@ -231,16 +753,17 @@ impl GraphInner {
         await!(GraphInner::check_base_schemas(schemas.clone()))?;
         Ok(GraphInner {
             schemas: schemas.clone(),
-            neb_client: neb_client.clone()
+            neb_client: neb_client.clone(),
+            metrics: Arc::new(metrics::MetricsCounters::default())
         })
     }
     #[async]
-    fn check_base_schema(schemas: Arc<SchemaContainer>, schema_id: u32, schema_name: &'static str, fields: &'static Field) -> Result<(), ExecError> {
+    fn check_base_schema(schemas: Arc<SchemaContainer>, schema_id: u32, schema_name: &'static str, fields: &'static Field, is_dynamic: bool) -> Result<(), ExecError> {
         match schemas.get_neb_schema(schema_id) {
             None => {
                 await!(schemas.neb_client.new_schema_with_id(
                     Schema::new_with_id(
-                        schema_id, schema_name, None, fields.clone(), false
+                        schema_id, schema_name, None, fields.clone(), is_dynamic
                     )
                 ))?;
             },
@@ -250,83 +773,277 @@ impl GraphInner {
     }
     #[async]
     fn check_base_schemas(schemas: Arc<SchemaContainer>) -> Result<(), ExecError> {
-        await!(GraphInner::check_base_schema(schemas.clone(), id_list::ID_LIST_SCHEMA_ID, "_NEB_ID_LIST", &*id_list::ID_LINKED_LIST))?;
-        await!(GraphInner::check_base_schema(schemas, id_list::TYPE_LIST_SCHEMA_ID, "_NEB_TYPE_ID_LIST", &*id_list::ID_TYPE_LIST))?;
+        await!(GraphInner::check_base_schema(schemas.clone(), id_list::ID_LIST_SCHEMA_ID, "_NEB_ID_LIST", &*id_list::ID_LINKED_LIST, false))?;
+        await!(GraphInner::check_base_schema(schemas.clone(), id_list::TYPE_LIST_SCHEMA_ID, "_NEB_TYPE_ID_LIST", &*id_list::ID_TYPE_LIST, false))?;
+        await!(GraphInner::check_base_schema(schemas.clone(), id_set::ID_SET_SCHEMA_ID, "_MORPHEUS_ID_SET", &*id_set::ID_SET, false))?;
+        await!(GraphInner::check_base_schema(schemas.clone(), id_set::ID_SET_MEMBER_SCHEMA_ID, "_MORPHEUS_ID_SET_MEMBER", &*id_set::ID_SET_MEMBER, false))?;
+        await!(GraphInner::check_base_schema(schemas.clone(), registry::VERTEX_REGISTRY_SCHEMA_ID, "_MORPHEUS_VERTEX_REGISTRY", &*registry::VERTEX_REGISTRY, false))?;
+        await!(GraphInner::check_base_schema(schemas.clone(), index::INDEX_ENTRY_SCHEMA_ID, "_MORPHEUS_INDEX_ENTRY", &*index::INDEX_ENTRY, false))?;
+        await!(GraphInner::check_base_schema(schemas.clone(), meta::META_SCHEMA_ID, "_MORPHEUS_GRAPH_META", &*meta::META, true))?;
+        // a failed write is not fatal, set_meta creates the cell when it is still missing
+        match await!(schemas.neb_client.write_cell(meta::empty_meta_cell())) {
+            Ok(Ok(_)) | Ok(Err(WriteError::CellAlreadyExisted)) => {},
+            Ok(Err(e)) => warn!("cannot create the graph metadata cell: {:?}", e),
+            Err(e) => warn!("cannot create the graph metadata cell: {:?}", e)
+        }
         Ok(())
     }
     pub fn new_vertex_group(&self, mut schema: MorpheusSchema)
         -> impl Future<Item = u32, Error = SchemaError>
     {
         schema.schema_type = SchemaType::Vertex;
-        self.schemas.new_schema(schema)
+        let neb_client = self.neb_client.clone();
+        self.schemas.new_schema(schema).and_then(move |schema_id| {
+            // a new schema has no vertices yet, so its registry starts out complete
+            neb_client.write_cell(registry::coverage_cell(schema_id)).then(move |result| match result {
+                Ok(Ok(_)) | Ok(Err(WriteError::CellAlreadyExisted)) => Ok(schema_id),
+                Ok(Err(e)) => Err(SchemaError::CannotInitRegistry(format!("{:?}", e))),
+                Err(e) => Err(SchemaError::CannotInitRegistry(format!("{:?}", e)))
+            })
+        })
+    }
+    pub fn new_vertex_group_with_options(&self, schema: MorpheusSchema, options: VertexGroupOptions)
+        -> impl Future<Item = u32, Error = SchemaError>
+    {
+        let schemas = self.schemas.clone();
+        self.new_vertex_group(schema).and_then(move |schema_id| {
+            if let Some(capacity) = options.id_list_capacity {
+                schemas.set_id_list_capacity(schema_id, capacity)?;
+            }
+            Ok(schema_id)
+        })
+    }
+    pub fn drop_vertex_group<S>(this: Arc<Self>, schema: S)
+        -> impl Future<Item = (), Error = SchemaError> where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&this.schemas);
+        async_block! {
+            match this.schemas.schema_type(schema_id) {
+                Some(SchemaType::Vertex) => {},
+                Some(t) => return Err(SchemaError::SchemaTypeMismatch(t)),
+                None => return Err(SchemaError::SchemaNotFound)
+            }
+            // registered ids are checked against neb, the registry is cleared with the check
+            match await!(this.graph_transaction(move |txn| txn.clear_unused_vertex_schema(schema_id))) {
+                Ok(Ok(true)) => {},
+                Ok(Ok(false)) => return Err(SchemaError::SchemaInUse),
+                _ => return Err(SchemaError::CannotCheckSchemaUsage)
+            }
+            match await!(this.schemas.del_schema(schema_id)) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    // the schema stays and still has no vertices, so its registry is complete again
+                    let _ = await!(this.neb_client.write_cell(registry::coverage_cell(schema_id)));
+                    Err(e)
+                }
+            }
+        }
+    }
+    // Edge cells are not enumerable per schema, the edge lists of the vertices registered as its
+    // users are checked instead. The drop is refused when the schema is not covered by the registry.
+    pub fn drop_edge_group<S>(this: Arc<Self>, schema: S)
+        -> impl Future<Item = (), Error = SchemaError> where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&this.schemas);
+        async_block! {
+            match this.schemas.schema_type(schema_id) {
+                Some(SchemaType::Edge(_)) => {},
+                Some(t) => return Err(SchemaError::SchemaTypeMismatch(t)),
+                None => return Err(SchemaError::SchemaNotFound)
+            }
+            match await!(this.graph_transaction(move |txn| txn.clear_unused_edge_schema(schema_id))) {
+                Ok(Ok(true)) => {},
+                Ok(Ok(false)) => return Err(SchemaError::SchemaInUse),
+                _ => return Err(SchemaError::CannotCheckSchemaUsage)
+            }
+            match await!(this.schemas.del_schema(schema_id)) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    // no vertex has edges of the schema, so its registry is complete again
+                    let _ = await!(this.neb_client.write_cell(registry::edge_coverage_cell(schema_id)));
+                    Err(e)
+                }
+            }
+        }
     }
     pub fn new_edge_group(&self, mut schema: MorpheusSchema, edge_attrs: edge::EdgeAttributes)
         -> impl Future<Item = u32, Error = SchemaError>
     {
         schema.schema_type = SchemaType::Edge(edge_attrs);
-        self.schemas.new_schema(schema)
+        let neb_client = self.neb_client.clone();
+        self.schemas.new_schema(schema).and_then(move |schema_id| {
+            // a new schema has no edges yet, so its user registry starts out complete
+            neb_client.write_cell(registry::edge_coverage_cell(schema_id)).then(move |result| match result {
+                Ok(Ok(_)) | Ok(Err(WriteError::CellAlreadyExisted)) => Ok(schema_id),
+                Ok(Err(e)) => Err(SchemaError::CannotInitRegistry(format!("{:?}", e))),
+                Err(e) => Err(SchemaError::CannotInitRegistry(format!("{:?}", e)))
+            })
+        })
     }
     pub fn new_vertex<S>(this: Arc<Self>, schema: S, data: Map)
         -> impl Future<Item = Vertex, Error = NewVertexError>
         where S: ToSchemaId
     {
-        let vertex = Vertex::new(schema.to_id(&this.schemas), data);
-        let mut cell_result = vertex_to_cell_for_write(&this.schemas, vertex);
-        async_block! {
-            let mut cell = cell_result?;
-            let header = match await!(this.neb_client.write_cell(cell.clone())) {
-                Ok(Ok(header)) => header,
-                Ok(Err(e)) => return Err(NewVertexError::WriteError(e)),
-                Err(e) => return Err(NewVertexError::RPCError(e))
-            };
-            cell.header = header;
-            Ok(vertex::cell_to_vertex(cell))
-        }
+        let schema_id = schema.to_id(&this.schemas);
+        this.graph_transaction(move |txn| txn.new_vertex(schema_id, data.clone()))
+            .then(|result| {
+                match result {
+                    Ok(Ok(vertex)) => Ok(vertex),
+                    Ok(Err(e)) => Err(e),
+                    Err(e) => Err(NewVertexError::TxnError(e))
+                }
+            })
+    }
+    pub fn new_vertex_with_key<S, K>(&self, schema: S, key: &K, data: Map, overwrite: bool)
+        -> impl Future<Item = Vertex, Error = NewVertexError>
+        where S: ToSchemaId, K: Serialize
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        let id = Cell::encode_cell_key(schema_id, key);
+        self.graph_transaction(move |txn| txn.new_vertex_with_id(schema_id, id, data.clone(), overwrite))
+            .then(|result| {
+                match result {
+                    Ok(Ok(vertex)) => Ok(vertex),
+                    Ok(Err(e)) => Err(e),
+                    Err(e) => Err(NewVertexError::TxnError(e))
+                }
+            })
+    }
+    pub fn new_vertices<S>(&self, items: Vec<(S, Map)>)
+        -> impl Future<Item = Vec<Vertex>, Error = NewVertexError>
+        where S: ToSchemaId
+    {
+        let items: Vec<(u32, Map)> = items
+            .into_iter()
+            .map(|(schema, data)| (schema.to_id(&self.schemas), data))
+            .collect();
+        let failed_item = Rc::new(cell::RefCell::new(None));
+        let failed_item_ref = failed_item.clone();
+        self.graph_transaction(move |txn| {
+            let mut vertices = Vec::with_capacity(items.len());
+            for (index, &(schema_id, ref data)) in items.iter().enumerate() {
+                match txn.new_vertex(schema_id, data.clone())? {
+                    Ok(vertex) => vertices.push(vertex),
+                    Err(e) => {
+                        // the items before it are written already, the abort rolls them back
+                        *failed_item_ref.borrow_mut() = Some((index, e));
+                        return Err(TxnError::Aborted(None));
+                    }
+                }
+            }
+            Ok(vertices)
+        }).then(move |result| match result {
+            Ok(vertices) => Ok(vertices),
+            Err(e) => Err(match failed_item.borrow_mut().take() {
+                Some((index, error)) => NewVertexError::BatchItemError(index, Box::new(error)),
+                None => NewVertexError::TxnError(e)
+            })
+        })
     }
     pub fn remove_vertex<V>(&self, vertex: V)
-        -> impl Future<Item = (), Error = TxnError> where V: ToVertexId
+        -> impl Future<Item = Result<(), vertex::RemoveError>, Error = TxnError> where V: ToVertexId
     {
         let id = vertex.to_id();
-        self.graph_transaction(move |txn| txn.remove_vertex(id)?
-            .map_err(|_| TxnError::Aborted(None)))
+        self.graph_transaction(move |txn| match txn.remove_vertex(id)? {
+            Err(vertex::RemoveError::NotFound) => Ok(Ok(())),
+            res => Ok(res)
+        })
     }
     pub fn remove_vertex_by_key<K, S>(&self, schema: S, key: K)
-        -> impl Future<Item = (), Error = TxnError>
+        -> impl Future<Item = Result<(), vertex::RemoveError>, Error = TxnError>
         where K: ToValue, S: ToSchemaId
     {
         let id = Cell::encode_cell_key(schema.to_id(&self.schemas), &key.value());
         self.remove_vertex(id)
     }
-    pub fn update_vertex<V, U>(&self, vertex: V, update: U) -> impl Future<Item = (), Error = TxnError>
+    // An index error aborts the transaction, so no entry is left half moved, and is then
+    // answered as the result
+    pub fn update_vertex<V, U>(&self, vertex: V, update: U)
+        -> impl Future<Item = Result<(), id_list::IdListError>, Error = TxnError>
         where V: ToVertexId, U: Fn(Vertex) -> Option<Vertex>, U: 'static
     {
         let id = vertex.to_id();
-        self.neb_client.transaction(move |txn|{
-            vertex::txn_update(txn, id, &update)
+        let schemas = self.schemas.clone();
+        let failed = Rc::new(cell::RefCell::new(None));
+        let failed_ref = failed.clone();
+        self.graph_transaction(move |txn|{
+            match vertex::txn_update(txn.neb_txn, &schemas, id, &update)? {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    *failed_ref.borrow_mut() = Some(e);
+                    Err(TxnError::Aborted(None))
+                }
+            }
+        }).then(move |result| match failed.borrow_mut().take() {
+            Some(e) => Ok(Err(e)),
+            None => result.map(Ok)
         })
     }
     pub fn update_vertex_by_key<K, U, S>(&self, schema: S, key: K, update: U)
-        -> impl Future<Item = (), Error = TxnError>
+        -> impl Future<Item = Result<(), id_list::IdListError>, Error = TxnError>
         where K: ToValue, S: ToSchemaId, U: Fn(Vertex) -> Option<Vertex>, U: 'static
     {
         let id = Cell::encode_cell_key(schema.to_id(&self.schemas), &key.value());
         self.update_vertex(id, update)
     }
+    pub fn upsert_vertex<K, S, U>(&self, schema: S, key: K, data: Map, update: U)
+        -> impl Future<Item = Result<Vertex, NewVertexError>, Error = TxnError>
+        where K: ToValue, S: ToSchemaId, U: Fn(Vertex) -> Option<Vertex>, U: 'static
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        let id = Cell::encode_cell_key(schema_id, &key.value());
+        self.graph_transaction(move |txn| {
+            txn.upsert_vertex_by_id(schema_id, id, data.clone(), &update)
+        })
+    }
+
+    pub fn update_edge<S, U>(&self, edge_id: &Id, schema: S, update: U)
+        -> impl Future<Item = Result<(), EdgeError>, Error = TxnError>
+        where S: ToSchemaId, U: Fn(edge::Edge) -> Option<edge::Edge>, U: 'static
+    {
+        let edge_id = *edge_id;
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.update_edge(&edge_id, schema_id, &update)
+        })
+    }
 
     pub fn vertex_by<V>(this: Arc<Self>, vertex: V)
         -> impl Future<Item = Option<Vertex>, Error = ReadVertexError> where V: ToVertexId
     {
-        this.neb_client.read_cell(vertex.to_id())
-            .then(|result| {
-                match result {
+        Self::read_cell_raw(this, &vertex.to_id())
+            .map(|cell| cell.map(vertex::cell_to_vertex))
+    }
+
+    pub fn read_cell_raw(this: Arc<Self>, id: &Id)
+        -> impl Future<Item = Option<Cell>, Error = ReadVertexError>
+    {
+        let counters = this.metrics.clone();
+        this.neb_client.read_cell(*id)
+            .then(move |result| {
+                let result = match result {
                     Err(e) => Err(ReadVertexError::RPCError(e)),
                     Ok(Err(ReadError::CellDoesNotExisted)) => Ok(None),
                     Ok(Err(e)) => Err(ReadVertexError::ReadError(e)),
-                    Ok(Ok(cell)) => Ok(Some(vertex::cell_to_vertex(cell)))
+                    Ok(Ok(cell)) => Ok(Some(cell))
+                };
+                if result.is_err() {
+                    counters.on_read_error();
                 }
+                result
             })
     }
 
+    pub fn read_vertices(this: Arc<Self>, ids: &[Id])
+        -> impl Future<Item = Vec<Option<Vertex>>, Error = ReadVertexError>
+    {
+        // reads are issued together and join_all keeps the input order
+        let reads: Vec<_> = ids.iter()
+            .map(|id| Self::vertex_by(this.clone(), *id))
+            .collect();
+        future::join_all(reads)
+    }
+
     pub fn vertex_by_key<K, S>(this: Arc<Self>, schema: S, key: K)
         -> impl Future<Item = Option<Vertex>, Error = ReadVertexError>
         where K: ToValue, S: ToSchemaId
@@ -337,15 +1054,65 @@ impl GraphInner {
 
     pub fn graph_transaction<TFN, TR>(&self, func: TFN) -> impl Future<Item = TR, Error = TxnError>
         where TFN: Fn(&GraphTransaction) -> Result<TR, TxnError>, TR: 'static, TFN: 'static
+    {
+        self.graph_transaction_until(None, func)
+    }
+    fn graph_transaction_until<TFN, TR>(&self, deadline: Option<Deadline>, func: TFN)
+        -> impl Future<Item = TR, Error = TxnError>
+        where TFN: Fn(&GraphTransaction) -> Result<TR, TxnError>, TR: 'static, TFN: 'static
     {
         let schemas = self.schemas.clone();
+        let counters = self.metrics.clone();
+        let pending = Rc::new(metrics::PendingMetrics::default());
+        let pending_ref = pending.clone();
         let wrapper = move |neb_txn: &Transaction| {
+            pending_ref.reset();
             func(&GraphTransaction {
                 neb_txn,
-                schemas: schemas.clone()
+                schemas: schemas.clone(),
+                deadline: deadline.clone(),
+                metrics: pending_ref.clone()
             })
         };
-        self.neb_client.transaction(wrapper)
+        // every Graph operation runs here, so each commit and abort is counted exactly once
+        // and every transaction attempt gets its own span
+        let txn = self.neb_client.transaction(wrapper).then(move |result| {
+            match result {
+                Ok(_) => counters.on_commit(&pending),
+                Err(TxnError::Aborted(_)) => counters.on_abort(),
+                Err(_) => {}
+            }
+            result
+        });
+        traced("transaction", None, txn, txn_outcome)
+    }
+    pub fn graph_transaction_retry<TFN, TR>(this: Arc<Self>, max_retries: usize, func: TFN)
+        -> impl Future<Item = TR, Error = TxnError>
+        where TFN: Fn(&GraphTransaction) -> Result<TR, TxnError>, TR: 'static, TFN: 'static
+    {
+        let func = Arc::new(func);
+        async_block! {
+            let mut retries = 0;
+            loop {
+                let func = func.clone();
+                // an abort returned by the closure itself, from txn.abort() or a deliberate
+                // Err(TxnError::Aborted), is the caller's decision and is not replayed
+                let aborted_by_caller = Rc::new(cell::Cell::new(false));
+                let aborted_by_caller_ref = aborted_by_caller.clone();
+                let result = await!(this.graph_transaction(move |txn| {
+                    let result = func(txn);
+                    if let Err(TxnError::Aborted(_)) = result {
+                        aborted_by_caller_ref.set(true);
+                    }
+                    result
+                }));
+                match result {
+                    // retried right away, sleeping here would block the executor
+                    Err(TxnError::Aborted(_)) if !aborted_by_caller.get() && retries < max_retries => retries += 1,
+                    result => return result
+                }
+            }
+        }
     }
     pub fn link<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
         -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
@@ -358,6 +1125,72 @@ impl GraphInner {
             txn.link(from_id, schema_id, to_id, body.clone())
         })
     }
+    pub fn link_unique<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.link_unique(from_id, schema_id, to_id, body.clone())
+        })
+    }
+    pub fn link_no_self_loop<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.link_no_self_loop(from_id, schema_id, to_id, body.clone())
+        })
+    }
+    pub fn link_checked<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.link_checked(from_id, schema_id, to_id, body.clone())
+        })
+    }
+    pub fn link_bilateral<V, S>(&self, a: V, schema: S, b: V, body: Map)
+        -> impl Future<Item = Result<edge::Edge, LinkVerticesError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let a_id = a.to_id();
+        let b_id = b.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.link_bilateral(a_id, schema_id, b_id, body.clone())
+        })
+    }
+    pub fn unlink<V, S>(&self, from: V, schema: S, to: V)
+        -> impl Future<Item = Result<(), EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.unlink(from_id, schema_id, to_id)
+        })
+    }
+    pub fn are_linked<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection)
+        -> impl Future<Item = Result<bool, edge::EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.are_linked(from_id, schema_id, to_id, ed)
+        })
+    }
     pub fn degree<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection)
         -> impl Future<Item = Result<usize, edge::EdgeError>, Error = TxnError>
         where V: ToVertexId, S: ToSchemaId
@@ -391,6 +1224,29 @@ impl GraphInner {
                 }
             })
     }
+    pub fn neighbourhoods_skip_missing<V, S, F>(this: Arc<Self>, vertex: V, schema: S, ed: EdgeDirection, filter: &Option<F>)
+        -> impl Future<Item = Result<(Vec<(Vertex, edge::Edge)>, Vec<Id>), NeighbourhoodError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId, F: Expr
+    {
+        let vertex_id = vertex.to_id();
+        let schema_id = schema.to_id(&this.schemas);
+        future::result(parse_optional_expr(filter))
+            .map_err(|e| {
+                NeighbourhoodError::FilterEvalError(e)
+            })
+            .then(move |filter_sexpr_result| {
+                async_block! {
+                    match filter_sexpr_result {
+                        Ok(filter_sexpr) => {
+                            return await!(this.graph_transaction(move |txn| {
+                                txn.neighbourhoods_skip_missing(vertex_id, schema_id, ed, &filter_sexpr)
+                            }))
+                        },
+                        Err(e) => return Ok(Err(e))
+                    }
+                }
+            })
+    }
     pub fn edges<V, S, F>(this: Arc<Self>, vertex: V, schema: S, ed: EdgeDirection, filter: &Option<F>)
         -> impl Future<Item = Result<Vec<edge::Edge>, EdgeError>, Error = TxnError>
         where V: ToVertexId, S: ToSchemaId, F: Expr
@@ -416,23 +1272,186 @@ impl GraphInner {
     }
 }
 
+// Edges of one vertex in every direction an edge schema allows,
+// directed schemas leave undirected empty and undirected schemas leave the other two empty
+#[derive(Debug)]
+pub struct Adjacency {
+    pub inbound: Vec<edge::Edge>,
+    pub outbound: Vec<edge::Edge>,
+    pub undirected: Vec<edge::Edge>
+}
+
+impl Adjacency {
+    pub fn degree(&self, ed: EdgeDirection) -> usize {
+        match ed {
+            EdgeDirection::Inbound => self.inbound.len(),
+            EdgeDirection::Outbound => self.outbound.len(),
+            EdgeDirection::Undirected => self.undirected.len()
+        }
+    }
+    pub fn total_degree(&self) -> usize {
+        self.inbound.len() + self.outbound.len() + self.undirected.len()
+    }
+}
+
+pub struct NeighbourhoodIterator<'a> {
+    ids: id_list::IdListIterator<'a>,
+    txn: &'a Transaction,
+    schemas: Arc<SchemaContainer>,
+    vertex_id: Id,
+    vertex_field: u64,
+    schema_id: u32,
+    deadline: Option<Deadline>
+}
+
+impl <'a> Iterator for NeighbourhoodIterator<'a> {
+    type Item = Result<Result<edge::Edge, EdgeError>, TxnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = check_deadline(self.txn, &self.deadline) {
+            return Some(Err(e));
+        }
+        let id = self.ids.next();
+        id.map(|id| edge::from_id(
+            &self.vertex_id, self.vertex_field, self.schema_id, &self.schemas, self.txn, &id
+        ))
+    }
+}
+
 pub struct GraphTransaction<'a> {
     pub neb_txn: &'a Transaction,
-    schemas: Arc<SchemaContainer>
+    schemas: Arc<SchemaContainer>,
+    deadline: Option<Deadline>,
+    metrics: Rc<metrics::PendingMetrics>
+}
+
+// expired is only set by check_deadline, so the caller can tell its abort from any other
+#[derive(Clone)]
+struct Deadline {
+    at: Instant,
+    expired: Rc<cell::Cell<bool>>
+}
+
+fn check_deadline(txn: &Transaction, deadline: &Option<Deadline>) -> Result<(), TxnError> {
+    match deadline {
+        &Some(ref deadline) if Instant::now() >= deadline.at => {
+            deadline.expired.set(true);
+            txn.abort()?;
+            Err(TxnError::Aborted(None))
+        },
+        _ => Ok(())
+    }
 }
 
 impl <'a>GraphTransaction<'a> {
+    fn check_deadline(&self) -> Result<(), TxnError> {
+        check_deadline(self.neb_txn, &self.deadline)
+    }
+    // edge lists are only walked through here, so every walk starts with a deadline check
+    fn edge_list(&self, vertex_id: &Id, field: u64, schema_id: u32) -> Result<id_list::IdList<'a>, TxnError> {
+        self.check_deadline()?;
+        Ok(id_list::IdList::from_txn_and_container(self.neb_txn, vertex_id, field, schema_id))
+    }
+    pub fn flatten<T, E>(result: Result<Result<T, E>, TxnError>) -> Result<T, GraphOpError<E>> {
+        match result {
+            Ok(Ok(v)) => Ok(v),
+            Ok(Err(e)) => Err(GraphOpError::OpError(e)),
+            Err(e) => Err(GraphOpError::TxnError(e))
+        }
+    }
     pub fn new_vertex<S>(&self, schema: S, data: Map)
         -> Result<Result<Vertex, NewVertexError>, TxnError>
         where S: ToSchemaId
     {
         let vertex = Vertex::new(schema.to_id(&self.schemas), data);
-        let mut cell = match vertex_to_cell_for_write(&self.schemas, vertex) {
+        let cell = match vertex_to_cell_for_write(&self.schemas, vertex) {
             Ok(cell) => cell, Err(e) => return Ok(Err(e))
         };
+        // a transactional write would overwrite, keep failing on duplicates like write_cell does
+        if self.neb_txn.read_selected(&cell.id(), &Vec::new())?.is_some() {
+            return Ok(Err(NewVertexError::WriteError(WriteError::CellAlreadyExisted)));
+        }
+        self.write_new_vertex_cell(cell)
+    }
+    pub fn new_vertex_with_key<S, K>(&self, schema: S, key: &K, data: Map, overwrite: bool)
+        -> Result<Result<Vertex, NewVertexError>, TxnError>
+        where S: ToSchemaId, K: Serialize
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        let id = Cell::encode_cell_key(schema_id, key);
+        self.new_vertex_with_id(schema_id, id, data, overwrite)
+    }
+    fn new_vertex_with_id(&self, schema_id: u32, id: Id, data: Map, overwrite: bool)
+        -> Result<Result<Vertex, NewVertexError>, TxnError>
+    {
+        let mut cell = match vertex_to_cell_for_write(&self.schemas, Vertex::new(schema_id, data)) {
+            Ok(cell) => Cell::new_with_id(schema_id, &id, cell.data),
+            Err(e) => return Ok(Err(e))
+        };
+        let existing = match self.neb_txn.read(&id)? {
+            Some(_) if !overwrite => return Ok(Err(NewVertexError::VertexExisted(id))),
+            Some(existing) => existing,
+            None => return self.write_new_vertex_cell(cell)
+        };
+        // the data is replaced but the edges stay, keep the existing edge lists
+        if let Value::Map(ref mut map) = cell.data {
+            for ed in fields::ALL_DIRECTIONS.iter() {
+                let list = existing.data[ed.as_field()].clone();
+                if let Value::Null = list { continue; }
+                map.insert_key_id(ed.as_field(), list);
+            }
+        }
+        match vertex::txn_update_cell(self.neb_txn, &self.schemas, &cell)? {
+            Ok(()) => Ok(Ok(vertex::cell_to_vertex(cell))),
+            Err(e) => Ok(Err(NewVertexError::IdListError(e)))
+        }
+    }
+    // the cell is written last, so a registry or index failure never leaves a vertex behind
+    fn write_new_vertex_cell(&self, cell: Cell) -> Result<Result<Vertex, NewVertexError>, TxnError> {
+        match registry::register(self.neb_txn, cell.header.schema, &cell.id())? {
+            Ok(()) => {}, Err(e) => return Ok(Err(NewVertexError::IdListError(e)))
+        }
+        match index::index_cell(self.neb_txn, &self.schemas, &cell)? {
+            Ok(()) => {}, Err(e) => return Ok(Err(NewVertexError::IdListError(e)))
+        }
         self.neb_txn.write(&cell)?;
+        self.metrics.vertex_created();
         Ok(Ok(vertex::cell_to_vertex(cell)))
     }
+    pub fn upsert_vertex<K, S, U>(&self, schema: S, key: K, data: Map, update: U)
+        -> Result<Result<Vertex, NewVertexError>, TxnError>
+        where K: ToValue, S: ToSchemaId, U: Fn(Vertex) -> Option<Vertex>
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        let id = Cell::encode_cell_key(schema_id, &key.value());
+        self.upsert_vertex_by_id(schema_id, id, data, update)
+    }
+    fn upsert_vertex_by_id<U>(&self, schema_id: u32, id: Id, data: Map, update: U)
+        -> Result<Result<Vertex, NewVertexError>, TxnError>
+        where U: Fn(Vertex) -> Option<Vertex>
+    {
+        if let Some(existing) = self.read_vertex(&id)? {
+            // the closure consumes the vertex, keep a copy to hand back when it changes nothing
+            let unchanged = existing.cell.clone();
+            return match update(existing) {
+                Some(updated) => {
+                    let cell = vertex::vertex_to_cell(updated);
+                    match vertex::txn_update_cell(self.neb_txn, &self.schemas, &cell)? {
+                        Ok(()) => Ok(Ok(vertex::cell_to_vertex(cell))),
+                        Err(e) => Ok(Err(NewVertexError::IdListError(e)))
+                    }
+                },
+                None => Ok(Ok(vertex::cell_to_vertex(unchanged)))
+            };
+        }
+        let cell = match vertex_to_cell_for_write(&self.schemas, Vertex::new(schema_id, data)) {
+            Ok(cell) => cell, Err(e) => return Ok(Err(e))
+        };
+        if cell.id() != id {
+            return Ok(Err(NewVertexError::KeyMismatch));
+        }
+        self.write_new_vertex_cell(cell)
+    }
     pub fn remove_vertex<V>(&self, vertex: V)
         -> Result<Result<(), vertex::RemoveError>, TxnError> where V: ToVertexId
     {
@@ -458,24 +1477,168 @@ impl <'a>GraphTransaction<'a> {
             Some(_) => return Ok(Err(LinkVerticesError::SchemaNotEdge)),
             None => return Ok(Err(LinkVerticesError::EdgeSchemaNotFound))
         };
-        match edge_attr.edge_type {
+        if let (true, Some(body)) = (edge_attr.has_body, body.as_ref()) {
+            if let Err(e) = validate_edge_body(&self.schemas, schema_id, edge_attr.edge_type, body) {
+                return Ok(Err(LinkVerticesError::InvalidBody(e)));
+            }
+        }
+        for id in &[from_id, to_id] {
+            match registry::register_edge_user(self.neb_txn, schema_id, id)? {
+                Ok(()) => {}, Err(e) => return Ok(Err(LinkVerticesError::EdgeError(EdgeError::IdListError(e))))
+            }
+        }
+        let linked = match edge_attr.edge_type {
             edge::EdgeType::Directed =>
-                Ok(edge::directed::DirectedEdge::link(from_id, to_id, body, &self.neb_txn, schema_id, &self.schemas)?
-                    .map_err(LinkVerticesError::EdgeError).map(edge::Edge::Directed)),
+                edge::directed::DirectedEdge::link(from_id, to_id, body, &self.neb_txn, schema_id, &self.schemas)?
+                    .map_err(LinkVerticesError::EdgeError).map(edge::Edge::Directed),
 
             edge::EdgeType::Undirected =>
-                Ok(edge::undirectd::UndirectedEdge::link(from_id, to_id, body, &self.neb_txn, schema_id, &self.schemas)?
-                    .map_err(LinkVerticesError::EdgeError).map(edge::Edge::Undirected))
+                edge::undirectd::UndirectedEdge::link(from_id, to_id, body, &self.neb_txn, schema_id, &self.schemas)?
+                    .map_err(LinkVerticesError::EdgeError).map(edge::Edge::Undirected)
+        };
+        if linked.is_ok() {
+            self.metrics.edge_linked();
         }
+        Ok(linked)
     }
 
-    pub fn update_vertex<V, U>(&self, vertex: V, update: U) -> Result<(), TxnError>
+    // Bilateral edges are undirected edges with a body cell that holds both endpoints.
+    // Use them when the relation itself carries data; plain undirected edges are cheaper otherwise.
+    pub fn link_bilateral<V, S>(&self, a: V, schema: S, b: V, body: Map)
+        -> Result<Result<edge::Edge, LinkVerticesError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        match self.schemas.schema_type(schema_id) {
+            Some(SchemaType::Edge(ea)) => {
+                if ea.edge_type != edge::EdgeType::Undirected || !ea.has_body {
+                    return Ok(Err(LinkVerticesError::NotBilateralEdge));
+                }
+            },
+            Some(_) => return Ok(Err(LinkVerticesError::SchemaNotEdge)),
+            None => return Ok(Err(LinkVerticesError::EdgeSchemaNotFound))
+        }
+        self.link(a, schema_id, b, Some(body))
+    }
+
+    // Links the vertices only when no edge of this schema exists between them yet,
+    // otherwise the existing edge is returned untouched.
+    pub fn link_unique<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> Result<Result<edge::Edge, LinkVerticesError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        match self.schemas.schema_type(schema_id) {
+            Some(SchemaType::Edge(_)) => {},
+            Some(_) => return Ok(Err(LinkVerticesError::SchemaNotEdge)),
+            None => return Ok(Err(LinkVerticesError::EdgeSchemaNotFound))
+        }
+        match self.find_edge(&from_id, schema_id, &to_id)? {
+            Ok(Some(edge)) => Ok(Ok(edge)),
+            Ok(None) => self.link(&from_id, schema_id, &to_id, body),
+            Err(e) => Ok(Err(LinkVerticesError::EdgeError(e)))
+        }
+    }
+
+    // Same as link, but refuses to connect a vertex to itself
+    pub fn link_no_self_loop<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> Result<Result<edge::Edge, LinkVerticesError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        if from_id == to_id {
+            return Ok(Err(LinkVerticesError::SelfLoopNotAllowed));
+        }
+        self.link(&from_id, schema, &to_id, body)
+    }
+
+    // Both endpoints are read before linking, cells of other schema types do not count as vertices
+    pub fn link_checked<V, S>(&self, from: V, schema: S, to: V, body: Option<Map>)
+        -> Result<Result<edge::Edge, LinkVerticesError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        for id in &[from_id, to_id] {
+            let is_vertex = match self.neb_txn.read(id)? {
+                Some(cell) => self.schemas.schema_type(cell.header.schema) == Some(SchemaType::Vertex),
+                None => false
+            };
+            if !is_vertex {
+                return Ok(Err(LinkVerticesError::EndpointNotFound(*id)));
+            }
+        }
+        self.link(&from_id, schema, &to_id, body)
+    }
+
+    pub fn find_edge<V, S>(&self, from: V, schema: S, to: V)
+        -> Result<Result<Option<edge::Edge>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = &from.to_id();
+        let to_id = &to.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let vertex_field = match edge_attr.edge_type {
+            edge::EdgeType::Directed => EdgeDirection::Outbound.as_field(),
+            edge::EdgeType::Undirected => EdgeDirection::Undirected.as_field()
+        };
+        let ids = match self.edge_list(from_id, vertex_field, schema_id)?.all()? {
+            Err(e) => return Ok(Err(EdgeError::IdListError(e))), Ok(ids) => ids
+        };
+        for id in ids {
+            self.check_deadline()?;
+            let edge = match edge::from_id(
+                from_id, vertex_field, schema_id, &self.schemas, self.neb_txn, &id
+            )? {
+                Ok(e) => e, Err(e) => return Ok(Err(e))
+            };
+            if edge.one_opposite_id_vertex_id(from_id) == Some(to_id) {
+                return Ok(Ok(Some(edge)));
+            }
+        }
+        Ok(Ok(None))
+    }
+
+    pub fn unlink<V, S>(&self, from: V, schema: S, to: V)
+        -> Result<Result<(), EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        match self.find_edge(from, schema, to)? {
+            Ok(Some(edge)) => edge.remove(self.neb_txn),
+            Ok(None) => Ok(Err(EdgeError::EdgeNotFound)),
+            Err(e) => Ok(Err(e))
+        }
+    }
+
+    // Returns the number of removed edges, both endpoints' id lists are updated for each
+    pub fn unlink_where<V, S, F>(&self, vertex: V, schema: S, ed: EdgeDirection, pred: F)
+        -> Result<Result<usize, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId, F: Fn(&edge::Edge) -> bool
+    {
+        let edges = match self.neighbourhoods_where(vertex, schema, ed, pred)? {
+            Ok(edges) => edges, Err(e) => return Ok(Err(e))
+        };
+        let count = edges.len();
+        for edge in edges {
+            match edge.remove(self.neb_txn)? {
+                Ok(()) => {}, Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(count))
+    }
+
+    pub fn update_vertex<V, U>(&self, vertex: V, update: U) -> Result<Result<(), id_list::IdListError>, TxnError>
         where V: ToVertexId, U: Fn(Vertex) -> Option<Vertex>
     {
-        vertex::txn_update(self.neb_txn, vertex, &update)
+        vertex::txn_update(self.neb_txn, &self.schemas, vertex, &update)
     }
     pub fn update_vertex_by_key<K, U, S>(&self, schema: S, key: K, update: U)
-        -> Result<(), TxnError>
+        -> Result<Result<(), id_list::IdListError>, TxnError>
         where K: ToValue, S: ToSchemaId, U: Fn(Vertex) -> Option<Vertex>
     {
         let id = Cell::encode_cell_key(schema.to_id(&self.schemas), &key.value());
@@ -487,6 +1650,65 @@ impl <'a>GraphTransaction<'a> {
     {
         self.neb_txn.read(&vertex.to_id()).map(|c| c.map(vertex::cell_to_vertex))
     }
+    // Selects no fields, so only the presence of the cell is fetched
+    pub fn vertex_exists<V>(&self, vertex: V) -> Result<bool, TxnError> where V: ToVertexId {
+        Ok(self.neb_txn.read_selected(&vertex.to_id(), &Vec::new())?.is_some())
+    }
+
+    pub fn read_edge<S>(&self, edge_id: &Id, schema: S)
+        -> Result<Result<Option<edge::Edge>, EdgeError>, TxnError> where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        match self.neb_txn.read(edge_id)? {
+            Some(cell) => {
+                if cell.header.schema != schema_id {
+                    return Ok(Err(EdgeError::WrongSchema));
+                }
+                Ok(edge::from_cell(cell, &self.schemas).map(Some))
+            },
+            None => Ok(Ok(None))
+        }
+    }
+
+    pub fn update_edge<S, U>(&self, edge_id: &Id, schema: S, update: U)
+        -> Result<Result<(), EdgeError>, TxnError>
+        where S: ToSchemaId, U: Fn(edge::Edge) -> Option<edge::Edge>
+    {
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        if !edge_attr.has_body {
+            return Ok(Err(EdgeError::SimpleEdgeShouldNotHaveBody));
+        }
+        let edge = match self.read_edge(edge_id, schema_id)? {
+            Ok(Some(edge)) => edge,
+            Ok(None) => return Ok(Err(EdgeError::CellNotFound)),
+            Err(e) => return Ok(Err(e))
+        };
+        let endpoint_ids = edge_endpoint_ids(edge_attr.edge_type);
+        let endpoints: Vec<Option<Id>> = endpoint_ids.iter().map(|&field| value::as_id(&edge[field])).collect();
+        let body = match update(edge).map(|e| e.into_data()) {
+            Some(Some(Cell { data: Value::Map(body), .. })) => body,
+            Some(_) => return Ok(Err(EdgeError::BodyRemoved)),
+            None => return Ok(Ok(()))
+        };
+        // the endpoints are also kept in the vertices' edge lists, so they cannot move here
+        for (&field, endpoint) in endpoint_ids.iter().zip(endpoints) {
+            if value::as_id(body.get_by_key_id(field)) != endpoint {
+                return Ok(Err(EdgeError::EndpointChanged(field)));
+            }
+        }
+        let mut fields = body.clone();
+        for field in &endpoint_ids {
+            fields.map.remove(field);
+        }
+        if let Err(e) = validate_edge_body(&self.schemas, schema_id, edge_attr.edge_type, &fields) {
+            return Ok(Err(EdgeError::InvalidBody(e)));
+        }
+        // written back under the edge's own id and schema whatever the closure did to the header
+        self.neb_txn.update(&Cell::new_with_id(schema_id, edge_id, Value::Map(body)))?;
+        Ok(Ok(()))
+    }
 
     pub fn get_vertex<K, S>(&self, schema: u32, key: K) -> Result<Option<Vertex>, TxnError>
         where K: ToValue, S: ToSchemaId
@@ -495,6 +1717,29 @@ impl <'a>GraphTransaction<'a> {
         self.read_vertex(&id)
     }
 
+    pub fn get_vertex_by_value<S>(&self, schema: S, key: &Value) -> Result<Option<Vertex>, TxnError>
+        where S: ToSchemaId
+    {
+        let id = Cell::encode_cell_key(schema.to_id(&self.schemas), key);
+        self.read_vertex(&id)
+    }
+
+    // results follow the order of vertex_ids, vertices without edges get an empty list
+    pub fn neighbourhoods_many<S>(&self, vertex_ids: &[Id], schema: S, ed: EdgeDirection)
+        -> Result<Result<Vec<(Id, Vec<edge::Edge>)>, EdgeError>, TxnError>
+        where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        let mut result = Vec::with_capacity(vertex_ids.len());
+        for vertex_id in vertex_ids {
+            match self.edges(vertex_id, schema_id, ed, &None)? {
+                Ok(edges) => result.push((*vertex_id, edges)),
+                Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(result))
+    }
+
     pub fn edges<V, S>(
         &self, vertex: V, schema: S, ed: EdgeDirection, filter: &Option<Vec<SExpr>>
     ) -> Result<Result<Vec<edge::Edge>, edge::EdgeError>, TxnError>
@@ -503,12 +1748,12 @@ impl <'a>GraphTransaction<'a> {
         let vertex_field = ed.as_field();
         let schema_id = schema.to_id(&self.schemas);
         let vertex_id = &vertex.to_id();
-        match id_list::IdList::from_txn_and_container
-            (self.neb_txn, vertex_id, vertex_field, schema_id).iter()? {
+        match self.edge_list(vertex_id, vertex_field, schema_id)?.iter()? {
             Err(e) => Ok(Err(edge::EdgeError::IdListError(e))),
             Ok(ids) => Ok(Ok({
                 let mut edges = Vec::new();
                 for id in ids {
+                    self.check_deadline()?;
                     match edge::from_id(
                         vertex_id, vertex_field, schema_id, &self.schemas, self.neb_txn, &id
                     )? {
@@ -527,27 +1772,118 @@ impl <'a>GraphTransaction<'a> {
         }
     }
 
+    pub fn adjacency<V, S>(&self, vertex: V, schema: S)
+        -> Result<Result<Adjacency, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let vertex_id = vertex.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut adjacency = Adjacency {
+            inbound: Vec::new(), outbound: Vec::new(), undirected: Vec::new()
+        };
+        let directions = match edge_attr.edge_type {
+            edge::EdgeType::Directed => vec![EdgeDirection::Inbound, EdgeDirection::Outbound],
+            edge::EdgeType::Undirected => vec![EdgeDirection::Undirected]
+        };
+        for ed in directions {
+            let edges = match self.edges(&vertex_id, schema_id, ed, &None)? {
+                Ok(edges) => edges, Err(e) => return Ok(Err(e))
+            };
+            match ed {
+                EdgeDirection::Inbound => adjacency.inbound = edges,
+                EdgeDirection::Outbound => adjacency.outbound = edges,
+                EdgeDirection::Undirected => adjacency.undirected = edges
+            }
+        }
+        Ok(Ok(adjacency))
+    }
+
+    pub fn neighbourhood_iter<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection)
+        -> Result<Result<NeighbourhoodIterator<'a>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let vertex_field = ed.as_field();
+        let schema_id = schema.to_id(&self.schemas);
+        let vertex_id = vertex.to_id();
+        match self.edge_list(&vertex_id, vertex_field, schema_id)?.iter()? {
+            Err(e) => Ok(Err(EdgeError::IdListError(e))),
+            Ok(ids) => Ok(Ok(NeighbourhoodIterator {
+                ids,
+                txn: self.neb_txn,
+                schemas: self.schemas.clone(),
+                vertex_id,
+                vertex_field,
+                schema_id,
+                deadline: self.deadline.clone()
+            }))
+        }
+    }
+
+    pub fn neighbourhoods_where<V, S, F>(&self, vertex: V, schema: S, ed: EdgeDirection, pred: F)
+        -> Result<Result<Vec<edge::Edge>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId, F: Fn(&edge::Edge) -> bool
+    {
+        let edges = match self.neighbourhood_iter(vertex, schema, ed)? {
+            Ok(iter) => iter, Err(e) => return Ok(Err(e))
+        };
+        let mut result = Vec::new();
+        for edge in edges {
+            match edge? {
+                Ok(edge) => {
+                    if pred(&edge) { result.push(edge); }
+                },
+                Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(result))
+    }
+
     pub fn neighbourhoods<V, S>(
         &self, vertex: V, schema: S, ed: EdgeDirection, filter: &Option<Vec<SExpr>>
     )
         -> Result<Result<Vec<(Vertex, edge::Edge)>, NeighbourhoodError>, TxnError>
         where V: ToVertexId, S: ToSchemaId
+    {
+        Ok(self.neighbourhoods_(vertex, schema, ed, filter, false)?.map(|(result, _)| result))
+    }
+
+    // Edges whose opposite vertex is gone are skipped, their ids are returned as orphans
+    pub fn neighbourhoods_skip_missing<V, S>(
+        &self, vertex: V, schema: S, ed: EdgeDirection, filter: &Option<Vec<SExpr>>
+    )
+        -> Result<Result<(Vec<(Vertex, edge::Edge)>, Vec<Id>), NeighbourhoodError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.neighbourhoods_(vertex, schema, ed, filter, true)
+    }
+
+    fn neighbourhoods_<V, S>(
+        &self, vertex: V, schema: S, ed: EdgeDirection, filter: &Option<Vec<SExpr>>, skip_missing: bool
+    )
+        -> Result<Result<(Vec<(Vertex, edge::Edge)>, Vec<Id>), NeighbourhoodError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
     {
         let vertex_field = ed.as_field();
         let schema_id = schema.to_id(&self.schemas);
         let vertex_id = &vertex.to_id();
-        match id_list::IdList::from_txn_and_container
-            (self.neb_txn, vertex_id, vertex_field, schema_id).iter()? {
+        match self.edge_list(vertex_id, vertex_field, schema_id)?.iter()? {
             Err(e) => Ok(Err(NeighbourhoodError::EdgeError(EdgeError::IdListError(e)))),
             Ok(ids) => {
                 let mut result: Vec<(Vertex, edge::Edge)> = Vec::new();
+                let mut orphans = Vec::new();
                 for id in ids {
+                    self.check_deadline()?;
                     match edge::from_id(
                         vertex_id, vertex_field, schema_id, &self.schemas, self.neb_txn, &id
                     )? {
                         Ok(edge) => {
                             let vertex = if let Some(opposite_id) = edge.one_opposite_id_vertex_id(vertex_id) {
-                                if let Some(v) = self.read_vertex(opposite_id)? { v } else {
+                                if let Some(v) = self.read_vertex(opposite_id)? { v } else if skip_missing {
+                                    orphans.push(*opposite_id);
+                                    continue;
+                                } else {
                                     return Ok(Err(NeighbourhoodError::VertexNotFound(*opposite_id)))
                                 }
                             } else { return Ok(Err(NeighbourhoodError::CannotFindOppositeId(*vertex_id))) };
@@ -557,29 +1893,178 @@ impl <'a>GraphTransaction<'a> {
                                 Err(err) => return Ok(Err(NeighbourhoodError::FilterEvalError(err))),
                             }
                         },
+                        // the list item of a simple edge is the opposite vertex itself
+                        Err(EdgeError::CellNotFound) if skip_missing => orphans.push(id),
                         Err(edge_error) => return Ok(Err(NeighbourhoodError::EdgeError(edge_error)))
                     }
                 }
-                return Ok(Ok(result));
+                return Ok(Ok((result, orphans)));
+            }
+        }
+    }
+
+    fn adjacent_ids(&self, vertex_id: &Id, schema_id: u32, edge_attr: &EdgeAttributes, ed: EdgeDirection)
+        -> Result<Result<Vec<Id>, EdgeError>, TxnError>
+    {
+        let mut id_list = self.edge_list(vertex_id, ed.as_field(), schema_id)?;
+        let ids = match id_list.iter()? {
+            Err(e) => return Ok(Err(EdgeError::IdListError(e))), Ok(ids) => ids
+        };
+        let mut result = Vec::new();
+        for id in ids {
+            self.check_deadline()?;
+            if let Some(opposite_id) = edge::opposite_id(vertex_id, &id, edge_attr, self.neb_txn)? {
+                result.push(opposite_id);
+            }
+        }
+        Ok(Ok(result))
+    }
+
+    // Adjacent vertex ids only, no edge bodies are read or edges built
+    pub fn neighbour_ids<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection)
+        -> Result<Result<Vec<Id>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let vertex_id = vertex.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        self.adjacent_ids(&vertex_id, schema_id, &edge_attr, ed)
+    }
+
+    // (from, to, edge) where from is the neighbour for inbound edges and the vertex otherwise,
+    // undirected edges are oriented away from the vertex
+    pub fn oriented_edges<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection)
+        -> Result<Result<Vec<(Id, Id, edge::Edge)>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let vertex_id = vertex.to_id();
+        let edges = match self.edges(&vertex_id, schema, ed, &None)? {
+            Ok(edges) => edges, Err(e) => return Ok(Err(e))
+        };
+        let mut result = Vec::with_capacity(edges.len());
+        for edge in edges {
+            let neighbour_id = match edge.one_opposite_id_vertex_id(&vertex_id) {
+                Some(id) => *id, None => return Ok(Err(EdgeError::WrongVertexField))
+            };
+            let (from_id, to_id) = match ed {
+                EdgeDirection::Inbound => (neighbour_id, vertex_id),
+                _ => (vertex_id, neighbour_id)
+            };
+            result.push((from_id, to_id, edge));
+        }
+        Ok(Ok(result))
+    }
+
+    pub fn latest_neighbour_ids<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection, limit: usize)
+        -> Result<Result<Vec<Id>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let vertex_id = &vertex.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let ids = match self.edge_list(vertex_id, ed.as_field(), schema_id)?.all_reversed()? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        let mut neighbours = Vec::new();
+        for id in ids.into_iter().take(limit) {
+            self.check_deadline()?;
+            if let Some(opposite_id) = edge::opposite_id(vertex_id, &id, &edge_attr, self.neb_txn)? {
+                neighbours.push(opposite_id);
+            }
+        }
+        Ok(Ok(neighbours))
+    }
+
+    // Returns a window of neighbour ids along with the total number of entries in the list
+    pub fn neighbour_ids_page<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection, offset: usize, limit: usize)
+        -> Result<Result<(Vec<Id>, usize), EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let vertex_id = &vertex.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut id_list = self.edge_list(vertex_id, ed.as_field(), schema_id)?;
+        let total = match id_list.count()? {
+            Ok(count) => count, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        let ids = match id_list.page(offset, limit)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        let mut neighbours = Vec::with_capacity(ids.len());
+        for id in ids {
+            self.check_deadline()?;
+            if let Some(opposite_id) = edge::opposite_id(vertex_id, &id, &edge_attr, self.neb_txn)? {
+                neighbours.push(opposite_id);
             }
         }
+        Ok(Ok((neighbours, total)))
+    }
+
+    pub fn are_linked<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection)
+        -> Result<Result<bool, edge::EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = &from.to_id();
+        let to_id = &to.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut id_list = self.edge_list(from_id, ed.as_field(), schema_id)?;
+        if !edge_attr.has_body { // simple edges keep the opposite vertex id in the list
+            return Ok(id_list.contains(to_id)?.map_err(EdgeError::IdListError));
+        }
+        let ids = match id_list.iter()? {
+            Err(e) => return Ok(Err(EdgeError::IdListError(e))), Ok(ids) => ids
+        };
+        for id in ids {
+            self.check_deadline()?;
+            if edge::opposite_id(from_id, &id, &edge_attr, self.neb_txn)? == Some(*to_id) {
+                return Ok(Ok(true));
+            }
+        }
+        Ok(Ok(false))
     }
 
     pub fn degree<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection)
         -> Result<Result<usize, edge::EdgeError>, TxnError>
         where V: ToVertexId, S: ToSchemaId
     {
-        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+        let (schema_id, _) = match edge_attr_from_schema(schema, &self.schemas) {
             Err(e) => return Ok(Err(e)), Ok(t) => t
         };
         let vertex_field = ed.as_field();
         let vertex_id = &vertex.to_id();
-        match id_list::IdList::from_txn_and_container
-            (self.neb_txn, vertex_id, vertex_field, schema_id).count()? {
+        match self.edge_list(vertex_id, vertex_field, schema_id)?.count()? {
             Err(e) => Ok(Err(edge::EdgeError::IdListError(e))),
             Ok(count) => Ok(Ok(count))
         }
     }
+    pub fn weighted_degree<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection, weight_field: u64, missing_as_zero: bool)
+        -> Result<Result<f64, edge::EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let (schema_id, _) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let edges = match self.neighbourhood_iter(vertex, schema_id, ed)? {
+            Ok(iter) => iter, Err(e) => return Ok(Err(e))
+        };
+        let mut total = 0f64;
+        for edge in edges {
+            let edge = match edge? {
+                Ok(edge) => edge, Err(e) => return Ok(Err(e))
+            };
+            match edge.weight(weight_field) {
+                Ok(weight) => total += weight,
+                Err(EdgeError::FieldMissing(_)) if missing_as_zero => {},
+                Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(total))
+    }
 }
 
 pub fn edge_attr_from_schema<S>(schema: S, schemas: &Arc<SchemaContainer>)
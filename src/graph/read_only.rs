@@ -0,0 +1,52 @@
+use neb::ram::cell::Cell;
+use neb::client::transaction::TxnError;
+use neb::dovahkiin::types::ToValue;
+use neb::dovahkiin::expr::SExpr;
+use futures::prelude::*;
+
+use server::schema::ToSchemaId;
+use super::{Graph, GraphTransaction, EdgeDirection, NeighbourhoodError};
+use super::vertex::{Vertex, ToVertexId};
+use super::edge;
+
+// Only exposes reads, so closures handed to read_transaction cannot write by mistake.
+pub struct ReadOnlyGraphTransaction<'a, 'b: 'a> {
+    txn: &'a GraphTransaction<'b>
+}
+
+impl Graph {
+    pub fn read_transaction<TFN, TR>(&self, func: TFN)
+        -> impl Future<Item = TR, Error = TxnError>
+        where TFN: Fn(&ReadOnlyGraphTransaction) -> Result<TR, TxnError>, TR: 'static, TFN: 'static
+    {
+        self.graph_transaction(move |txn| func(&ReadOnlyGraphTransaction { txn }))
+    }
+}
+
+impl <'a, 'b> ReadOnlyGraphTransaction<'a, 'b> {
+    pub fn read_vertex<V>(&self, vertex: V)
+        -> Result<Option<Vertex>, TxnError> where V: ToVertexId
+    {
+        self.txn.read_vertex(vertex)
+    }
+    pub fn get_vertex<K, S>(&self, schema: S, key: K) -> Result<Option<Vertex>, TxnError>
+        where K: ToValue, S: ToSchemaId
+    {
+        let id = Cell::encode_cell_key(schema.to_id(&self.txn.schemas), &key.value());
+        self.txn.read_vertex(&id)
+    }
+    pub fn neighbourhoods<V, S>(
+        &self, vertex: V, schema: S, ed: EdgeDirection, filter: &Option<Vec<SExpr>>
+    )
+        -> Result<Result<Vec<(Vertex, edge::Edge)>, NeighbourhoodError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.txn.neighbourhoods(vertex, schema, ed, filter)
+    }
+    pub fn degree<V, S>(&self, vertex: V, schema: S, ed: EdgeDirection)
+        -> Result<Result<usize, edge::EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.txn.degree(vertex, schema, ed)
+    }
+}
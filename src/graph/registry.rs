@@ -0,0 +1,379 @@
+use neb::ram::schema::Field;
+use neb::ram::types::{TypeId, Id, Map, Value, key_hash};
+use neb::ram::cell::Cell;
+use neb::client::transaction::{Transaction, TxnError};
+use server::schema::ToSchemaId;
+use futures::prelude::*;
+
+use super::{Graph, GraphInner, GraphTransaction};
+use super::id_list::{IdList, IdListIterator, IdListError};
+use super::id_set::IdSet;
+use super::fields::ALL_DIRECTIONS;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+pub const VERTICES_KEY: &'static str = "_vertices";
+
+pub static VERTEX_REGISTRY_SCHEMA_ID: u32 = 200;
+
+// Vertices of a schema are spread over this many id lists by their id, so concurrent
+// writers of one schema rarely append to the same segment
+pub const REGISTRY_SHARDS: u64 = 16;
+
+lazy_static! {
+    pub static ref VERTEX_REGISTRY: Field = Field::new("*", TypeId::Map as u32, false, false, Some(vec![
+        Field::new(&String::from(VERTICES_KEY), TypeId::Id as u32, false, false, None)
+    ]));
+    pub static ref VERTICES_KEY_ID: u64 = key_hash(&String::from(VERTICES_KEY));
+}
+
+const VERTEX_SETS: &'static str = "VERTEXSET";
+const EDGE_USER_SETS: &'static str = "EDGEUSERSET";
+
+// the higher part is hashed so the shards of a schema are placed on different servers
+fn shard_id(kind: &str, schema_id: u32, shard: u64) -> Id {
+    Id::new(key_hash(&format!("{}-{}-{}", kind, schema_id, shard)), shard)
+}
+
+// Only vertices written through the registry are listed in it. The coverage cell marks a
+// schema whose every vertex is known to be listed: it is written when the schema is created,
+// or by a backfill for schemas that already had vertices. Enumerating a schema without it
+// is an error instead of a silently short list.
+// Edge schemas are covered the same way, for the set of vertices that have their edges.
+fn coverage_id(kind: &str, schema_id: u32) -> Id {
+    Id::new(key_hash(&format!("{}-{}-covered", kind, schema_id)), REGISTRY_SHARDS)
+}
+
+fn registry_cell(id: &Id) -> Cell {
+    let mut data = Map::new();
+    data.insert_key_id(*VERTICES_KEY_ID, Value::Id(Id::unit_id()));
+    Cell::new_with_id(VERTEX_REGISTRY_SCHEMA_ID, id, Value::Map(data))
+}
+
+pub fn coverage_cell(schema_id: u32) -> Cell {
+    registry_cell(&coverage_id(VERTEX_SETS, schema_id))
+}
+
+pub fn edge_coverage_cell(edge_schema_id: u32) -> Cell {
+    registry_cell(&coverage_id(EDGE_USER_SETS, edge_schema_id))
+}
+
+fn cell_existed(txn: &Transaction, id: &Id) -> Result<bool, TxnError> {
+    Ok(txn.read_selected(id, &vec![*VERTICES_KEY_ID])?.is_some())
+}
+
+fn shard_of(vertex_id: &Id) -> u64 {
+    vertex_id.lower % REGISTRY_SHARDS
+}
+
+fn shard_set<'a>(txn: &'a Transaction, kind: &str, schema_id: u32, shard: u64) -> IdSet<'a> {
+    IdSet::new(txn, &shard_id(kind, schema_id, shard))
+}
+
+fn check_covered(txn: &Transaction, kind: &str, schema_id: u32) -> Result<Result<(), IdListError>, TxnError> {
+    if cell_existed(txn, &coverage_id(kind, schema_id))? {
+        Ok(Ok(()))
+    } else {
+        Ok(Err(IdListError::RegistryIncomplete(schema_id)))
+    }
+}
+
+fn add_member(txn: &Transaction, kind: &str, schema_id: u32, vertex_id: &Id)
+    -> Result<Result<bool, IdListError>, TxnError>
+{
+    shard_set(txn, kind, schema_id, shard_of(vertex_id)).add(vertex_id)
+}
+
+fn remove_member(txn: &Transaction, kind: &str, schema_id: u32, vertex_id: &Id)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    Ok(shard_set(txn, kind, schema_id, shard_of(vertex_id)).remove(vertex_id)?.map(|_| ()))
+}
+
+fn members(txn: &Transaction, kind: &str, schema_id: u32) -> Result<Result<Vec<Id>, IdListError>, TxnError> {
+    match check_covered(txn, kind, schema_id)? {
+        Ok(()) => {}, Err(e) => return Ok(Err(e))
+    }
+    let mut ids = Vec::new();
+    for shard in 0..REGISTRY_SHARDS {
+        match shard_set(txn, kind, schema_id, shard).all()? {
+            Ok(shard_ids) => ids.extend(shard_ids), Err(e) => return Ok(Err(e))
+        }
+    }
+    Ok(Ok(ids))
+}
+
+fn clear_sets(txn: &Transaction, kind: &str, schema_id: u32) -> Result<Result<(), IdListError>, TxnError> {
+    for shard in 0..REGISTRY_SHARDS {
+        match shard_set(txn, kind, schema_id, shard).clear()? {
+            Ok(()) => {}, Err(e) => return Ok(Err(e))
+        }
+    }
+    if cell_existed(txn, &coverage_id(kind, schema_id))? {
+        txn.remove(&coverage_id(kind, schema_id))?;
+    }
+    Ok(Ok(()))
+}
+
+pub fn register(txn: &Transaction, schema_id: u32, vertex_id: &Id)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    Ok(add_member(txn, VERTEX_SETS, schema_id, vertex_id)?.map(|_| ()))
+}
+
+pub fn unregister(txn: &Transaction, schema_id: u32, vertex_id: &Id)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    remove_member(txn, VERTEX_SETS, schema_id, vertex_id)
+}
+
+pub fn mark_covered(txn: &Transaction, schema_id: u32) -> Result<(), TxnError> {
+    if !cell_existed(txn, &coverage_id(VERTEX_SETS, schema_id))? {
+        txn.write(&coverage_cell(schema_id))?;
+    }
+    Ok(())
+}
+
+// Removes every registry cell of the schema, for schemas that are dropped
+pub fn clear(txn: &Transaction, schema_id: u32) -> Result<Result<(), IdListError>, TxnError> {
+    clear_sets(txn, VERTEX_SETS, schema_id)
+}
+
+// A vertex stays listed as a user of an edge schema after its last edge of it is unlinked,
+// it is only taken out when the vertex is removed
+pub fn register_edge_user(txn: &Transaction, edge_schema_id: u32, vertex_id: &Id)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    Ok(add_member(txn, EDGE_USER_SETS, edge_schema_id, vertex_id)?.map(|_| ()))
+}
+
+pub fn unregister_edge_user(txn: &Transaction, edge_schema_id: u32, vertex_id: &Id)
+    -> Result<Result<(), IdListError>, TxnError>
+{
+    remove_member(txn, EDGE_USER_SETS, edge_schema_id, vertex_id)
+}
+
+pub fn mark_edge_users_covered(txn: &Transaction, edge_schema_id: u32) -> Result<(), TxnError> {
+    if !cell_existed(txn, &coverage_id(EDGE_USER_SETS, edge_schema_id))? {
+        txn.write(&edge_coverage_cell(edge_schema_id))?;
+    }
+    Ok(())
+}
+
+pub fn clear_edge_users(txn: &Transaction, edge_schema_id: u32) -> Result<Result<(), IdListError>, TxnError> {
+    clear_sets(txn, EDGE_USER_SETS, edge_schema_id)
+}
+
+pub fn edge_user_ids(txn: &Transaction, edge_schema_id: u32)
+    -> Result<Result<Vec<Id>, IdListError>, TxnError>
+{
+    members(txn, EDGE_USER_SETS, edge_schema_id)
+}
+
+pub fn vertex_ids(txn: &Transaction, schema_id: u32)
+    -> Result<Result<Vec<Id>, IdListError>, TxnError>
+{
+    members(txn, VERTEX_SETS, schema_id)
+}
+
+pub struct VertexIdIterator<'a> {
+    shards: VecDeque<IdListIterator<'a>>
+}
+
+impl <'a> Iterator for VertexIdIterator<'a> {
+    type Item = Id;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.shards.front_mut() {
+                Some(ids) => if let Some(id) = ids.next() { return Some(id); },
+                None => return None
+            }
+            self.shards.pop_front();
+        }
+    }
+}
+
+pub fn vertex_id_iter<'a>(txn: &'a Transaction, schema_id: u32)
+    -> Result<Result<VertexIdIterator<'a>, IdListError>, TxnError>
+{
+    match check_covered(txn, VERTEX_SETS, schema_id)? {
+        Ok(()) => {}, Err(e) => return Ok(Err(e))
+    }
+    let mut iters = VecDeque::new();
+    for shard in 0..REGISTRY_SHARDS {
+        match shard_set(txn, VERTEX_SETS, schema_id, shard).iter()? {
+            Ok(ids) => iters.push_back(ids), Err(e) => return Ok(Err(e))
+        }
+    }
+    Ok(Ok(VertexIdIterator { shards: iters }))
+}
+
+pub fn vertex_count(txn: &Transaction, schema_id: u32)
+    -> Result<Result<usize, IdListError>, TxnError>
+{
+    match check_covered(txn, VERTEX_SETS, schema_id)? {
+        Ok(()) => {}, Err(e) => return Ok(Err(e))
+    }
+    let mut count = 0;
+    for shard in 0..REGISTRY_SHARDS {
+        match shard_set(txn, VERTEX_SETS, schema_id, shard).count()? {
+            Ok(shard_count) => count += shard_count, Err(e) => return Ok(Err(e))
+        }
+    }
+    Ok(Ok(count))
+}
+
+impl Graph {
+    // Migration for schemas that had vertices before they were registered. neb cannot list
+    // the cells of a schema, so the caller supplies the ids, e.g. derived from their keys.
+    // Ids are registered in transactions of batch_size, the schema is only marked as covered
+    // after the last batch committed. A schema that is already covered is left as it is.
+    pub fn backfill_vertex_registry<S>(&self, schema: S, ids: Vec<Id>, batch_size: usize)
+        -> impl Future<Item = Result<usize, IdListError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        GraphInner::backfill_vertex_registry(self.inner.clone(), schema, ids, batch_size)
+    }
+    // Same migration for the vertices that use an edge schema, the ids given are checked for
+    // edges of it and only those that have some are registered
+    pub fn backfill_edge_registry<S>(&self, edge_schema: S, vertex_ids: Vec<Id>, batch_size: usize)
+        -> impl Future<Item = Result<usize, IdListError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        GraphInner::backfill_edge_registry(self.inner.clone(), edge_schema, vertex_ids, batch_size)
+    }
+}
+
+impl GraphInner {
+    pub fn backfill_vertex_registry<S>(this: Arc<Self>, schema: S, ids: Vec<Id>, batch_size: usize)
+        -> impl Future<Item = Result<usize, IdListError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&this.schemas);
+        let batch_size = batch_size.max(1);
+        async_block! {
+            let covered = await!(this.graph_transaction(move |txn| {
+                cell_existed(txn.neb_txn, &coverage_id(VERTEX_SETS, schema_id))
+            }))?;
+            if covered {
+                return Ok(Ok(0));
+            }
+            let batches: Vec<Vec<Id>> = ids.chunks(batch_size).map(|batch| batch.to_vec()).collect();
+            let mut registered = 0;
+            for batch in batches {
+                match await!(this.graph_transaction(move |txn| txn.register_vertices(schema_id, &batch)))? {
+                    Ok(count) => registered += count, Err(e) => return Ok(Err(e))
+                }
+            }
+            await!(this.graph_transaction(move |txn| mark_covered(txn.neb_txn, schema_id)))?;
+            Ok(Ok(registered))
+        }
+    }
+    pub fn backfill_edge_registry<S>(this: Arc<Self>, edge_schema: S, vertex_ids: Vec<Id>, batch_size: usize)
+        -> impl Future<Item = Result<usize, IdListError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        let schema_id = edge_schema.to_id(&this.schemas);
+        let batch_size = batch_size.max(1);
+        async_block! {
+            let covered = await!(this.graph_transaction(move |txn| {
+                cell_existed(txn.neb_txn, &coverage_id(EDGE_USER_SETS, schema_id))
+            }))?;
+            if covered {
+                return Ok(Ok(0));
+            }
+            let batches: Vec<Vec<Id>> = vertex_ids.chunks(batch_size).map(|batch| batch.to_vec()).collect();
+            let mut registered = 0;
+            for batch in batches {
+                match await!(this.graph_transaction(move |txn| txn.register_edge_users(schema_id, &batch)))? {
+                    Ok(count) => registered += count, Err(e) => return Ok(Err(e))
+                }
+            }
+            await!(this.graph_transaction(move |txn| mark_edge_users_covered(txn.neb_txn, schema_id)))?;
+            Ok(Ok(registered))
+        }
+    }
+}
+
+impl <'a> GraphTransaction<'a> {
+    // Answers false and leaves the registry as it is when a registered vertex still exists
+    pub fn clear_unused_vertex_schema(&self, schema_id: u32) -> Result<Result<bool, IdListError>, TxnError> {
+        let ids = match vertex_ids(self.neb_txn, schema_id)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(e))
+        };
+        for id in ids {
+            self.check_deadline()?;
+            if self.vertex_exists(id)? {
+                return Ok(Ok(false));
+            }
+        }
+        Ok(clear(self.neb_txn, schema_id)?.map(|_| true))
+    }
+    // Answers false and leaves the registry as it is when a registered user still has edges
+    pub fn clear_unused_edge_schema(&self, edge_schema_id: u32) -> Result<Result<bool, IdListError>, TxnError> {
+        match self.edge_schema_in_use(edge_schema_id)? {
+            Ok(false) => {}, Ok(true) => return Ok(Ok(false)), Err(e) => return Ok(Err(e))
+        }
+        Ok(clear_edge_users(self.neb_txn, edge_schema_id)?.map(|_| true))
+    }
+    // Only the vertices registered as users of the schema are checked
+    pub fn edge_schema_in_use(&self, edge_schema_id: u32) -> Result<Result<bool, IdListError>, TxnError> {
+        let ids = match edge_user_ids(self.neb_txn, edge_schema_id)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(e))
+        };
+        for id in ids {
+            match self.has_edges_of(&id, edge_schema_id)? {
+                Ok(false) => {}, Ok(true) => return Ok(Ok(true)), Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(false))
+    }
+    fn has_edges_of(&self, id: &Id, edge_schema_id: u32) -> Result<Result<bool, IdListError>, TxnError> {
+        for ed in ALL_DIRECTIONS.iter() {
+            let field = ed.as_field();
+            match IdList::cell_types(self.neb_txn, id, field)? {
+                Some((_, ref types)) if types.contains(&edge_schema_id) => {},
+                _ => continue
+            }
+            match self.edge_list(id, field, edge_schema_id)?.count()? {
+                Ok(0) => {}, Ok(_) => return Ok(Ok(true)), Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(false))
+    }
+    // Ids that are missing or of another schema are skipped, adding an id that is already
+    // registered is a no-op so it is not counted
+    fn register_vertices(&self, schema_id: u32, ids: &[Id])
+        -> Result<Result<usize, IdListError>, TxnError>
+    {
+        let mut registered = 0;
+        for id in ids {
+            self.check_deadline()?;
+            match self.neb_txn.read(id)? {
+                Some(ref cell) if cell.header.schema == schema_id => {},
+                _ => continue
+            }
+            match add_member(self.neb_txn, VERTEX_SETS, schema_id, id)? {
+                Ok(true) => registered += 1, Ok(false) => {}, Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(registered))
+    }
+    fn register_edge_users(&self, edge_schema_id: u32, ids: &[Id])
+        -> Result<Result<usize, IdListError>, TxnError>
+    {
+        let mut registered = 0;
+        for id in ids {
+            self.check_deadline()?;
+            match self.has_edges_of(id, edge_schema_id)? {
+                Ok(true) => {}, Ok(false) => continue, Err(e) => return Ok(Err(e))
+            }
+            match add_member(self.neb_txn, EDGE_USER_SETS, edge_schema_id, id)? {
+                Ok(true) => registered += 1, Ok(false) => {}, Err(e) => return Ok(Err(e))
+            }
+        }
+        Ok(Ok(registered))
+    }
+}
@@ -0,0 +1,271 @@
+use neb::ram::types::Id;
+use neb::client::transaction::{Transaction, TxnError};
+use futures::prelude::*;
+
+use server::schema::{SchemaContainer, SchemaType, ToSchemaId};
+use super::{Graph, GraphInner, GraphTransaction, EdgeDirection, ReadVertexError, Deadline};
+use super::{edge_attr_from_schema, check_deadline};
+use super::id_list::{IdList, IdListError};
+use super::edge::{self, Edge, EdgeType, EdgeError};
+use super::vertex::{self, Vertex};
+use super::registry::{self, VertexIdIterator};
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+pub struct VertexScanIterator<'a> {
+    ids: VertexIdIterator<'a>,
+    txn: &'a Transaction,
+    deadline: Option<Deadline>
+}
+
+impl <'a> Iterator for VertexScanIterator<'a> {
+    type Item = Result<Vertex, TxnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let txn = self.txn;
+        while let Some(id) = self.ids.next() {
+            if let Err(e) = check_deadline(txn, &self.deadline) {
+                return Some(Err(e));
+            }
+            match txn.read(&id) {
+                Ok(Some(cell)) => return Some(Ok(vertex::cell_to_vertex(cell))),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e))
+            }
+        }
+        None
+    }
+}
+
+// Edges are not registered on their own, simple edges do not even have a cell.
+// They are enumerated from the outbound (or undirected) id lists of every vertex in
+// every vertex schema, holding only the edges of one vertex at a time.
+// Undirected edges are reported once, from the endpoint with the smaller id.
+pub struct EdgeScanIterator<'a> {
+    txn: &'a Transaction,
+    schemas: Arc<SchemaContainer>,
+    schema_id: u32,
+    ed: EdgeDirection,
+    undirected: bool,
+    vertex_schemas: VecDeque<u32>,
+    vertex_ids: Option<VertexIdIterator<'a>>,
+    pending: VecDeque<Edge>,
+    deadline: Option<Deadline>
+}
+
+impl <'a> EdgeScanIterator<'a> {
+    fn next_vertex_id(&mut self) -> Result<Result<Option<Id>, EdgeError>, TxnError> {
+        loop {
+            if let Some(ref mut ids) = self.vertex_ids {
+                if let Some(id) = ids.next() {
+                    return Ok(Ok(Some(id)));
+                }
+            }
+            let vertex_schema_id = match self.vertex_schemas.pop_front() {
+                Some(id) => id, None => return Ok(Ok(None))
+            };
+            self.vertex_ids = match registry::vertex_id_iter(self.txn, vertex_schema_id)? {
+                Ok(ids) => Some(ids), Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+            };
+        }
+    }
+    fn load_edges(&mut self, vertex_id: &Id) -> Result<Result<(), EdgeError>, TxnError> {
+        let field = self.ed.as_field();
+        let ids = match IdList::from_txn_and_container(self.txn, vertex_id, field, self.schema_id).all()? {
+            Ok(ids) => ids,
+            Err(IdListError::ContainerCellNotFound) => return Ok(Ok(())), // removed since it was registered
+            Err(e) => return Ok(Err(EdgeError::IdListError(e)))
+        };
+        for id in ids {
+            let edge = match edge::from_id(vertex_id, field, self.schema_id, &self.schemas, self.txn, &id)? {
+                Ok(edge) => edge, Err(e) => return Ok(Err(e))
+            };
+            if self.undirected {
+                match edge.one_opposite_id_vertex_id(vertex_id) {
+                    Some(opposite_id) if opposite_id < vertex_id => continue,
+                    _ => {}
+                }
+            }
+            self.pending.push_back(edge);
+        }
+        Ok(Ok(()))
+    }
+}
+
+impl <'a> Iterator for EdgeScanIterator<'a> {
+    type Item = Result<Result<Edge, EdgeError>, TxnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(edge) = self.pending.pop_front() {
+                return Some(Ok(Ok(edge)));
+            }
+            let vertex_id = match self.next_vertex_id() {
+                Ok(Ok(Some(id))) => id,
+                Ok(Ok(None)) => return None,
+                Ok(Err(e)) => return Some(Ok(Err(e))),
+                Err(e) => return Some(Err(e))
+            };
+            if let Err(e) = check_deadline(self.txn, &self.deadline) {
+                return Some(Err(e));
+            }
+            match self.load_edges(&vertex_id) {
+                Ok(Ok(())) => {},
+                Ok(Err(e)) => return Some(Ok(Err(e))),
+                Err(e) => return Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Graph {
+    pub fn scan_vertices<S>(&self, schema: S)
+        -> impl Future<Item = Result<Vec<Vertex>, ReadVertexError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        self.inner.scan_vertices(schema)
+    }
+    pub fn count_vertices<S>(&self, schema: S)
+        -> impl Future<Item = Result<u64, ReadVertexError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        self.inner.count_vertices(schema)
+    }
+    pub fn find_vertices<S, F>(&self, schema: S, pred: F)
+        -> impl Future<Item = Result<Vec<Vertex>, ReadVertexError>, Error = TxnError>
+        where S: ToSchemaId, F: Fn(&Vertex) -> bool, F: 'static
+    {
+        self.inner.find_vertices(schema, None, pred)
+    }
+    pub fn find_vertices_limit<S, F>(&self, schema: S, limit: usize, pred: F)
+        -> impl Future<Item = Result<Vec<Vertex>, ReadVertexError>, Error = TxnError>
+        where S: ToSchemaId, F: Fn(&Vertex) -> bool, F: 'static
+    {
+        self.inner.find_vertices(schema, Some(limit), pred)
+    }
+    pub fn scan_edges<S>(&self, edge_schema: S)
+        -> impl Future<Item = Result<Vec<Edge>, EdgeError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        self.inner.scan_edges(edge_schema)
+    }
+}
+
+impl GraphInner {
+    pub fn scan_vertices<S>(&self, schema: S)
+        -> impl Future<Item = Result<Vec<Vertex>, ReadVertexError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            match txn.scan_vertices(schema_id)? {
+                Ok(vertices) => vertices.collect::<Result<Vec<_>, _>>().map(Ok),
+                Err(e) => Ok(Err(e))
+            }
+        })
+    }
+    pub fn count_vertices<S>(&self, schema: S)
+        -> impl Future<Item = Result<u64, ReadVertexError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.count_vertices(schema_id)
+        })
+    }
+    pub fn find_vertices<S, F>(&self, schema: S, limit: Option<usize>, pred: F)
+        -> impl Future<Item = Result<Vec<Vertex>, ReadVertexError>, Error = TxnError>
+        where S: ToSchemaId, F: Fn(&Vertex) -> bool, F: 'static
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.find_vertices(schema_id, limit, &pred)
+        })
+    }
+    pub fn scan_edges<S>(&self, edge_schema: S)
+        -> impl Future<Item = Result<Vec<Edge>, EdgeError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        let schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            let edges = match txn.scan_edges(schema_id)? {
+                Ok(edges) => edges, Err(e) => return Ok(Err(e))
+            };
+            let mut result = Vec::new();
+            for edge in edges {
+                match edge? {
+                    Ok(edge) => result.push(edge), Err(e) => return Ok(Err(e))
+                }
+            }
+            Ok(Ok(result))
+        })
+    }
+}
+
+impl <'a> GraphTransaction<'a> {
+    pub fn scan_vertices<S>(&self, schema: S)
+        -> Result<Result<VertexScanIterator<'a>, ReadVertexError>, TxnError>
+        where S: ToSchemaId
+    {
+        let schema_id = schema.to_id(&self.schemas);
+        match registry::vertex_id_iter(self.neb_txn, schema_id)? {
+            Ok(ids) => Ok(Ok(VertexScanIterator { ids, txn: self.neb_txn, deadline: self.deadline.clone() })),
+            Err(e) => Ok(Err(ReadVertexError::IdListError(e)))
+        }
+    }
+    pub fn count_vertices<S>(&self, schema: S)
+        -> Result<Result<u64, ReadVertexError>, TxnError>
+        where S: ToSchemaId
+    {
+        // counts registry segments without reading the vertex cells
+        let schema_id = schema.to_id(&self.schemas);
+        Ok(registry::vertex_count(self.neb_txn, schema_id)?
+            .map(|count| count as u64)
+            .map_err(ReadVertexError::IdListError))
+    }
+    // stops reading vertex cells once limit matches are found
+    pub fn find_vertices<S, F>(&self, schema: S, limit: Option<usize>, pred: F)
+        -> Result<Result<Vec<Vertex>, ReadVertexError>, TxnError>
+        where S: ToSchemaId, F: Fn(&Vertex) -> bool
+    {
+        let vertices = match self.scan_vertices(schema)? {
+            Ok(iter) => iter, Err(e) => return Ok(Err(e))
+        };
+        let limit = limit.unwrap_or(::std::usize::MAX);
+        let mut found = Vec::new();
+        for vertex in vertices {
+            if found.len() >= limit { break; }
+            let vertex = vertex?;
+            if pred(&vertex) {
+                found.push(vertex);
+            }
+        }
+        Ok(Ok(found))
+    }
+    pub fn scan_edges<S>(&self, edge_schema: S)
+        -> Result<Result<EdgeScanIterator<'a>, EdgeError>, TxnError>
+        where S: ToSchemaId
+    {
+        let (schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let undirected = edge_attr.edge_type == EdgeType::Undirected;
+        let vertex_schemas = self.schemas.list_schemas()
+            .into_iter()
+            .filter(|&(_, _, ref schema_type)| *schema_type == SchemaType::Vertex)
+            .map(|(id, _, _)| id)
+            .collect();
+        Ok(Ok(EdgeScanIterator {
+            txn: self.neb_txn,
+            schemas: self.schemas.clone(),
+            schema_id,
+            ed: if undirected { EdgeDirection::Undirected } else { EdgeDirection::Outbound },
+            undirected,
+            vertex_schemas,
+            vertex_ids: None,
+            pending: VecDeque::new(),
+            deadline: self.deadline.clone()
+        }))
+    }
+}
@@ -0,0 +1,130 @@
+use neb::ram::types::Id;
+use neb::client::transaction::TxnError;
+use futures::prelude::*;
+
+use server::schema::ToSchemaId;
+use super::{Graph, GraphInner, GraphTransaction, EdgeDirection, edge_attr_from_schema};
+use super::edge::{EdgeType, EdgeError};
+use super::vertex::{Vertex, ToVertexId};
+
+use std::collections::BTreeMap;
+
+// Vertices and the edges among them, detached from the transaction that read them
+#[derive(Debug)]
+pub struct Subgraph {
+    vertices: BTreeMap<Id, Vertex>,
+    edges: Vec<(Id, Id)>,
+    adjacency: BTreeMap<Id, Vec<Id>>
+}
+
+impl Subgraph {
+    pub fn vertices(&self) -> &BTreeMap<Id, Vertex> {
+        &self.vertices
+    }
+    pub fn edges(&self) -> &[(Id, Id)] {
+        &self.edges
+    }
+    // outbound neighbours for directed edges, both ends for undirected edges
+    pub fn neighbours(&self, vertex_id: &Id) -> &[Id] {
+        self.adjacency.get(vertex_id).map(|ids| ids.as_slice()).unwrap_or(&[])
+    }
+    pub fn contains(&self, vertex_id: &Id) -> bool {
+        self.vertices.contains_key(vertex_id)
+    }
+}
+
+impl Graph {
+    pub fn extract_subgraph<S>(&self, vertices: &[Id], edge_schema: S)
+        -> impl Future<Item = Result<Subgraph, EdgeError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        self.inner.extract_subgraph(vertices, edge_schema)
+    }
+    pub fn egonet<V, S>(&self, center: V, edge_schema: S, ed: EdgeDirection)
+        -> impl Future<Item = Result<Subgraph, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.egonet(center, edge_schema, ed)
+    }
+}
+
+impl GraphInner {
+    pub fn extract_subgraph<S>(&self, vertices: &[Id], edge_schema: S)
+        -> impl Future<Item = Result<Subgraph, EdgeError>, Error = TxnError>
+        where S: ToSchemaId
+    {
+        let vertex_ids = vertices.to_vec();
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.extract_subgraph(&vertex_ids, edge_schema_id)
+        })
+    }
+    pub fn egonet<V, S>(&self, center: V, edge_schema: S, ed: EdgeDirection)
+        -> impl Future<Item = Result<Subgraph, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let center_id = center.to_id();
+        let edge_schema_id = edge_schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.egonet(center_id, edge_schema_id, ed)
+        })
+    }
+}
+
+impl <'a> GraphTransaction<'a> {
+    pub fn extract_subgraph<S>(&self, vertices: &[Id], edge_schema: S)
+        -> Result<Result<Subgraph, EdgeError>, TxnError>
+        where S: ToSchemaId
+    {
+        let (edge_schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut subgraph = Subgraph {
+            vertices: BTreeMap::new(),
+            edges: Vec::new(),
+            adjacency: BTreeMap::new()
+        };
+        for vertex_id in vertices {
+            if let Some(vertex) = self.read_vertex(vertex_id)? { // missing vertices are left out
+                subgraph.vertices.insert(*vertex_id, vertex);
+            }
+        }
+        let ed = match edge_attr.edge_type {
+            EdgeType::Directed => EdgeDirection::Outbound,
+            EdgeType::Undirected => EdgeDirection::Undirected
+        };
+        let vertex_ids: Vec<Id> = subgraph.vertices.keys().cloned().collect();
+        for vertex_id in vertex_ids {
+            let neighbours = match self.adjacent_ids(&vertex_id, edge_schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            let neighbours: Vec<Id> = neighbours
+                .into_iter()
+                .filter(|id| subgraph.vertices.contains_key(id)) // keep only induced edges
+                .collect();
+            for neighbour_id in &neighbours {
+                // undirected edges are listed on both ends, record them once
+                if edge_attr.edge_type == EdgeType::Directed || vertex_id <= *neighbour_id {
+                    subgraph.edges.push((vertex_id, *neighbour_id));
+                }
+            }
+            subgraph.adjacency.insert(vertex_id, neighbours);
+        }
+        Ok(Ok(subgraph))
+    }
+    // the center, its neighbours in direction ed and every edge among them
+    pub fn egonet<V, S>(&self, center: V, edge_schema: S, ed: EdgeDirection)
+        -> Result<Result<Subgraph, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let center_id = center.to_id();
+        let (edge_schema_id, edge_attr) = match edge_attr_from_schema(edge_schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut members = match self.adjacent_ids(&center_id, edge_schema_id, &edge_attr, ed)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(e))
+        };
+        members.push(center_id);
+        self.extract_subgraph(&members, edge_schema_id)
+    }
+}
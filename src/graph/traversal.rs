@@ -0,0 +1,524 @@
+use neb::ram::types::Id;
+use neb::client::transaction::TxnError;
+use futures::prelude::*;
+
+use server::schema::ToSchemaId;
+use super::{Graph, GraphInner, GraphTransaction, EdgeDirection, edge_attr_from_schema};
+use super::edge::{EdgeError, EdgeAttributes};
+use super::vertex::ToVertexId;
+
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::f64;
+
+impl Graph {
+    pub fn bfs<V, S>(&self, start: V, schema: S, ed: EdgeDirection, max_depth: usize)
+        -> impl Future<Item = Result<Vec<(Id, usize)>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.bfs(start, schema, ed, max_depth)
+    }
+    pub fn k_hop<V, S>(&self, start: V, schema: S, ed: EdgeDirection, k: usize)
+        -> impl Future<Item = Result<Vec<Id>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.k_hop(start, schema, ed, k)
+    }
+    pub fn dfs<V, S, F>(&self, start: V, schema: S, ed: EdgeDirection, visit: F)
+        -> impl Future<Item = Result<(), EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId, F: FnMut(&Id, usize) -> bool, F: 'static
+    {
+        self.inner.dfs(start, schema, ed, visit)
+    }
+    pub fn shortest_path<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection)
+        -> impl Future<Item = Result<Option<Vec<Id>>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.shortest_path(from, schema, to, ed)
+    }
+    pub fn shortest_path_weighted<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection, weight_field: u64)
+        -> impl Future<Item = Result<Option<(Vec<Id>, f64)>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.shortest_path_weighted(from, schema, to, ed, weight_field)
+    }
+    pub fn all_paths<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection, max_len: usize)
+        -> impl Future<Item = Result<Vec<Vec<Id>>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.all_paths(from, schema, to, ed, max_len)
+    }
+    pub fn random_walk<V, S>(&self, start: V, schema: S, ed: EdgeDirection, steps: usize, seed: u64)
+        -> impl Future<Item = Result<Vec<Id>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.random_walk(start, schema, ed, steps, seed)
+    }
+    pub fn shortest_path_bidirectional<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection)
+        -> impl Future<Item = Result<Option<Vec<Id>>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.shortest_path_bidirectional(from, schema, to, ed)
+    }
+    pub fn sssp_tree<V, S>(&self, source: V, schema: S, ed: EdgeDirection)
+        -> impl Future<Item = Result<Vec<(Id, usize, Option<Id>)>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        self.inner.sssp_tree(source, schema, ed)
+    }
+}
+
+impl GraphInner {
+    pub fn bfs<V, S>(&self, start: V, schema: S, ed: EdgeDirection, max_depth: usize)
+        -> impl Future<Item = Result<Vec<(Id, usize)>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let start_id = start.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.bfs(start_id, schema_id, ed, max_depth)
+        })
+    }
+    pub fn k_hop<V, S>(&self, start: V, schema: S, ed: EdgeDirection, k: usize)
+        -> impl Future<Item = Result<Vec<Id>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let start_id = start.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.k_hop(start_id, schema_id, ed, k)
+        })
+    }
+    pub fn dfs<V, S, F>(&self, start: V, schema: S, ed: EdgeDirection, visit: F)
+        -> impl Future<Item = Result<(), EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId, F: FnMut(&Id, usize) -> bool, F: 'static
+    {
+        let start_id = start.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        let visit = RefCell::new(visit);
+        self.graph_transaction(move |txn| {
+            let mut visit = visit.borrow_mut();
+            txn.dfs(start_id, schema_id, ed, &mut *visit)
+        })
+    }
+    pub fn shortest_path<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection)
+        -> impl Future<Item = Result<Option<Vec<Id>>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.shortest_path(from_id, schema_id, to_id, ed)
+        })
+    }
+    pub fn shortest_path_weighted<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection, weight_field: u64)
+        -> impl Future<Item = Result<Option<(Vec<Id>, f64)>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.shortest_path_weighted(from_id, schema_id, to_id, ed, weight_field)
+        })
+    }
+    pub fn all_paths<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection, max_len: usize)
+        -> impl Future<Item = Result<Vec<Vec<Id>>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.all_paths(from_id, schema_id, to_id, ed, max_len)
+        })
+    }
+    pub fn random_walk<V, S>(&self, start: V, schema: S, ed: EdgeDirection, steps: usize, seed: u64)
+        -> impl Future<Item = Result<Vec<Id>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let start_id = start.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.random_walk(start_id, schema_id, ed, steps, seed)
+        })
+    }
+    pub fn shortest_path_bidirectional<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection)
+        -> impl Future<Item = Result<Option<Vec<Id>>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.shortest_path_bidirectional(from_id, schema_id, to_id, ed)
+        })
+    }
+    pub fn sssp_tree<V, S>(&self, source: V, schema: S, ed: EdgeDirection)
+        -> impl Future<Item = Result<Vec<(Id, usize, Option<Id>)>, EdgeError>, Error = TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let source_id = source.to_id();
+        let schema_id = schema.to_id(&self.schemas);
+        self.graph_transaction(move |txn| {
+            txn.sssp_tree(source_id, schema_id, ed)
+        })
+    }
+}
+
+// splitmix64, walks only need to be reproducible from the seed
+struct WalkRng {
+    state: u64
+}
+
+impl WalkRng {
+    fn new(seed: u64) -> WalkRng {
+        WalkRng { state: seed }
+    }
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[derive(PartialEq)]
+struct CostEntry {
+    cost: f64,
+    vertex_id: Id
+}
+
+impl Eq for CostEntry {}
+
+impl Ord for CostEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // costs add up weights that Edge::weight checked to be finite and not negative, the
+        // bits of such floats order like their values. Reversed so the heap pops the cheapest first
+        other.cost.to_bits().cmp(&self.cost.to_bits())
+            .then_with(|| self.vertex_id.cmp(&other.vertex_id))
+    }
+}
+
+impl PartialOrd for CostEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn trace_path(predecessors: &BTreeMap<Id, Id>, from_id: &Id, to_id: &Id) -> Vec<Id> {
+    let mut path = vec![*to_id];
+    let mut current = *to_id;
+    while current != *from_id {
+        current = predecessors[&current];
+        path.push(current);
+    }
+    path.reverse();
+    path
+}
+
+impl <'a> GraphTransaction<'a> {
+    // Expands one whole bfs layer. Stops at the first vertex already reached from the other end,
+    // all meetings within a layer give paths of the same length.
+    fn expand_layer(
+        &self, frontier: &[Id], visited: &mut BTreeMap<Id, Id>, other: &BTreeMap<Id, Id>,
+        schema_id: u32, edge_attr: &EdgeAttributes, ed: EdgeDirection
+    ) -> Result<Result<(Vec<Id>, Option<(Id, Id)>), EdgeError>, TxnError> {
+        let mut next_frontier = Vec::new();
+        for vertex_id in frontier {
+            let neighbours = match self.adjacent_ids(vertex_id, schema_id, edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            for neighbour_id in neighbours {
+                if other.contains_key(&neighbour_id) {
+                    return Ok(Ok((next_frontier, Some((*vertex_id, neighbour_id)))));
+                }
+                if !visited.contains_key(&neighbour_id) {
+                    visited.insert(neighbour_id, *vertex_id);
+                    next_frontier.push(neighbour_id);
+                }
+            }
+        }
+        Ok(Ok((next_frontier, None)))
+    }
+    fn extend_paths(
+        &self, path: &mut Vec<Id>, on_path: &mut BTreeSet<Id>, to_id: &Id,
+        schema_id: u32, edge_attr: &EdgeAttributes, ed: EdgeDirection, max_len: usize,
+        paths: &mut Vec<Vec<Id>>
+    ) -> Result<Result<(), EdgeError>, TxnError> {
+        let vertex_id = *path.last().unwrap();
+        if vertex_id == *to_id {
+            paths.push(path.clone());
+            return Ok(Ok(()));
+        }
+        if path.len() > max_len { return Ok(Ok(())); } // path already has max_len edges
+        let neighbours = match self.adjacent_ids(&vertex_id, schema_id, edge_attr, ed)? {
+            Ok(ids) => ids, Err(e) => return Ok(Err(e))
+        };
+        for neighbour_id in neighbours {
+            if !on_path.insert(neighbour_id) { continue; }
+            path.push(neighbour_id);
+            let extended = self.extend_paths(path, on_path, to_id, schema_id, edge_attr, ed, max_len, paths)?;
+            path.pop();
+            on_path.remove(&neighbour_id);
+            if let Err(e) = extended { return Ok(Err(e)); }
+        }
+        Ok(Ok(()))
+    }
+    pub fn bfs<V, S>(&self, start: V, schema: S, ed: EdgeDirection, max_depth: usize)
+        -> Result<Result<Vec<(Id, usize)>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let start_id = start.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        let mut result = Vec::new();
+        visited.insert(start_id);
+        queue.push_back((start_id, 0));
+        while let Some((vertex_id, depth)) = queue.pop_front() {
+            result.push((vertex_id, depth));
+            if depth >= max_depth { continue; }
+            let neighbours = match self.adjacent_ids(&vertex_id, schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            for neighbour_id in neighbours {
+                if visited.insert(neighbour_id) {
+                    queue.push_back((neighbour_id, depth + 1));
+                }
+            }
+        }
+        Ok(Ok(result))
+    }
+    pub fn k_hop<V, S>(&self, start: V, schema: S, ed: EdgeDirection, k: usize)
+        -> Result<Result<Vec<Id>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        Ok(self.bfs(start, schema, ed, k)?.map(|visited| {
+            visited
+                .into_iter()
+                .filter(|&(_, depth)| depth > 0) // exclude the start vertex
+                .map(|(id, _)| id)
+                .collect()
+        }))
+    }
+    pub fn dfs<V, S, F>(&self, start: V, schema: S, ed: EdgeDirection, mut visit: F)
+        -> Result<Result<(), EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId, F: FnMut(&Id, usize) -> bool
+    {
+        let start_id = start.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![(start_id, 0)];
+        while let Some((vertex_id, depth)) = stack.pop() {
+            if !visited.insert(vertex_id) { continue; }
+            if !visit(&vertex_id, depth) { continue; } // prune this branch
+            let neighbours = match self.adjacent_ids(&vertex_id, schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            for neighbour_id in neighbours.into_iter().rev() { // keep id list order on the stack
+                if !visited.contains(&neighbour_id) {
+                    stack.push((neighbour_id, depth + 1));
+                }
+            }
+        }
+        Ok(Ok(()))
+    }
+    pub fn shortest_path<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection)
+        -> Result<Result<Option<Vec<Id>>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut predecessors = BTreeMap::new();
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(from_id);
+        queue.push_back(from_id);
+        while let Some(vertex_id) = queue.pop_front() {
+            if vertex_id == to_id {
+                return Ok(Ok(Some(trace_path(&predecessors, &from_id, &to_id))));
+            }
+            let neighbours = match self.adjacent_ids(&vertex_id, schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            for neighbour_id in neighbours {
+                if visited.insert(neighbour_id) {
+                    predecessors.insert(neighbour_id, vertex_id);
+                    queue.push_back(neighbour_id);
+                }
+            }
+        }
+        Ok(Ok(None))
+    }
+    pub fn shortest_path_weighted<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection, weight_field: u64)
+        -> Result<Result<Option<(Vec<Id>, f64)>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let (schema_id, _) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut costs = BTreeMap::new();
+        let mut predecessors = BTreeMap::new();
+        let mut heap = BinaryHeap::new();
+        costs.insert(from_id, 0f64);
+        heap.push(CostEntry { cost: 0f64, vertex_id: from_id });
+        while let Some(CostEntry { cost, vertex_id }) = heap.pop() {
+            if vertex_id == to_id {
+                return Ok(Ok(Some((trace_path(&predecessors, &from_id, &to_id), cost))));
+            }
+            if cost > *costs.get(&vertex_id).unwrap_or(&f64::INFINITY) { continue; } // stale entry
+            let edges = match self.neighbourhood_iter(vertex_id, schema_id, ed)? {
+                Ok(iter) => iter, Err(e) => return Ok(Err(e))
+            };
+            for edge in edges {
+                let edge = match edge? {
+                    Ok(edge) => edge, Err(e) => return Ok(Err(e))
+                };
+                let neighbour_id = match edge.one_opposite_id_vertex_id(&vertex_id) {
+                    Some(id) => *id, None => continue
+                };
+                let weight = match edge.weight(weight_field) {
+                    Ok(w) => w, Err(e) => return Ok(Err(e))
+                };
+                let next_cost = cost + weight;
+                if next_cost < *costs.get(&neighbour_id).unwrap_or(&f64::INFINITY) {
+                    costs.insert(neighbour_id, next_cost);
+                    predecessors.insert(neighbour_id, vertex_id);
+                    heap.push(CostEntry { cost: next_cost, vertex_id: neighbour_id });
+                }
+            }
+        }
+        Ok(Ok(None))
+    }
+    pub fn all_paths<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection, max_len: usize)
+        -> Result<Result<Vec<Vec<Id>>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut path = vec![from_id];
+        let mut on_path = BTreeSet::new();
+        let mut paths = Vec::new();
+        on_path.insert(from_id);
+        match self.extend_paths(&mut path, &mut on_path, &to_id, schema_id, &edge_attr, ed, max_len, &mut paths)? {
+            Ok(()) => Ok(Ok(paths)), Err(e) => Ok(Err(e))
+        }
+    }
+    pub fn shortest_path_bidirectional<V, S>(&self, from: V, schema: S, to: V, ed: EdgeDirection)
+        -> Result<Result<Option<Vec<Id>>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let from_id = from.to_id();
+        let to_id = to.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        if from_id == to_id {
+            return Ok(Ok(Some(vec![from_id])));
+        }
+        // forward maps a vertex to its predecessor, backward to its successor towards the target
+        let mut forward = BTreeMap::new();
+        let mut backward = BTreeMap::new();
+        forward.insert(from_id, from_id);
+        backward.insert(to_id, to_id);
+        let mut forward_frontier = vec![from_id];
+        let mut backward_frontier = vec![to_id];
+        while !forward_frontier.is_empty() && !backward_frontier.is_empty() {
+            let meeting = if forward_frontier.len() <= backward_frontier.len() {
+                let (next, meeting) = match self.expand_layer(
+                    &forward_frontier, &mut forward, &backward, schema_id, &edge_attr, ed
+                )? {
+                    Ok(t) => t, Err(e) => return Ok(Err(e))
+                };
+                forward_frontier = next;
+                meeting
+            } else {
+                let (next, meeting) = match self.expand_layer(
+                    &backward_frontier, &mut backward, &forward, schema_id, &edge_attr, ed.reversed()
+                )? {
+                    Ok(t) => t, Err(e) => return Ok(Err(e))
+                };
+                backward_frontier = next;
+                meeting.map(|(backward_id, forward_id)| (forward_id, backward_id))
+            };
+            if let Some((forward_id, backward_id)) = meeting {
+                let mut path = trace_path(&forward, &from_id, &forward_id);
+                let mut current = backward_id;
+                path.push(current);
+                while current != to_id {
+                    current = backward[&current];
+                    path.push(current);
+                }
+                return Ok(Ok(Some(path)));
+            }
+        }
+        Ok(Ok(None))
+    }
+    // distance and predecessor of every reachable vertex, in visiting order; the source has no predecessor
+    pub fn sssp_tree<V, S>(&self, source: V, schema: S, ed: EdgeDirection)
+        -> Result<Result<Vec<(Id, usize, Option<Id>)>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let source_id = source.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        let mut tree = Vec::new();
+        visited.insert(source_id);
+        queue.push_back((source_id, 0, None));
+        while let Some((vertex_id, distance, predecessor)) = queue.pop_front() {
+            tree.push((vertex_id, distance, predecessor));
+            let neighbours = match self.adjacent_ids(&vertex_id, schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            for neighbour_id in neighbours {
+                if visited.insert(neighbour_id) {
+                    queue.push_back((neighbour_id, distance + 1, Some(vertex_id)));
+                }
+            }
+        }
+        Ok(Ok(tree))
+    }
+    pub fn random_walk<V, S>(&self, start: V, schema: S, ed: EdgeDirection, steps: usize, seed: u64)
+        -> Result<Result<Vec<Id>, EdgeError>, TxnError>
+        where V: ToVertexId, S: ToSchemaId
+    {
+        let start_id = start.to_id();
+        let (schema_id, edge_attr) = match edge_attr_from_schema(schema, &self.schemas) {
+            Err(e) => return Ok(Err(e)), Ok(t) => t
+        };
+        let mut rng = WalkRng::new(seed);
+        let mut walk = vec![start_id];
+        let mut current = start_id;
+        for _ in 0..steps {
+            let neighbours = match self.adjacent_ids(&current, schema_id, &edge_attr, ed)? {
+                Ok(ids) => ids, Err(e) => return Ok(Err(e))
+            };
+            if neighbours.is_empty() { break; } // dead end
+            current = neighbours[rng.below(neighbours.len())];
+            walk.push(current);
+        }
+        Ok(Ok(walk))
+    }
+}
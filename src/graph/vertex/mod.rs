@@ -1,13 +1,25 @@
+#[macro_use]
+pub mod typed;
+
 use neb::ram::cell::Cell;
 use neb::ram::types::{Id, key_hash, Map};
 use neb::client::transaction::{Transaction, TxnError};
 use neb::dovahkiin::types::Value;
 use graph::id_list::{IdList, IdListError};
+use graph::registry;
+use graph::index;
+use graph::labels;
 use graph::edge;
+use graph::fields::{INBOUND_KEY_ID, OUTBOUND_KEY_ID, UNDIRECTED_KEY_ID, ToFieldId};
+use utils::value;
+use serde::{Serialize, Serializer};
+use serde_json::{self, Value as JsonValue};
 use server::schema::SchemaContainer;
 
 use std::ops::{Index, IndexMut};
 use std::sync::Arc;
+use std::error::Error;
+use std::fmt;
 use super::EdgeDirection;
 
 #[derive(Debug)]
@@ -15,6 +27,7 @@ pub struct Vertex {
     pub cell: Cell
 }
 
+#[derive(Debug)]
 pub enum RemoveError {
     NotFound,
     FormatError,
@@ -41,6 +54,37 @@ impl Vertex {
     pub fn schema(&self) -> u32 {
         self.cell.header.schema
     }
+    // same as schema, named to match Edge::schema_id
+    pub fn schema_id(&self) -> u32 {
+        self.schema()
+    }
+    // low level access, the cell also carries the internal edge list fields
+    pub fn as_cell(&self) -> &Cell {
+        &self.cell
+    }
+    pub fn get_str<K>(&self, field: K) -> Option<&str> where K: ToFieldId {
+        value::as_str(&self[field.to_field_id()])
+    }
+    pub fn get_i64<K>(&self, field: K) -> Option<i64> where K: ToFieldId {
+        value::to_i64(&self[field.to_field_id()])
+    }
+    pub fn get_f64<K>(&self, field: K) -> Option<f64> where K: ToFieldId {
+        value::to_f64(&self[field.to_field_id()])
+    }
+    pub fn get_id<K>(&self, field: K) -> Option<Id> where K: ToFieldId {
+        value::as_id(&self[field.to_field_id()])
+    }
+    pub fn to_json(&self) -> JsonValue {
+        let data = match &self.cell.data {
+            &Value::Map(ref map) => value::map_to_json(map, &[*INBOUND_KEY_ID, *OUTBOUND_KEY_ID, *UNDIRECTED_KEY_ID]),
+            other => value::to_json(other)
+        };
+        let mut object = serde_json::Map::new();
+        object.insert("id".to_string(), value::id_to_json(&self.cell.id()));
+        object.insert("schema".to_string(), JsonValue::from(self.schema()));
+        object.insert("data".to_string(), data);
+        JsonValue::Object(object)
+    }
 }
 
 pub fn txn_remove<V>(txn: &Transaction, schemas: &Arc<SchemaContainer>, vertex: V)
@@ -51,9 +95,12 @@ pub fn txn_remove<V>(txn: &Transaction, schemas: &Arc<SchemaContainer>, vertex:
             let remove_field_lists = |id: &Id, txn: &Transaction, field_id: u64|
                 -> Result<Result<(), RemoveError>, TxnError> {
                 let (type_list_id, schemas_ids) = match IdList::cell_types(txn, id, field_id)? {
-                    Some(t) => t, None => return Ok(Err(RemoveError::FormatError))
+                    Some(t) => t, None => return Ok(Ok(())) // no edges in this direction
                 };
                 for schema_id in schemas_ids {
+                    match registry::unregister_edge_user(txn, schema_id, id)? {
+                        Ok(()) => {}, Err(e) => return Ok(Err(RemoveError::IdListError(e)))
+                    }
                     let mut id_list = IdList::from_txn_and_container(txn, id, field_id, schema_id);
                     {                          // remove edge cells
                         let mut iter = match id_list.iter()? {
@@ -87,13 +134,35 @@ pub fn txn_remove<V>(txn: &Transaction, schemas: &Arc<SchemaContainer>, vertex:
             match remove_field_lists(id, txn, EdgeDirection::Outbound.as_field())? {
                 Ok(()) => {}, Err(e) => return Ok(Err(e))
             }
+            match registry::unregister(txn, cell.header.schema, id)? {
+                Ok(()) => {}, Err(e) => return Ok(Err(RemoveError::IdListError(e)))
+            }
+            match index::unindex_cell(txn, schemas, &cell)? {
+                Ok(()) => {}, Err(e) => return Ok(Err(RemoveError::IdListError(e)))
+            }
+            match labels::clear_labels(txn, id, cell.header.schema)? {
+                Ok(()) => {}, Err(e) => return Ok(Err(RemoveError::IdListError(e)))
+            }
             txn.remove(id).map(|_| Ok(())) // remove vertex cell
         },
         None => Ok(Err(RemoveError::NotFound))
     }
 }
 
-pub fn txn_update<U, V>(txn: &Transaction, vertex: V, update: &U) -> Result<(), TxnError>
+// Writes an updated vertex cell, moving it between secondary index entries as needed
+pub fn txn_update_cell(txn: &Transaction, schemas: &Arc<SchemaContainer>, cell: &Cell)
+    -> Result<Result<(), IdListError>, TxnError> {
+    if let Some(original) = txn.read(&cell.id())? {
+        match index::reindex_cell(txn, schemas, &original, cell)? {
+            Ok(()) => {}, Err(e) => return Ok(Err(e))
+        }
+    }
+    txn.update(cell).map(Ok)
+}
+
+// Aborts when the vertex is missing or the update gives up, index errors are kept as the result
+pub fn txn_update<U, V>(txn: &Transaction, schemas: &Arc<SchemaContainer>, vertex: V, update: &U)
+    -> Result<Result<(), IdListError>, TxnError>
     where V: ToVertexId, U: Fn(Vertex) -> Option<Vertex> {
     let id = &vertex.to_id();
     let update_cell = |cell: Cell| {
@@ -106,11 +175,11 @@ pub fn txn_update<U, V>(txn: &Transaction, vertex: V, update: &U) -> Result<(),
     match cell {
         Some(cell) => {
             match update_cell(cell) {
-                Some(cell) => txn.update(&cell),
-                None => txn.abort()
+                Some(cell) => txn_update_cell(txn, schemas, &cell),
+                None => txn.abort().map(Ok)
             }
         },
-        None => txn.abort()
+        None => txn.abort().map(Ok)
     }
 }
 
@@ -166,4 +235,31 @@ impl IndexMut<u64> for Vertex {
     fn index_mut<'a>(&'a mut self, index: u64) -> &'a mut Self::Output {
         &mut self.cell[index]
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for RemoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &RemoveError::NotFound => write!(f, "vertex not found"),
+            &RemoveError::FormatError => write!(f, "vertex cell has unexpected format"),
+            &RemoveError::IdListError(ref e) => write!(f, "id list error: {}", e),
+            &RemoveError::EdgeError(ref e) => write!(f, "edge error: {}", e)
+        }
+    }
+}
+
+impl Error for RemoveError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            &RemoveError::IdListError(ref e) => Some(e),
+            &RemoveError::EdgeError(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl Serialize for Vertex {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        self.to_json().serialize(serializer)
+    }
+}
@@ -0,0 +1,125 @@
+use neb::ram::schema::Field;
+use neb::ram::types::{TypeId, Id, Map, Value};
+use server::schema::MorpheusSchema;
+
+use std::error::Error;
+use std::fmt;
+use super::Vertex;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FieldError {
+    Missing(&'static str),
+    TypeMismatch(&'static str)
+}
+
+// Rust types that map onto a single neb field
+pub trait FieldValue: Sized {
+    fn type_id() -> u32;
+    fn to_value(&self) -> Value;
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+macro_rules! field_value {
+    ($t: ty, $variant: ident) => {
+        impl FieldValue for $t {
+            fn type_id() -> u32 { TypeId::$variant as u32 }
+            fn to_value(&self) -> Value { Value::$variant(self.clone()) }
+            fn from_value(value: &Value) -> Option<Self> {
+                match value {
+                    &Value::$variant(ref v) => Some(v.clone()),
+                    _ => None
+                }
+            }
+        }
+    };
+}
+
+field_value!(bool, Bool);
+field_value!(i8, I8);
+field_value!(i16, I16);
+field_value!(i32, I32);
+field_value!(i64, I64);
+field_value!(u8, U8);
+field_value!(u16, U16);
+field_value!(u32, U32);
+field_value!(u64, U64);
+field_value!(f32, F32);
+field_value!(f64, F64);
+field_value!(String, String);
+field_value!(Id, Id);
+
+pub trait TypedVertex: Sized {
+    fn schema_name() -> &'static str;
+    fn fields() -> Vec<Field>;
+    fn to_map(&self) -> Map;
+    fn from_vertex(vertex: &Vertex) -> Result<Self, FieldError>;
+    fn schema() -> MorpheusSchema {
+        MorpheusSchema::new(Self::schema_name(), None, &Self::fields(), false)
+    }
+}
+
+pub fn read_field<T>(vertex: &Vertex, name: &'static str, key_id: u64) -> Result<T, FieldError>
+    where T: FieldValue
+{
+    match vertex[key_id] {
+        Value::Null => Err(FieldError::Missing(name)),
+        ref value => T::from_value(value).ok_or(FieldError::TypeMismatch(name))
+    }
+}
+
+// Implements TypedVertex for the struct it wraps, storing each field under the key id of
+// its name. The struct keeps its own attributes and derives, e.g.
+// morpheus_vertex! { "user" => #[derive(Debug)] pub struct User { pub name: String, pub age: i64 } }
+#[macro_export]
+macro_rules! morpheus_vertex {
+    ($schema: expr => $(#[$attr: meta])* pub struct $struc: ident {
+        $($(#[$field_attr: meta])* pub $field: ident : $t: ty),* $(,)*
+    }) => {
+        $(#[$attr])*
+        pub struct $struc {
+            $($(#[$field_attr])* pub $field: $t),*
+        }
+
+        impl $crate::graph::vertex::typed::TypedVertex for $struc {
+            fn schema_name() -> &'static str {
+                $schema
+            }
+            fn fields() -> Vec<::neb::ram::schema::Field> {
+                vec![$(::neb::ram::schema::Field::new(
+                    &String::from(stringify!($field)),
+                    <$t as $crate::graph::vertex::typed::FieldValue>::type_id(),
+                    false, false, None
+                )),*]
+            }
+            fn to_map(&self) -> ::neb::ram::types::Map {
+                let mut map = ::neb::ram::types::Map::new();
+                $(map.insert_key_id(
+                    ::neb::ram::types::key_hash(&String::from(stringify!($field))),
+                    $crate::graph::vertex::typed::FieldValue::to_value(&self.$field)
+                );)*
+                map
+            }
+            fn from_vertex(vertex: &$crate::graph::vertex::Vertex)
+                -> Result<Self, $crate::graph::vertex::typed::FieldError>
+            {
+                Ok($struc {
+                    $($field: $crate::graph::vertex::typed::read_field(
+                        vertex, stringify!($field),
+                        ::neb::ram::types::key_hash(&String::from(stringify!($field)))
+                    )?),*
+                })
+            }
+        }
+    };
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &FieldError::Missing(name) => write!(f, "field {} is missing", name),
+            &FieldError::TypeMismatch(name) => write!(f, "field {} has unexpected type", name)
+        }
+    }
+}
+
+impl Error for FieldError {}
@@ -21,6 +21,12 @@ extern crate parking_lot;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
+extern crate csv;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 extern crate chashmap;
 #[macro_use]
 extern crate log;
@@ -31,6 +37,7 @@ extern crate serde_yaml;
 
 use futures::Future;
 
+#[macro_use]
 mod graph;
 mod server;
 mod utils;
@@ -5,12 +5,22 @@ use bifrost_hasher::hash_str;
 use graph::edge::{EdgeAttributes, EdgeType};
 use graph::edge;
 use chashmap::CHashMap;
+use parking_lot::RwLock;
 use std::sync::Arc;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use neb::ram::schema::{Field, Schema};
+use neb::ram::types::{TypeId, key_hash};
+use neb::dovahkiin::types::Value;
 use neb::client::{AsyncClient as NebClient};
 use neb::server::{ServerMeta as NebServerMeta};
 use server::schema::sm::schema_types::client::SMClient;
-use graph::fields::VERTEX_TEMPLATE;
+use server::schema::sm::schema_defaults::client::SMClient as DefaultsSMClient;
+use server::schema::sm::schema_indexes::client::SMClient as IndexesSMClient;
+use server::schema::sm::schema_list_capacities::client::SMClient as ListCapacitiesSMClient;
+use graph::fields::{self, ALL_DIRECTIONS};
+use graph::EdgeDirection;
 use futures::{Future, future};
 
 mod sm;
@@ -28,12 +38,64 @@ pub enum SchemaError {
     NewMorpheusSchemaExecError(ExecError),
     SimpleEdgeShouldNotHaveSchema,
     SchemaTypeUnspecified,
+    SchemaNotFound,
+    SchemaTypeMismatch(SchemaType),
+    SchemaInUse,
+    CannotCheckSchemaUsage,
+    CannotInitRegistry(String),
+    DelNebSchemaExecError(ExecError),
+    DelMorpheusSchemaExecError(ExecError),
+    NewSchemaDefaultsExecError(ExecError),
+    NewIndexExecError(ExecError),
+    NewListCapacityExecError(ExecError),
+    FieldNotFound(u64),
+    InvalidFieldName(String),
+    MapFieldWithoutSubFields(String),
+    ReservedField(String),
+    DuplicateField(String),
+    KeyFieldNotFound(String),
 }
 
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &SchemaError::NewNebSchemaExecError(ref e) => write!(f, "cannot create neb schema: {:?}", e),
+            &SchemaError::NewMorpheusSchemaExecError(ref e) => write!(f, "cannot register schema: {:?}", e),
+            &SchemaError::SimpleEdgeShouldNotHaveSchema => write!(f, "simple edges cannot have fields"),
+            &SchemaError::SchemaTypeUnspecified => write!(f, "schema type is not specified"),
+            &SchemaError::SchemaNotFound => write!(f, "schema not found"),
+            &SchemaError::SchemaTypeMismatch(ref t) => write!(f, "schema is of another type: {:?}", t),
+            &SchemaError::SchemaInUse => write!(f, "schema is still in use"),
+            &SchemaError::CannotCheckSchemaUsage => write!(f, "cannot check whether the schema is in use"),
+            &SchemaError::CannotInitRegistry(ref e) => write!(f, "cannot initialize the schema registry: {}", e),
+            &SchemaError::DelNebSchemaExecError(ref e) => write!(f, "cannot remove neb schema: {:?}", e),
+            &SchemaError::DelMorpheusSchemaExecError(ref e) => write!(f, "cannot unregister schema: {:?}", e),
+            &SchemaError::NewSchemaDefaultsExecError(ref e) => write!(f, "cannot store schema defaults: {:?}", e),
+            &SchemaError::NewIndexExecError(ref e) => write!(f, "cannot register index: {:?}", e),
+            &SchemaError::NewListCapacityExecError(ref e) => write!(f, "cannot store list capacity: {:?}", e),
+            &SchemaError::FieldNotFound(field) => write!(f, "field {} not found", field),
+            &SchemaError::InvalidFieldName(ref name) => write!(f, "invalid field name {}", name),
+            &SchemaError::MapFieldWithoutSubFields(ref name) => write!(f, "map field {} has no sub fields", name),
+            &SchemaError::ReservedField(ref name) => write!(f, "field {} is reserved", name),
+            &SchemaError::DuplicateField(ref name) => write!(f, "field {} is defined more than once", name),
+            &SchemaError::KeyFieldNotFound(ref name) => write!(f, "key field {} not found", name)
+        }
+    }
+}
+
+impl Error for SchemaError {}
+
 pub struct SchemaContainer {
     pub neb_client: Arc<NebClient>,
     map: Arc<CHashMap<u32, SchemaType>>,
+    defaults: Arc<CHashMap<u32, Vec<(String, Value)>>>,
+    indexes: Arc<CHashMap<u32, Vec<u64>>>,
+    list_capacities: Arc<CHashMap<u32, usize>>,
     sm_client: Arc<SMClient>,
+    defaults_sm_client: Arc<DefaultsSMClient>,
+    indexes_sm_client: Arc<IndexesSMClient>,
+    list_capacities_sm_client: Arc<ListCapacitiesSMClient>,
+    neb_schema_cache: Arc<RwLock<HashMap<u32, Arc<Schema>>>>,
     neb_mata: Arc<NebServerMeta>,
 }
 
@@ -44,6 +106,8 @@ pub struct MorpheusSchema {
     pub schema_type: SchemaType,
     pub key_field: Option<Vec<String>>,
     pub fields: Vec<Field>,
+    pub defaults: Vec<(String, Value)>,
+    pub edge_directions: Vec<EdgeDirection>,
     pub is_dynamic: bool
 }
 
@@ -58,18 +122,31 @@ impl MorpheusSchema {
             name: name.to_string(),
             key_field: key_field.cloned(),
             fields: fields.clone(),
+            defaults: Vec::new(),
+            edge_directions: ALL_DIRECTIONS.to_vec(),
             schema_type: SchemaType::Unspecified,
             is_dynamic
         }
     }
+    // Vertices only reserve edge list fields for these directions, linking in others fails
+    pub fn with_edge_directions(mut self, directions: &[EdgeDirection]) -> MorpheusSchema {
+        self.edge_directions = directions.to_vec();
+        self
+    }
+    pub fn with_default<'a>(mut self, field: &'a str, value: Value) -> MorpheusSchema {
+        self.defaults.push((field.to_string(), value));
+        self
+    }
     pub fn into_ref(self) -> Arc<MorpheusSchema> {
         Arc::new(self)
     }
 }
 
-pub fn cell_fields(schema_type: SchemaType, mut body_fields: Vec<Field>) -> Result<Vec<Field>, SchemaError> {
+pub fn cell_fields(schema_type: SchemaType, directions: &[EdgeDirection], mut body_fields: Vec<Field>)
+    -> Result<Vec<Field>, SchemaError>
+{
     let mut fields = match schema_type {
-        SchemaType::Vertex => VERTEX_TEMPLATE.clone(),
+        SchemaType::Vertex => fields::vertex_template(directions),
         SchemaType::Edge(edge_attr) => {
             if !edge_attr.has_body && body_fields.len() > 0 {
                 return Err(SchemaError::SimpleEdgeShouldNotHaveSchema);
@@ -85,16 +162,81 @@ pub fn cell_fields(schema_type: SchemaType, mut body_fields: Vec<Field>) -> Resu
     Ok(fields)
 }
 
+fn validate_fields(fields: &[Field], reserved: &[u64]) -> Result<(), SchemaError> {
+    let mut seen = Vec::with_capacity(fields.len());
+    for field in fields {
+        if field.name.is_empty() || field.name == "*" {
+            return Err(SchemaError::InvalidFieldName(field.name.clone()));
+        }
+        let key_id = key_hash(&field.name);
+        if reserved.contains(&key_id) {
+            return Err(SchemaError::ReservedField(field.name.clone()));
+        }
+        if seen.contains(&key_id) {
+            return Err(SchemaError::DuplicateField(field.name.clone()));
+        }
+        seen.push(key_id);
+        match (field.type_id == TypeId::Map as u32, &field.sub_fields) {
+            (true, &Some(ref sub_fields)) => validate_fields(sub_fields, &[])?,
+            (true, &None) => return Err(SchemaError::MapFieldWithoutSubFields(field.name.clone())),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Runs the checks new_schema relies on without registering anything. Edge list keys are
+// reserved for every direction, not only the ones the schema keeps lists for.
+pub fn validate_schema(schema: &MorpheusSchema) -> Result<(), SchemaError> {
+    let template = cell_fields(schema.schema_type, &ALL_DIRECTIONS, Vec::new())?;
+    cell_fields(schema.schema_type, &schema.edge_directions, schema.fields.clone())?;
+    let reserved: Vec<u64> = template
+        .iter()
+        .map(|field| key_hash(&field.name))
+        .chain(ALL_DIRECTIONS.iter().map(|ed| ed.as_field()))
+        .collect();
+    validate_fields(&schema.fields, &reserved)?;
+    if !schema.is_dynamic {
+        for key_name in schema.key_field.iter().flat_map(|names| names.iter()) {
+            if !schema.fields.iter().any(|field| &field.name == key_name) {
+                return Err(SchemaError::KeyFieldNotFound(key_name.clone()));
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn generate_sm_id<'a>(group: &'a str) -> u64 {
     hash_str(&format!("{}-{}", sm::DEFAULT_RAFT_PREFIX, group))
 }
 
+pub fn generate_defaults_sm_id<'a>(group: &'a str) -> u64 {
+    hash_str(&format!("{}-{}", sm::DEFAULTS_RAFT_PREFIX, group))
+}
+
+pub fn generate_indexes_sm_id<'a>(group: &'a str) -> u64 {
+    hash_str(&format!("{}-{}", sm::INDEXES_RAFT_PREFIX, group))
+}
+
+pub fn generate_list_capacities_sm_id<'a>(group: &'a str) -> u64 {
+    hash_str(&format!("{}-{}", sm::LIST_CAPACITIES_RAFT_PREFIX, group))
+}
+
 impl SchemaContainer {
 
     pub fn new_meta_service<'a>(group: &'a str, raft_service: &Arc<RaftService>) {
         let mut container_sm = sm::schema_types::Map::new(generate_sm_id(group));
         container_sm.init_callback(raft_service);
         raft_service.register_state_machine(Box::new(container_sm));
+        let mut defaults_sm = sm::schema_defaults::Map::new(generate_defaults_sm_id(group));
+        defaults_sm.init_callback(raft_service);
+        raft_service.register_state_machine(Box::new(defaults_sm));
+        let mut indexes_sm = sm::schema_indexes::Map::new(generate_indexes_sm_id(group));
+        indexes_sm.init_callback(raft_service);
+        raft_service.register_state_machine(Box::new(indexes_sm));
+        let mut list_capacities_sm = sm::schema_list_capacities::Map::new(generate_list_capacities_sm_id(group));
+        list_capacities_sm.init_callback(raft_service);
+        raft_service.register_state_machine(Box::new(list_capacities_sm));
     }
 
     pub fn new_client<'a>(
@@ -105,26 +247,88 @@ impl SchemaContainer {
     ) -> Result<Arc<SchemaContainer>, ExecError> {
         let sm_client = Arc::new(SMClient::new(generate_sm_id(group), &raft_client));
         let sm_entries = sm_client.entries()?.unwrap();
+        let defaults_sm_client = Arc::new(DefaultsSMClient::new(generate_defaults_sm_id(group), &raft_client));
+        let defaults_sm_entries = defaults_sm_client.entries()?.unwrap();
+        let indexes_sm_client = Arc::new(IndexesSMClient::new(generate_indexes_sm_id(group), &raft_client));
+        let indexes_sm_entries = indexes_sm_client.entries()?.unwrap();
+        let list_capacities_sm_client = Arc::new(
+            ListCapacitiesSMClient::new(generate_list_capacities_sm_id(group), &raft_client)
+        );
+        let list_capacities_sm_entries = list_capacities_sm_client.entries()?.unwrap();
         let container = SchemaContainer {
             map: Arc::new(CHashMap::new()),
+            defaults: Arc::new(CHashMap::new()),
+            indexes: Arc::new(CHashMap::new()),
+            list_capacities: Arc::new(CHashMap::new()),
             sm_client: sm_client.clone(),
+            defaults_sm_client: defaults_sm_client.clone(),
+            indexes_sm_client: indexes_sm_client.clone(),
+            list_capacities_sm_client: list_capacities_sm_client.clone(),
+            neb_schema_cache: Arc::new(RwLock::new(HashMap::new())),
             neb_client: neb_client.clone(),
             neb_mata: neb_meta.clone()
         };
         let container_ref = Arc::new(container);
         let container_ref1 = container_ref.clone();
         let container_ref2 = container_ref.clone();
+        let container_ref3 = container_ref.clone();
+        let container_ref4 = container_ref.clone();
+        let container_ref5 = container_ref.clone();
+        let container_ref6 = container_ref.clone();
+        let container_ref7 = container_ref.clone();
+        let container_ref8 = container_ref.clone();
         for (schema_id, schema_type) in sm_entries {
             container_ref.map.insert(schema_id, schema_type);
         }
+        for (schema_id, defaults) in defaults_sm_entries {
+            container_ref.defaults.insert(schema_id, defaults);
+        }
+        for (schema_id, fields) in indexes_sm_entries {
+            container_ref.indexes.insert(schema_id, fields);
+        }
+        for (schema_id, capacity) in list_capacities_sm_entries {
+            container_ref.list_capacities.insert(schema_id, capacity as usize);
+        }
         sm_client.on_inserted(move |res| {
             if let Ok((id, schema_type)) = res {
                 container_ref1.map.insert(id, schema_type);
+                container_ref1.evict_neb_schema(id);
             }
         })?;
         sm_client.on_removed(move |res| {
             if let Ok((id, schema_type)) = res {
                 container_ref2.map.remove(&id);
+                container_ref2.evict_neb_schema(id);
+            }
+        })?;
+        defaults_sm_client.on_inserted(move |res| {
+            if let Ok((id, defaults)) = res {
+                container_ref3.defaults.insert(id, defaults);
+            }
+        })?;
+        defaults_sm_client.on_removed(move |res| {
+            if let Ok((id, _)) = res {
+                container_ref4.defaults.remove(&id);
+            }
+        })?;
+        indexes_sm_client.on_inserted(move |res| {
+            if let Ok((id, fields)) = res {
+                container_ref5.indexes.insert(id, fields);
+            }
+        })?;
+        indexes_sm_client.on_removed(move |res| {
+            if let Ok((id, _)) = res {
+                container_ref6.indexes.remove(&id);
+            }
+        })?;
+        list_capacities_sm_client.on_inserted(move |res| {
+            if let Ok((id, capacity)) = res {
+                container_ref7.list_capacities.insert(id, capacity as usize);
+            }
+        })?;
+        list_capacities_sm_client.on_removed(move |res| {
+            if let Ok((id, _)) = res {
+                container_ref8.list_capacities.remove(&id);
             }
         })?;
         return Ok(container_ref);
@@ -132,9 +336,12 @@ impl SchemaContainer {
 
     pub fn new_schema(&self, schema: MorpheusSchema) -> impl Future<Item = u32, Error = SchemaError> {
         let schema_type = schema.schema_type;
+        let defaults = schema.defaults.clone();
         let sm_client = self.sm_client.clone();
+        let defaults_sm_client = self.defaults_sm_client.clone();
         let neb_client = self.neb_client.clone();
-        future::result(cell_fields(schema_type, schema.fields.clone()))
+        let neb_schema_cache = self.neb_schema_cache.clone();
+        future::result(cell_fields(schema_type, &schema.edge_directions, schema.fields.clone()))
             .and_then(move |schema_fields| {
                 let mut neb_schema = Schema::new(
                     &schema.name,
@@ -146,6 +353,12 @@ impl SchemaContainer {
                     .map_err(|e| SchemaError::NewNebSchemaExecError(e))
             })
             .and_then(move |(schema_id, _)| {
+                neb_schema_cache.write().remove(&schema_id); // ids of dropped schemas can be reused
+                if !defaults.is_empty() {
+                    if let Err(e) = defaults_sm_client.insert(&schema_id, &defaults) {
+                        return Err(SchemaError::NewSchemaDefaultsExecError(e));
+                    }
+                }
                 match sm_client.insert(&schema_id, &schema_type) {
                     Ok(_) => Ok(schema_id),
                     Err(e) => Err(SchemaError::NewMorpheusSchemaExecError(e))
@@ -153,10 +366,82 @@ impl SchemaContainer {
             })
     }
 
+    pub fn del_schema(&self, schema_id: u32) -> impl Future<Item = (), Error = SchemaError> {
+        let sm_client = self.sm_client.clone();
+        let defaults_sm_client = self.defaults_sm_client.clone();
+        let indexes_sm_client = self.indexes_sm_client.clone();
+        let list_capacities_sm_client = self.list_capacities_sm_client.clone();
+        let neb_client = self.neb_client.clone();
+        let neb_schema_cache = self.neb_schema_cache.clone();
+        future::result(self.get_neb_schema(schema_id).ok_or(SchemaError::SchemaNotFound))
+            .and_then(move |neb_schema| {
+                neb_client.del_schema(&neb_schema.name)
+                    .map_err(|e| SchemaError::DelNebSchemaExecError(e))
+            })
+            .and_then(move |_| {
+                if let Err(e) = defaults_sm_client.remove(&schema_id) {
+                    return Err(SchemaError::DelMorpheusSchemaExecError(e));
+                }
+                neb_schema_cache.write().remove(&schema_id);
+                if let Err(e) = indexes_sm_client.remove(&schema_id) {
+                    return Err(SchemaError::DelMorpheusSchemaExecError(e));
+                }
+                if let Err(e) = list_capacities_sm_client.remove(&schema_id) {
+                    return Err(SchemaError::DelMorpheusSchemaExecError(e));
+                }
+                match sm_client.remove(&schema_id) {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(SchemaError::DelMorpheusSchemaExecError(e))
+                }
+            })
+    }
+
     pub fn schema_type(&self, schema_id: u32) -> Option<SchemaType> {
         Self::schema_type_(&self.map, schema_id)
     }
 
+    pub fn field_defaults(&self, schema_id: u32) -> Vec<(String, Value)> {
+        match self.defaults.get(&schema_id) {
+            Some(defaults) => defaults.clone(),
+            None => Vec::new()
+        }
+    }
+
+    pub fn indexed_fields(&self, schema_id: u32) -> Vec<u64> {
+        match self.indexes.get(&schema_id) {
+            Some(fields) => fields.clone(),
+            None => Vec::new()
+        }
+    }
+
+    pub fn add_index(&self, schema_id: u32, field: u64) -> Result<(), SchemaError> {
+        let mut fields = self.indexed_fields(schema_id);
+        if fields.contains(&field) {
+            return Ok(());
+        }
+        fields.push(field);
+        self.indexes_sm_client.insert(&schema_id, &fields)
+            .map_err(SchemaError::NewIndexExecError)?;
+        // take effect locally without waiting for the state machine callback
+        self.indexes.insert(schema_id, fields);
+        Ok(())
+    }
+
+    pub fn id_list_capacity(&self, schema_id: u32) -> Option<usize> {
+        self.list_capacities.get(&schema_id).map(|capacity| *capacity)
+    }
+
+    pub fn has_id_list_capacities(&self) -> bool {
+        !self.list_capacities.is_empty()
+    }
+
+    pub fn set_id_list_capacity(&self, schema_id: u32, capacity: usize) -> Result<(), SchemaError> {
+        self.list_capacities_sm_client.insert(&schema_id, &(capacity as u64))
+            .map_err(SchemaError::NewListCapacityExecError)?;
+        self.list_capacities.insert(schema_id, capacity);
+        Ok(())
+    }
+
     fn schema_type_(map: &Arc<CHashMap<u32, SchemaType>>, schema_id: u32) -> Option<SchemaType> {
         match map.get(&schema_id) {
             Some(t) => Some(*t),
@@ -164,6 +449,18 @@ impl SchemaContainer {
         }
     }
 
+    // served from the local schema type map and neb meta, without any rpc
+    pub fn list_schemas(&self) -> Vec<(u32, String, SchemaType)> {
+        let mut schemas: Vec<_> = (*self.map).clone()
+            .into_iter()
+            .filter_map(|(id, schema_type)| {
+                self.get_neb_schema(id).map(|schema| (id, schema.name.clone(), schema_type))
+            })
+            .collect();
+        schemas.sort_by_key(|&(id, _, _)| id);
+        schemas
+    }
+
     pub fn id_from_name<'a>(&self, name : &'a str) -> Option<u32> {
         self.neb_mata.schemas.name_to_id(name)
     }
@@ -175,13 +472,29 @@ impl SchemaContainer {
         }
     }
 
+    // Schemas are cached on first use, entries are evicted when the schema is created or dropped
     pub fn get_neb_schema(&self, schema_id: u32) -> Option<Arc<Schema>> {
-        self.neb_mata.schemas.get(&schema_id)
+        if let Some(schema) = self.neb_schema_cache.read().get(&schema_id) {
+            return Some(schema.clone());
+        }
+        let schema = self.neb_mata.schemas.get(&schema_id)?;
+        self.neb_schema_cache.write().insert(schema_id, schema.clone());
+        Some(schema)
+    }
+    pub fn evict_neb_schema(&self, schema_id: u32) {
+        self.neb_schema_cache.write().remove(&schema_id);
+    }
+    pub fn clear_neb_schema_cache(&self) {
+        self.neb_schema_cache.write().clear();
     }
     pub fn neb_to_morpheus_schema(&self, schema: &Arc<Schema>) -> Option<MorpheusSchema> {
-        Self::neb_to_morpheus_schema_(&self.map, schema)
+        Self::neb_to_morpheus_schema_(&self.map, &self.defaults, schema)
     }
-    fn neb_to_morpheus_schema_(schema_map: &Arc<CHashMap<u32, SchemaType>>, schema: &Arc<Schema>) -> Option<MorpheusSchema> {
+    fn neb_to_morpheus_schema_(
+        schema_map: &Arc<CHashMap<u32, SchemaType>>,
+        defaults_map: &Arc<CHashMap<u32, Vec<(String, Value)>>>,
+        schema: &Arc<Schema>
+    ) -> Option<MorpheusSchema> {
         if let Some(schema_type) = Self::schema_type_(schema_map, schema.id) {
             if let Some(ref fields) = schema.fields.sub_fields {
                 Some(MorpheusSchema {
@@ -190,6 +503,12 @@ impl SchemaContainer {
                     schema_type,
                     key_field: schema.str_key_field.clone(),
                     fields: fields.clone(),
+                    defaults: defaults_map.get(&schema.id).map(|d| d.clone()).unwrap_or_else(Vec::new),
+                    edge_directions: ALL_DIRECTIONS
+                        .iter()
+                        .filter(|ed| fields::vertex_list_keys(fields).contains(&ed.as_field()))
+                        .cloned()
+                        .collect(),
                     is_dynamic: schema.is_dynamic
                 })
             } else { None }
@@ -197,11 +516,12 @@ impl SchemaContainer {
     }
     pub fn all_morpheus_schemas(&self) -> impl Future<Item = Vec<MorpheusSchema>, Error = ExecError> {
         let schema_map = self.map.clone();
+        let defaults_map = self.defaults.clone();
         self.neb_client.get_all_schema()
             .map(move |neb_schemas| {
                 neb_schemas
                     .into_iter()
-                    .map(|schema| Self::neb_to_morpheus_schema_(&schema_map, &Arc::new(schema)))
+                    .map(|schema| Self::neb_to_morpheus_schema_(&schema_map, &defaults_map, &Arc::new(schema)))
                     .filter_map(|ms| ms)
                     .collect()
             })
@@ -263,3 +583,47 @@ impl <'a>ToSchemaId for &'a str {
     }
 }
 
+// Ids handed out by new_vertex_group and new_edge_group. Graph methods taking a vertex
+// schema only accept ToVertexSchemaId and edge methods only ToEdgeSchemaId, so the two
+// typed ids cannot be mixed up. Raw ids, names and schemas are accepted by both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VertexSchemaId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EdgeSchemaId(pub u32);
+
+pub trait ToVertexSchemaId: ToSchemaId {}
+pub trait ToEdgeSchemaId: ToSchemaId {}
+
+impl ToSchemaId for VertexSchemaId {
+    fn to_id(&self, _: &Arc<SchemaContainer>) -> u32 {
+        self.0
+    }
+}
+
+impl ToSchemaId for EdgeSchemaId {
+    fn to_id(&self, _: &Arc<SchemaContainer>) -> u32 {
+        self.0
+    }
+}
+
+impl ToVertexSchemaId for VertexSchemaId {}
+impl ToEdgeSchemaId for EdgeSchemaId {}
+
+impl ToVertexSchemaId for u32 {}
+impl ToVertexSchemaId for MorpheusSchema {}
+impl ToVertexSchemaId for Schema {}
+impl ToVertexSchemaId for Arc<Schema> {}
+impl ToVertexSchemaId for Arc<MorpheusSchema> {}
+impl <'a>ToVertexSchemaId for &'a MorpheusSchema {}
+impl <'a>ToVertexSchemaId for &'a Schema {}
+impl <'a>ToVertexSchemaId for &'a str {}
+
+impl ToEdgeSchemaId for u32 {}
+impl ToEdgeSchemaId for MorpheusSchema {}
+impl ToEdgeSchemaId for Schema {}
+impl ToEdgeSchemaId for Arc<Schema> {}
+impl ToEdgeSchemaId for Arc<MorpheusSchema> {}
+impl <'a>ToEdgeSchemaId for &'a MorpheusSchema {}
+impl <'a>ToEdgeSchemaId for &'a Schema {}
+impl <'a>ToEdgeSchemaId for &'a str {}
@@ -1,6 +1,13 @@
 use std::collections::HashMap;
 use super::SchemaType;
+use neb::dovahkiin::types::Value;
 
 pub static DEFAULT_RAFT_PREFIX: &'static str = "MORPHEUS_SCHEMA_RAFT_SM";
+pub static DEFAULTS_RAFT_PREFIX: &'static str = "MORPHEUS_SCHEMA_DEFAULTS_RAFT_SM";
+pub static INDEXES_RAFT_PREFIX: &'static str = "MORPHEUS_SCHEMA_INDEXES_RAFT_SM";
+pub static LIST_CAPACITIES_RAFT_PREFIX: &'static str = "MORPHEUS_SCHEMA_LIST_CAPACITIES_RAFT_SM";
 
 def_store_hash_map!(schema_types <u32, SchemaType>);
+def_store_hash_map!(schema_defaults <u32, Vec<(String, Value)>>);
+def_store_hash_map!(schema_indexes <u32, Vec<u64>>);
+def_store_hash_map!(schema_list_capacities <u32, u64>);
@@ -1,14 +1,34 @@
 use super::start_server;
+use server::MorpheusServer;
 use graph;
 use graph::*;
 use graph::edge::*;
+use graph::async_graph::AsyncGraph;
 use graph::vertex::*;
-use server::schema::{MorpheusSchema, SchemaError, EMPTY_FIELDS};
+use graph::vertex::typed::{TypedVertex, FieldError};
+use server::schema::{MorpheusSchema, SchemaError, SchemaType, EMPTY_FIELDS, VertexSchemaId, EdgeSchemaId};
 use neb::ram::schema::Field;
-use neb::ram::types::{TypeId, Value, Map};
-use neb::ram::cell::Cell;
+use neb::ram::types::{TypeId, Value, Map, Id, key_hash};
+use neb::ram::cell::{Cell, WriteError};
+use neb::client::transaction::TxnError;
 use env_logger;
 use futures::Future;
+use futures_cpupool::CpuPool;
+use std::time::Duration;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::cell;
+
+morpheus_vertex! {
+    "test_typed_vertex_schema" =>
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct TypedUser { pub name: String, pub age: i64 }
+}
+
+fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+    items.sort();
+    items
+}
 
 #[test]
 pub fn schemas() {
@@ -35,15 +55,200 @@ pub fn schemas() {
     let mut vertex_schema = edge_schema.clone();
     vertex_schema.name = "test_vertex_schema".to_string();
     let vertex_schema_id = graph.new_vertex_group(vertex_schema.clone()).wait().unwrap();
-    assert_eq!(edge_schema_id, 1);
-    assert_eq!(vertex_schema_id, 2);
+    assert_eq!(edge_schema_id, EdgeSchemaId(1));
+    assert_eq!(vertex_schema_id, VertexSchemaId(2));
     let mut test_data = Map::new();
-    vertex_schema.id = vertex_schema_id;
+    vertex_schema.id = vertex_schema_id.0;
         test_data.insert("test_field", Value::U32(1));
     graph.new_vertex(vertex_schema, test_data.clone()).wait().unwrap();
     graph.new_vertex("test_edge_schema", test_data.clone()).wait().is_err();
 }
 
+// test_vertex_schema has a single U32 field that may not be null
+fn test_vertex_schema(port: u32, group: &str) -> Arc<MorpheusServer> {
+    let server = start_server(port, group);
+    let vertex_schema = MorpheusSchema::new("test_vertex_schema", None, &vec![
+        Field::new(&"test_field", TypeId::U32 as u32, false, false, None)
+    ], false);
+    server.graph.new_vertex_group(vertex_schema).wait().unwrap();
+    server
+}
+
+#[test]
+pub fn required_fields() {
+    let server = test_vertex_schema(4024, "required_fields");
+    match server.graph.new_vertex("test_vertex_schema", Map::new()).wait() {
+        Err(NewVertexError::MissingRequiredField(field)) => assert_eq!(field, key_hash(&String::from("test_field"))),
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+pub fn field_types() {
+    let server = test_vertex_schema(4025, "field_types");
+    let mut mistyped_data = Map::new();
+    mistyped_data.insert("test_field", Value::String("one".to_string()));
+    match server.graph.new_vertex("test_vertex_schema", mistyped_data).wait() {
+        Err(NewVertexError::FieldTypeMismatch { field, expected, got }) => {
+            assert_eq!(field, key_hash(&String::from("test_field")));
+            assert_eq!(expected, TypeId::U32 as u32);
+            assert_eq!(got, TypeId::String as u32);
+        },
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+pub fn field_defaults() {
+    let server = start_server(4026, "field_defaults");
+    let graph = &server.graph;
+    let defaulted_schema = MorpheusSchema::new("test_defaulted_vertex_schema", None, &vec![
+        Field::new(&"test_field", TypeId::U32 as u32, false, false, None)
+    ], false).with_default("test_field", Value::U32(7));
+    graph.new_vertex_group(defaulted_schema).wait().unwrap();
+    let defaulted = graph.new_vertex("test_defaulted_vertex_schema", Map::new()).wait().unwrap();
+    match defaulted["test_field"] {
+        Value::U32(7) => {},
+        ref other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+pub fn edge_directions() {
+    let server = start_server(4027, "edge_directions");
+    let graph = &server.graph;
+    let undirected_only = MorpheusSchema::new("test_undirected_vertex_schema", None, &EMPTY_FIELDS, false)
+        .with_edge_directions(&[EdgeDirection::Undirected]);
+    graph.new_vertex_group(undirected_only).wait().unwrap();
+    graph.new_edge_group(
+        MorpheusSchema::new("test_undirected_edge_schema", None, &EMPTY_FIELDS, false),
+        graph::edge::EdgeAttributes::new(graph::edge::EdgeType::Undirected, false)
+    ).wait().unwrap();
+    let x = graph.new_vertex("test_undirected_vertex_schema", Map::new()).wait().unwrap();
+    let y = graph.new_vertex("test_undirected_vertex_schema", Map::new()).wait().unwrap();
+    match x[*graph::fields::OUTBOUND_KEY_ID] {
+        Value::Null => {},
+        ref other => panic!("{:?}", other)
+    }
+    let undirected_edge = graph.link(&x, "test_undirected_edge_schema", &y, None).wait().unwrap().unwrap();
+    assert!(undirected_edge.reversed().is_none());
+    assert!(graph.are_linked(&x, "test_undirected_edge_schema", &y, EdgeDirection::Undirected).wait().unwrap().unwrap());
+    assert_eq!(
+        server.schema_container.from_name("test_undirected_vertex_schema").unwrap().edge_directions.len(), 1
+    );
+}
+
+#[test]
+pub fn typed_vertices() {
+    let server = test_vertex_schema(4028, "typed_vertices");
+    let graph = &server.graph;
+    graph.new_vertex_group(TypedUser::schema()).wait().unwrap();
+    let user = TypedUser { name: "Jack".to_string(), age: 30 };
+    let stored = graph.new_vertex_typed(&user).wait().unwrap();
+    assert_eq!(TypedUser::from_vertex(&stored).unwrap(), user);
+    let untyped = graph.new_vertex("test_vertex_schema", data_map!{ test_field: 1 as u32 }).wait().unwrap();
+    assert_eq!(TypedUser::from_vertex(&untyped), Err(FieldError::Missing("name")));
+}
+
+#[test]
+pub fn reserved_fields() {
+    let server = test_vertex_schema(4029, "reserved_fields");
+    let mut clobbering = Map::new();
+    clobbering.insert("test_field", Value::U32(2));
+    clobbering.insert_key_id(*graph::fields::INBOUND_KEY_ID, Value::U32(7));
+    match server.graph.new_vertex("test_vertex_schema", clobbering).wait() {
+        Err(NewVertexError::ReservedFieldConflict(field)) => assert_eq!(field, *graph::fields::INBOUND_KEY_ID),
+        other => panic!("data under edge list keys should be rejected, got {:?}", other)
+    }
+}
+
+#[test]
+pub fn vertex_keys() {
+    let server = test_vertex_schema(4030, "vertex_keys");
+    let graph = &server.graph;
+    let mut imported = Map::new();
+    imported.insert("test_field", Value::U32(3));
+    let first_import = graph.new_vertex_with_key("test_vertex_schema", &"record-1", imported.clone(), false).wait().unwrap();
+    match graph.new_vertex_with_key("test_vertex_schema", &"record-1", imported.clone(), false).wait() {
+        Err(NewVertexError::VertexExisted(id)) => assert_eq!(id, first_import.cell.id()),
+        other => panic!("the same key should collide, got {:?}", other)
+    }
+    imported.insert("test_field", Value::U32(4));
+    let reimport = graph.new_vertex_with_key("test_vertex_schema", &"record-1", imported, true).wait().unwrap();
+    assert_eq!(reimport.cell.id(), first_import.cell.id());
+    match graph.vertex_by(&first_import).wait().unwrap().unwrap()["test_field"] {
+        Value::U32(4) => {},
+        ref other => panic!("overwrite should replace the data, got {:?}", other)
+    }
+}
+
+#[test]
+pub fn metadata() {
+    let server = start_server(4031, "metadata");
+    let graph = &server.graph;
+    let meta_cell_id = Cell::encode_cell_key(graph::meta::META_SCHEMA_ID, &Value::String("graph".to_string()));
+    assert!(graph.read_cell_raw(&meta_cell_id).wait().unwrap().is_some()); // written on startup
+    assert!(graph.get_meta("schema_version").wait().unwrap().unwrap().is_none());
+    graph.set_meta("schema_version", Value::U32(3)).wait().unwrap().unwrap();
+    graph.set_meta("imported_from", Value::String("people.csv".to_string())).wait().unwrap().unwrap();
+    graph.set_meta("schema_version", Value::U32(4)).wait().unwrap().unwrap();
+    match graph.get_meta("schema_version").wait().unwrap().unwrap() {
+        Some(Value::U32(4)) => {},
+        other => panic!("expected the latest version, got {:?}", other)
+    }
+    assert_eq!(graph.get_meta("imported_from").wait().unwrap().unwrap().unwrap().String().unwrap(), "people.csv");
+    // maps and arrays are stored as they are
+    let sources = Value::Array(vec![Value::String("people.csv".to_string()), Value::String("roads.csv".to_string())]);
+    graph.set_meta("sources", sources).wait().unwrap().unwrap();
+    match graph.get_meta("sources").wait().unwrap().unwrap() {
+        Some(Value::Array(ref sources)) => {
+            assert_eq!(sources.len(), 2);
+            assert_eq!(sources[1].String().unwrap(), "roads.csv");
+        },
+        other => panic!("expected the sources array, got {:?}", other)
+    }
+    graph.set_meta("config", Value::Map(data_map!{ batch_size: 64 as u32, dry_run: false })).wait().unwrap().unwrap();
+    match graph.get_meta("config").wait().unwrap().unwrap() {
+        Some(config @ Value::Map(_)) => {
+            assert_eq!(config["batch_size"].U32().unwrap(), 64);
+            assert!(match config["dry_run"] { Value::Bool(dry_run) => !dry_run, _ => false });
+        },
+        other => panic!("expected the config map, got {:?}", other)
+    }
+    graph.set_meta("imported_from", Value::Null).wait().unwrap().unwrap();
+    assert!(graph.get_meta("imported_from").wait().unwrap().unwrap().is_none());
+}
+
+#[test]
+pub fn schema_validation() {
+    let server = start_server(4032, "schema_validation");
+    let graph = &server.graph;
+    let mut checked_schema = MorpheusSchema::new("checked", Some(&vec!["name".to_string()]), &vec![
+        Field::new("name", TypeId::String as u32, false, false, None)
+    ], false);
+    checked_schema.schema_type = SchemaType::Vertex;
+    graph.validate_schema(&checked_schema).unwrap();
+    let mut reserved_schema = checked_schema.clone().with_edge_directions(&[EdgeDirection::Outbound]);
+    reserved_schema.fields.push(Field::new("_inbound", TypeId::Id as u32, false, false, None));
+    match graph.validate_schema(&reserved_schema) {
+        Err(SchemaError::ReservedField(ref name)) if name == "_inbound" => {},
+        other => panic!("direction keys should be reserved, got {:?}", other)
+    }
+    let mut duplicated_schema = checked_schema.clone();
+    duplicated_schema.fields.push(Field::new("name", TypeId::U32 as u32, false, false, None));
+    match graph.validate_schema(&duplicated_schema) {
+        Err(SchemaError::DuplicateField(ref name)) if name == "name" => {},
+        other => panic!("duplicate fields should be rejected, got {:?}", other)
+    }
+    let mut unkeyed_schema = checked_schema.clone();
+    unkeyed_schema.key_field = Some(vec!["email".to_string()]);
+    match graph.validate_schema(&unkeyed_schema) {
+        Err(SchemaError::KeyFieldNotFound(ref name)) if name == "email" => {},
+        other => panic!("key fields should exist, got {:?}", other)
+    }
+    assert!(server.schema_container.from_name("checked").is_none()); // nothing was registered
+}
+
 #[test]
 pub fn relationship() {
     env_logger::init();
@@ -76,10 +281,10 @@ pub fn relationship() {
             false
         )
     ).wait().unwrap();
-    assert_eq!(people_schema_id, 1);
-    assert_eq!(movie_schema_id, 2);
-    assert_eq!(acted_in_schema_id, 3);
-    assert_eq!(spouse_schema_id, 4);
+    assert_eq!(people_schema_id, VertexSchemaId(1));
+    assert_eq!(movie_schema_id, VertexSchemaId(2));
+    assert_eq!(acted_in_schema_id, EdgeSchemaId(3));
+    assert_eq!(spouse_schema_id, EdgeSchemaId(4));
     let morgan_freeman_name = "Morgan Freeman";
     let batman_begins_name = "Batman Begins";
     let the_dark_knight_name = "The Dark Knight";
@@ -185,7 +390,7 @@ pub fn relationship() {
 
     let should_error = graph.link(&morgan_freeman, "acted-in", &oblivion, Some(data_map!{
         // missing required field should fail
-    })).wait().err().unwrap();
+    })).wait().unwrap().err().unwrap();
     {
         let neighbourhoods_should_have = 3;
         let morgan_acted_in =
@@ -239,3 +444,1134 @@ pub fn relationship() {
             .wait().unwrap().unwrap());
 }
 
+// people are keyed by name, knows is a directed edge without body and rated one with a score
+fn people_network(port: u32, group: &str) -> (Arc<MorpheusServer>, EdgeSchemaId, EdgeSchemaId) {
+    let server = start_server(port, group);
+    let (knows_schema_id, rated_schema_id) = {
+        let graph = &server.graph;
+        let people_schema = MorpheusSchema::new("people", Some(&vec!["name".to_string()]), &vec! [
+            Field::new("name", TypeId::String as u32, false, false, None)
+        ], true);
+        let knows_schema = MorpheusSchema::new("knows", None, &EMPTY_FIELDS, false);
+        let rated_schema = MorpheusSchema::new("rated", None, &vec! [
+            Field::new("score", TypeId::U32 as u32, false, false, None)
+        ], true);
+        graph.new_vertex_group(people_schema).wait().unwrap();
+        (graph.new_edge_group(knows_schema, EdgeAttributes::new(EdgeType::Directed, false)).wait().unwrap(),
+         graph.new_edge_group(rated_schema, EdgeAttributes::new(EdgeType::Directed, true)).wait().unwrap())
+    };
+    (server, knows_schema_id, rated_schema_id)
+}
+
+#[test]
+pub fn edge_validation() {
+    let (server, _, _) = people_network(4033, "edge_validation");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    match graph.link(&alice, "rated", &bob, Some(Map::new())).wait().unwrap() {
+        Err(LinkVerticesError::InvalidBody(EdgeBodyError::MissingRequiredField(field))) =>
+            assert_eq!(field, key_hash(&String::from("score"))),
+        other => panic!("{:?}", other)
+    }
+    match graph.link(&alice, "rated", &bob, Some(data_map!{ score: "high" })).wait().unwrap() {
+        Err(LinkVerticesError::InvalidBody(EdgeBodyError::FieldTypeMismatch { .. })) => {},
+        other => panic!("{:?}", other)
+    }
+    assert_eq!(graph.degree(&alice, "rated", EdgeDirection::Outbound).wait().unwrap().unwrap(), 0);
+}
+
+#[test]
+pub fn unlink() {
+    let (server, knows_schema_id, _) = people_network(4003, "unlink");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    graph.link(&alice, "knows", &bob, None).wait().unwrap().unwrap();
+    assert_eq!(graph.degree(&alice, "knows", EdgeDirection::Outbound).wait().unwrap().unwrap(), 1);
+    assert_eq!(graph.degree(&alice, knows_schema_id, EdgeDirection::Outbound).wait().unwrap().unwrap(), 1);
+    assert_eq!(graph.degree(&bob, "knows", EdgeDirection::Inbound).wait().unwrap().unwrap(), 1);
+    assert!(graph.are_linked(&alice, "knows", &bob, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    assert!(!graph.are_linked(&bob, "knows", &alice, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    graph.unlink(&alice, "knows", &bob).wait().unwrap().unwrap();
+    assert_eq!(graph.degree(&alice, "knows", EdgeDirection::Outbound).wait().unwrap().unwrap(), 0);
+    assert_eq!(graph.degree(&bob, "knows", EdgeDirection::Inbound).wait().unwrap().unwrap(), 0);
+    assert!(!graph.are_linked(&alice, "knows", &bob, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    match graph.unlink(&alice, "knows", &bob).wait().unwrap() {
+        Err(EdgeError::EdgeNotFound) => {},
+        other => panic!("unlinking a missing edge should fail, got {:?}", other)
+    }
+}
+
+#[test]
+pub fn self_loops() {
+    let (server, _, _) = people_network(4034, "self_loops");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    match graph.link_no_self_loop(&alice, "knows", &alice, None).wait().unwrap() {
+        Err(LinkVerticesError::SelfLoopNotAllowed) => {},
+        other => panic!("self loop should be rejected, got {:?}", other)
+    }
+    assert_eq!(graph.degree(&alice, "knows", EdgeDirection::Outbound).wait().unwrap().unwrap(), 0);
+    graph.link_no_self_loop(&alice, "knows", &bob, None).wait().unwrap().unwrap();
+    assert!(graph.are_linked(&alice, "knows", &bob, EdgeDirection::Outbound).wait().unwrap().unwrap());
+}
+
+#[test]
+pub fn checked_links() {
+    let (server, _, _) = people_network(4035, "checked_links");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    let nobody = Cell::encode_cell_key(alice.cell.header.schema, &Value::String("Nobody".to_string()));
+    match graph.link_checked(alice.cell.id(), "knows", nobody, None).wait().unwrap() {
+        Err(LinkVerticesError::EndpointNotFound(id)) => assert_eq!(id, nobody),
+        other => panic!("linking to a missing vertex should fail, got {:?}", other)
+    }
+    assert_eq!(graph.degree(&alice, "knows", EdgeDirection::Outbound).wait().unwrap().unwrap(), 0);
+    graph.link_checked(alice.cell.id(), "knows", bob.cell.id(), None).wait().unwrap().unwrap();
+    assert!(graph.are_linked(&alice, "knows", &bob, EdgeDirection::Outbound).wait().unwrap().unwrap());
+}
+
+#[test]
+pub fn merge_vertices() {
+    let (server, _, _) = people_network(4036, "merge_vertices");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    let carol = graph.new_vertex("people", data_map!{ name: "Carol" }).wait().unwrap();
+    graph.link(&alice, "knows", &bob, None).wait().unwrap().unwrap();
+    graph.link(&carol, "knows", &alice, None).wait().unwrap().unwrap();
+    graph.link(&alice, "knows", &carol, None).wait().unwrap().unwrap(); // duplicates alice -> bob after the merge
+    graph.link(&bob, "knows", &carol, None).wait().unwrap().unwrap(); // would become a self-loop
+    graph.merge_vertices(&bob, &carol).wait().unwrap().unwrap();
+    assert!(graph.vertex_by(&carol).wait().unwrap().is_none());
+    assert!(graph.are_linked(&bob, "knows", &alice, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    assert_eq!(graph.degree(&alice, "knows", EdgeDirection::Outbound).wait().unwrap().unwrap(), 1);
+    assert_eq!(graph.degree(&bob, "knows", EdgeDirection::Outbound).wait().unwrap().unwrap(), 1);
+    assert_eq!(graph.degree(&bob, "knows", EdgeDirection::Inbound).wait().unwrap().unwrap(), 1);
+    assert_eq!(graph.vertex_by(&bob).wait().unwrap().unwrap()["name"].String().unwrap(), "Bob");
+}
+
+#[test]
+pub fn vertex_values() {
+    let (server, _, _) = people_network(4037, "vertex_values");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap().cell.id();
+    assert_eq!(graph.get_vertex_by_value("people", &Value::String("Alice".to_string())).wait().unwrap().unwrap().cell.id(), alice);
+    assert!(graph.get_vertex_by_value("people", &Value::String("Bob".to_string())).wait().unwrap().is_none());
+}
+
+#[test]
+pub fn raw_cells() {
+    let (server, _, _) = people_network(4038, "raw_cells");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap().cell.id();
+    let raw = graph.read_cell_raw(&alice).wait().unwrap().unwrap();
+    let alice_vertex = graph.vertex_by(&alice).wait().unwrap().unwrap();
+    assert_eq!(raw.header.schema, alice_vertex.as_cell().header.schema);
+    assert_eq!(raw.id(), alice_vertex.as_cell().id());
+    let nobody = Cell::encode_cell_key(raw.header.schema, &Value::String("Nobody".to_string()));
+    assert!(graph.read_cell_raw(&nobody).wait().unwrap().is_none());
+}
+
+#[test]
+pub fn labels() {
+    let (server, _, _) = people_network(4039, "labels");
+    let graph = &server.graph;
+    let alice_vertex = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let alice = alice_vertex.cell.id();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap().cell.id();
+    let carol = graph.new_vertex("people", data_map!{ name: "Carol" }).wait().unwrap().cell.id();
+    graph.add_label(&bob, "verified").wait().unwrap().unwrap();
+    graph.add_label(&bob, "verified").wait().unwrap().unwrap();
+    graph.add_label(&alice, "verified").wait().unwrap().unwrap();
+    let verified = graph.find_by_label("people", "verified").wait().unwrap().unwrap();
+    assert_eq!(sorted(verified), sorted(vec![alice, bob]));
+    graph.remove_label(&bob, "verified").wait().unwrap().unwrap();
+    assert_eq!(graph.find_by_label("people", "verified").wait().unwrap().unwrap(), vec![alice]);
+    assert!(graph.find_by_label("people", "flagged").wait().unwrap().unwrap().is_empty());
+    let nobody = Cell::encode_cell_key(alice_vertex.schema(), &Value::String("Nobody".to_string()));
+    match graph.add_label(&nobody, "flagged").wait().unwrap() {
+        Err(ReadVertexError::ReadError(_)) => {},
+        other => panic!("labelling a missing vertex should fail, got {:?}", other)
+    }
+    // labels follow merged and rekeyed vertices and go with removed ones
+    graph.add_label(&carol, "friendly").wait().unwrap().unwrap();
+    graph.merge_vertices(&bob, &carol).wait().unwrap().unwrap();
+    assert_eq!(graph.find_by_label("people", "friendly").wait().unwrap().unwrap(), vec![bob]);
+    let robert = graph.rekey_vertex(&bob, "people", &Value::String("Robert".to_string()))
+        .wait().unwrap().unwrap();
+    assert_eq!(graph.find_by_label("people", "friendly").wait().unwrap().unwrap(), vec![robert]);
+    graph.remove_vertex(&robert).wait().unwrap().unwrap();
+    assert!(graph.find_by_label("people", "friendly").wait().unwrap().unwrap().is_empty());
+}
+
+#[test]
+pub fn unlink_where() {
+    let (server, knows_schema_id, _) = people_network(4040, "unlink_where");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap().cell.id();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap().cell.id();
+    let carol = graph.new_vertex("people", data_map!{ name: "Carol" }).wait().unwrap().cell.id();
+    graph.link(&alice, "knows", &bob, None).wait().unwrap().unwrap();
+    graph.link(&alice, "knows", &carol, None).wait().unwrap().unwrap();
+    graph.link(&bob, "knows", &alice, None).wait().unwrap().unwrap();
+    let removed = graph.graph_transaction(move |txn| {
+        Ok(txn.unlink_where(&alice, knows_schema_id, EdgeDirection::Outbound,
+                            |edge| edge.endpoints().1 == bob)?.unwrap())
+    }).wait().unwrap();
+    assert_eq!(removed, 1);
+    assert!(!graph.are_linked(&alice, "knows", &bob, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    assert_eq!(graph.degree(&bob, "knows", EdgeDirection::Inbound).wait().unwrap().unwrap(), 0);
+    assert!(graph.are_linked(&bob, "knows", &alice, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    assert!(graph.are_linked(&alice, "knows", &carol, EdgeDirection::Outbound).wait().unwrap().unwrap());
+}
+
+#[test]
+pub fn retrying() {
+    let (server, _, _) = people_network(4041, "retrying");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let dave = graph.new_vertex("people", data_map!{ name: "Dave" }).wait().unwrap();
+    graph.link_retrying(&alice, "knows", &dave, None, 3).wait().unwrap().unwrap();
+    assert!(graph.are_linked(&alice, "knows", &dave, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    graph.unlink_retrying(&alice, "knows", &dave, 3).wait().unwrap().unwrap();
+    match graph.unlink_retrying(&alice, "knows", &dave, 3).wait().unwrap() {
+        Err(EdgeError::EdgeNotFound) => {},
+        other => panic!("unlinking a missing edge should fail, got {:?}", other)
+    }
+    graph.remove_vertex_retrying(&dave, 3).wait().unwrap().unwrap();
+    assert!(graph.vertex_by(&dave).wait().unwrap().is_none());
+}
+
+#[test]
+pub fn retry_aborts() {
+    let server = start_server(4042, "retry_aborts");
+    let attempts = Rc::new(cell::Cell::new(0));
+    let attempts_ref = attempts.clone();
+    let given_up = server.graph.graph_transaction_retry(3, move |_txn| -> Result<(), TxnError> {
+        attempts_ref.set(attempts_ref.get() + 1);
+        Err(TxnError::Aborted(None)) // a deliberate abort is not retried
+    }).wait();
+    assert!(given_up.is_err());
+    assert_eq!(attempts.get(), 1);
+}
+
+#[test]
+pub fn edge_body() {
+    let (server, _, rated_schema_id) = people_network(4004, "edge_body");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    let edge = graph.link(&alice, "rated", &bob, Some(data_map!{ score: 1 as u32 }))
+        .wait().unwrap().unwrap();
+    let edge_id = edge.get_data().as_ref().unwrap().id();
+    graph.update_edge(&edge_id, rated_schema_id, |mut edge| {
+        edge["score"] = Value::U32(5);
+        Some(edge)
+    }).wait().unwrap().unwrap();
+    let score = graph.graph_transaction(move |txn| {
+        let edge = txn.read_edge(&edge_id, rated_schema_id)?.unwrap().unwrap();
+        Ok(match edge["score"] { Value::U32(score) => Some(score), _ => None })
+    }).wait().unwrap();
+    assert_eq!(score, Some(5));
+}
+
+#[test]
+pub fn edge_attributes() {
+    assert_eq!(EdgeAttributes::directed().with_body(), EdgeAttributes::new(EdgeType::Directed, true));
+    assert_eq!(EdgeAttributes::bilateral(), EdgeAttributes::undirected().with_body());
+    assert_eq!(EdgeAttributes::default(), EdgeAttributes::directed());
+}
+
+#[test]
+pub fn compound_keys() {
+    let schema_id = 7;
+    assert_eq!(Graph::encode_compound_key(schema_id, &["a", "bc"]), Graph::encode_compound_key(schema_id, &["a", "bc"]));
+    assert!(Graph::encode_compound_key(schema_id, &["a", "bc"]) != Graph::encode_compound_key(schema_id, &["ab", "c"]));
+    assert!(Graph::encode_compound_key(schema_id, &[1u32, 2]) != Graph::encode_compound_key(schema_id + 1, &[1u32, 2]));
+}
+
+#[test]
+pub fn field_getters() {
+    let (server, _, _) = people_network(4043, "field_getters");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    let edge = graph.link(&alice, "rated", &bob, Some(data_map!{ score: 1 as u32 })).wait().unwrap().unwrap();
+    assert_eq!(alice.get_str("name"), Some("Alice"));
+    assert_eq!(alice.get_i64("name"), None);
+    assert_eq!(edge.get_i64("score"), Some(1));
+    assert_eq!(edge.get_f64(key_hash(&String::from("score"))), Some(1.0));
+    assert_eq!(edge.get_str("score"), None);
+    assert_eq!(edge.get_id("missing"), None);
+}
+
+#[test]
+pub fn edge_accessors() {
+    let (server, _, rated_schema_id) = people_network(4044, "edge_accessors");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    let edge = graph.link(&alice, "rated", &bob, Some(data_map!{ score: 1 as u32 })).wait().unwrap().unwrap();
+    assert_eq!(edge.schema_id(), rated_schema_id.0);
+    assert_eq!(edge.edge_type(), EdgeType::Directed);
+    assert_eq!(edge.endpoints(), (alice.cell.id(), bob.cell.id()));
+}
+
+#[test]
+pub fn vertex_schema_ids() {
+    let server = start_server(4045, "vertex_schema_ids");
+    let graph = &server.graph;
+    let people_schema = MorpheusSchema::new("people", Some(&vec!["name".to_string()]), &vec! [
+        Field::new("name", TypeId::String as u32, false, false, None)
+    ], true);
+    let people_schema_id = graph.new_vertex_group(people_schema).wait().unwrap();
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    assert_eq!(alice.schema_id(), people_schema_id.0);
+    assert_eq!(alice.schema_id(), alice.schema());
+}
+
+#[test]
+pub fn field_lookup() {
+    let (server, _, rated_schema_id) = people_network(4046, "field_lookup");
+    let graph = &server.graph;
+    assert_eq!(graph.field_id_in_schema(rated_schema_id.0, "score"), Some(Graph::field_id("score")));
+    assert_eq!(graph.field_id_in_schema(rated_schema_id.0, "scroe"), None);
+    assert_eq!(graph.field_id_in_schema(1000, "score"), None);
+}
+
+#[test]
+pub fn schema_cache() {
+    let (server, _, rated_schema_id) = people_network(4047, "schema_cache");
+    let graph = &server.graph;
+    assert_eq!(graph.field_id_in_schema(rated_schema_id.0, "score"), Some(Graph::field_id("score")));
+    graph.refresh_schema_cache(); // the next lookup fetches the schema from neb again
+    assert_eq!(graph.field_id_in_schema(rated_schema_id.0, "score"), Some(Graph::field_id("score")));
+}
+
+#[test]
+pub fn edge_ids() {
+    let (server, _, _) = people_network(4048, "edge_ids");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap();
+    let rated = graph.link(&alice, "rated", &bob, Some(data_map!{ score: 1 as u32 })).wait().unwrap().unwrap();
+    assert_eq!(rated.id(), Some(rated.get_data().as_ref().unwrap().id()));
+    let knows = graph.link(&alice, "knows", &bob, None).wait().unwrap().unwrap();
+    assert_eq!(knows.id(), None); // simple edges have no cell of their own
+}
+
+#[test]
+pub fn weighted_degrees() {
+    let (server, _, _) = people_network(4049, "weighted_degrees");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap().cell.id();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap().cell.id();
+    let carol = graph.new_vertex("people", data_map!{ name: "Carol" }).wait().unwrap().cell.id();
+    graph.link(&alice, "rated", &bob, Some(data_map!{ score: 2 as u32 })).wait().unwrap().unwrap();
+    graph.link(&alice, "rated", &carol, Some(data_map!{ score: 3 as u32 })).wait().unwrap().unwrap();
+    let (score_field, missing_field) = (Graph::field_id("score"), Graph::field_id("missing"));
+    let weighted = graph.graph_transaction(move |txn| {
+        Ok((
+            txn.weighted_degree(&alice, "rated", EdgeDirection::Outbound, score_field, false)?.unwrap(),
+            txn.weighted_degree(&alice, "rated", EdgeDirection::Outbound, missing_field, true)?.unwrap(),
+            txn.weighted_degree(&alice, "rated", EdgeDirection::Outbound, missing_field, false)?.is_err()
+        ))
+    }).wait().unwrap();
+    assert_eq!(weighted, (5f64, 0f64, true));
+}
+
+#[test]
+pub fn indexes() {
+    let (server, _, _) = people_network(4050, "indexes");
+    let graph = &server.graph;
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap();
+    let name_field = Graph::field_id("name");
+    let alice_name = Value::String("Alice".to_string());
+    match graph.find_by_index("people", name_field, &alice_name).wait().unwrap() {
+        Err(ReadVertexError::FieldNotIndexed(_)) => {},
+        other => panic!("{:?}", other)
+    }
+    graph.create_index("people", name_field).wait().unwrap(); // indexes existing vertices
+    assert!(graph.create_index("rated", name_field).wait().is_err());
+    assert_eq!(graph.find_by_index("people", name_field, &alice_name).wait().unwrap().unwrap(), vec![alice.cell.id()]);
+    let carol = graph.new_vertex("people", data_map!{ name: "Carol" }).wait().unwrap();
+    let carol_name = Value::String("Carol".to_string());
+    assert_eq!(graph.find_by_index("people", name_field, &carol_name).wait().unwrap().unwrap(), vec![carol.cell.id()]);
+    graph.remove_vertex(&carol).wait().unwrap().unwrap();
+    assert!(graph.find_by_index("people", name_field, &carol_name).wait().unwrap().unwrap().is_empty());
+}
+
+// a -> b -> c -> d -> a, a -> c, e is unreachable
+fn road_network(port: u32, group: &str) -> (Arc<MorpheusServer>, EdgeSchemaId, Vec<Id>) {
+    let server = start_server(port, group);
+    let road_schema_id = {
+        let graph = &server.graph;
+        let city_schema = MorpheusSchema::new("city", Some(&vec!["name".to_string()]), &vec! [
+            Field::new("name", TypeId::String as u32, false, false, None)
+        ], true);
+        let road_schema = MorpheusSchema::new("road", None, &EMPTY_FIELDS, false);
+        graph.new_vertex_group(city_schema).wait().unwrap();
+        graph.new_edge_group(road_schema, EdgeAttributes::new(EdgeType::Directed, false)).wait().unwrap()
+    };
+    let ids: Vec<Id> = ["a", "b", "c", "d", "e"].iter()
+        .map(|name| server.graph.new_vertex("city", data_map!{ name: *name }).wait().unwrap().cell.id())
+        .collect();
+    for &(from, to) in &[(0, 1), (1, 2), (2, 3), (3, 0), (0, 2)] {
+        let road = server.graph.link(&ids[from], "road", &ids[to], None).wait().unwrap().unwrap();
+        assert_eq!(road.id(), None); // simple edges have no cell of their own
+    }
+    (server, road_schema_id, ids)
+}
+
+#[test]
+pub fn traversal() {
+    let (server, _, ids) = road_network(4005, "traversal");
+    let graph = &server.graph;
+    let city_json = graph.vertex_by(&ids[0]).wait().unwrap().unwrap().to_json();
+    assert_eq!(city_json["data"].as_object().unwrap().len(), 1); // internal edge lists are hidden
+    let mut scanned: Vec<_> = graph.scan_edges("road").wait().unwrap().unwrap()
+        .iter().map(|edge| edge.endpoints()).collect();
+    scanned.sort();
+    let mut roads: Vec<_> = [(0, 1), (1, 2), (2, 3), (3, 0), (0, 2)].iter()
+        .map(|&(from, to)| (ids[from], ids[to])).collect();
+    roads.sort();
+    assert_eq!(scanned, roads);
+    let visited = graph.bfs(&ids[0], "road", EdgeDirection::Outbound, ::std::usize::MAX)
+        .wait().unwrap().unwrap();
+    assert_eq!(visited, vec![(ids[0], 0), (ids[1], 1), (ids[2], 1), (ids[3], 2)]);
+    let visited = graph.bfs(&ids[0], "road", EdgeDirection::Outbound, 1)
+        .wait().unwrap().unwrap();
+    assert_eq!(visited.len(), 3);
+    assert_eq!(
+        graph.k_hop(&ids[1], "road", EdgeDirection::Outbound, 2).wait().unwrap().unwrap(),
+        vec![ids[2], ids[3]]
+    );
+    let walk = graph.random_walk(&ids[1], "road", EdgeDirection::Outbound, 10, 42).wait().unwrap().unwrap();
+    assert_eq!(walk.len(), 11);
+    assert_eq!(walk[0], ids[1]);
+    assert_eq!(walk, graph.random_walk(&ids[1], "road", EdgeDirection::Outbound, 10, 42).wait().unwrap().unwrap());
+    assert_eq!(
+        graph.random_walk(&ids[4], "road", EdgeDirection::Outbound, 10, 42).wait().unwrap().unwrap(),
+        vec![ids[4]]
+    );
+}
+
+#[test]
+pub fn paths() {
+    let (server, _, ids) = road_network(4006, "paths");
+    let graph = &server.graph;
+    assert_eq!(
+        graph.shortest_path(&ids[0], "road", &ids[3], EdgeDirection::Outbound).wait().unwrap().unwrap(),
+        Some(vec![ids[0], ids[2], ids[3]])
+    );
+    assert_eq!(
+        graph.shortest_path(&ids[3], "road", &ids[2], EdgeDirection::Outbound).wait().unwrap().unwrap(),
+        Some(vec![ids[3], ids[0], ids[2]])
+    );
+    assert_eq!(
+        graph.shortest_path(&ids[0], "road", &ids[4], EdgeDirection::Outbound).wait().unwrap().unwrap(),
+        None
+    );
+    assert_eq!(
+        graph.all_paths(&ids[0], "road", &ids[3], EdgeDirection::Outbound, 3).wait().unwrap().unwrap(),
+        vec![vec![ids[0], ids[1], ids[2], ids[3]], vec![ids[0], ids[2], ids[3]]]
+    );
+    assert_eq!(
+        graph.all_paths(&ids[0], "road", &ids[3], EdgeDirection::Outbound, 2).wait().unwrap().unwrap(),
+        vec![vec![ids[0], ids[2], ids[3]]]
+    );
+    for &(from, to) in &[(0, 3), (3, 2), (1, 0), (0, 4), (2, 2)] {
+        assert_eq!(
+            graph.shortest_path_bidirectional(&ids[from], "road", &ids[to], EdgeDirection::Outbound)
+                .wait().unwrap().unwrap(),
+            graph.shortest_path(&ids[from], "road", &ids[to], EdgeDirection::Outbound).wait().unwrap().unwrap()
+        );
+    }
+    assert_eq!(
+        graph.shortest_path_bidirectional(&ids[1], "road", &ids[0], EdgeDirection::Outbound)
+            .wait().unwrap().unwrap(),
+        Some(vec![ids[1], ids[2], ids[3], ids[0]])
+    );
+    assert_eq!(
+        graph.sssp_tree(&ids[0], "road", EdgeDirection::Outbound).wait().unwrap().unwrap(),
+        vec![(ids[0], 0, None), (ids[1], 1, Some(ids[0])), (ids[2], 1, Some(ids[0])), (ids[3], 2, Some(ids[2]))]
+    );
+}
+
+#[test]
+pub fn subgraphs() {
+    let (server, _, ids) = road_network(4007, "subgraphs");
+    let graph = &server.graph;
+    let subgraph = graph.extract_subgraph(&[ids[0], ids[1], ids[2], Id::new(0, 2)], "road")
+        .wait().unwrap().unwrap();
+    assert_eq!(subgraph.vertices().len(), 3);
+    let mut edges = subgraph.edges().to_vec();
+    edges.sort();
+    let mut expected = vec![(ids[0], ids[1]), (ids[0], ids[2]), (ids[1], ids[2])];
+    expected.sort();
+    assert_eq!(edges, expected);
+    assert_eq!(subgraph.neighbours(&ids[0]), &[ids[1], ids[2]][..]);
+    assert!(subgraph.neighbours(&ids[3]).is_empty());
+    let egonet = graph.egonet(&ids[1], "road", EdgeDirection::Inbound).wait().unwrap().unwrap();
+    assert_eq!(egonet.vertices().len(), 2); // b and a
+    assert_eq!(egonet.edges(), &[(ids[0], ids[1])][..]);
+}
+
+#[test]
+pub fn transaction_timeout() {
+    let (server, _, ids) = road_network(4008, "transaction_timeout");
+    let graph = &server.graph;
+    let start = ids[0];
+    match graph.graph_transaction_timeout(Duration::from_millis(0), move |txn| {
+        txn.bfs(&start, "road", EdgeDirection::Outbound, 10)
+    }).wait().unwrap() {
+        Err(TxnTimeoutError::TimedOut) => {},
+        other => panic!("expected the traversal to time out, got {:?}", other)
+    }
+    match graph.graph_transaction_timeout(Duration::from_millis(0), move |txn| {
+        txn.degree(&start, "road", EdgeDirection::Outbound)
+    }).wait().unwrap() {
+        Err(TxnTimeoutError::TimedOut) => {}, // counting a list is a walk too
+        other => panic!("expected the degree to time out, got {:?}", other)
+    }
+    match graph.graph_transaction_timeout(Duration::from_millis(0), move |txn| {
+        txn.scan_vertices("city")?.unwrap().collect::<Result<Vec<_>, _>>().map(|vertices| vertices.len())
+    }).wait().unwrap() {
+        Err(TxnTimeoutError::TimedOut) => {},
+        other => panic!("expected the scan to time out, got {:?}", other)
+    }
+    // past the deadline, but the deadline was never checked, so the abort is the closure's own
+    match graph.graph_transaction_timeout(Duration::from_millis(0), |_txn| -> Result<(), TxnError> {
+        Err(TxnError::Aborted(None))
+    }).wait() {
+        Err(TxnError::Aborted(_)) => {},
+        other => panic!("expected a plain abort, got {:?}", other)
+    }
+    assert_eq!(
+        graph.graph_transaction_timeout(Duration::from_secs(60), move |txn| {
+            txn.bfs(&start, "road", EdgeDirection::Outbound, 10)
+        }).wait().unwrap().unwrap().unwrap().len(),
+        4
+    );
+}
+
+// the registry does not keep insertion order, results over a whole schema are compared sorted
+#[test]
+pub fn schema_scans() {
+    let (server, _, ids) = road_network(4009, "schema_scans");
+    let graph = &server.graph;
+    let components: Vec<_> = graph.connected_components("city", "road").wait().unwrap().unwrap()
+        .into_iter().map(sorted).collect();
+    assert_eq!(sorted(components), sorted(vec![sorted(ids[0..4].to_vec()), vec![ids[4]]]));
+    assert_eq!(graph.count_vertices("city").wait().unwrap().unwrap(), 5);
+    let scanned: Vec<_> = graph.scan_vertices("city").wait().unwrap().unwrap()
+        .into_iter().map(|v| v.cell.id()).collect();
+    assert_eq!(sorted(scanned), sorted(ids.clone()));
+    let found: Vec<_> = graph.find_vertices("city", |v| v.get_str("name") != Some("c"))
+        .wait().unwrap().unwrap()
+        .into_iter().map(|v| v.cell.id()).collect();
+    assert_eq!(sorted(found), sorted(vec![ids[0], ids[1], ids[3], ids[4]]));
+    let limited = graph.find_vertices_limit("city", 2, |v| v.get_str("name") != Some("a"))
+        .wait().unwrap().unwrap();
+    assert_eq!(limited.len(), 2);
+    assert!(limited.iter().all(|v| v.cell.id() != ids[0]));
+}
+
+#[test]
+pub fn analytics() {
+    let (server, _, ids) = road_network(4010, "analytics");
+    let graph = &server.graph;
+    let ranks = graph.pagerank("city", "road", 0.85, 20).wait().unwrap().unwrap();
+    assert_eq!(ranks, graph.pagerank("city", "road", 0.85, 20).wait().unwrap().unwrap());
+    #[cfg(feature = "rayon")]
+    {
+        let parallel = graph.pagerank_parallel("city", "road", 0.85, 20).wait().unwrap().unwrap();
+        assert_eq!(parallel.len(), ranks.len());
+        for (&(id, rank), &(parallel_id, parallel_rank)) in ranks.iter().zip(parallel.iter()) {
+            assert_eq!(id, parallel_id);
+            assert!((rank - parallel_rank).abs() < 1e-9);
+        }
+    }
+    let total: f64 = ranks.iter().map(|&(_, rank)| rank).sum();
+    assert!((total - 1f64).abs() < 1e-9);
+    let rank_of = |id: Id| ranks.iter().find(|&&(ranked, _)| ranked == id).unwrap().1;
+    assert!(rank_of(ids[2]) > rank_of(ids[1])); // c is reached from both a and b
+    let communities = graph.louvain("city", "road", 1.0).wait().unwrap().unwrap();
+    assert_eq!(communities, graph.louvain("city", "road", 1.0).wait().unwrap().unwrap());
+    let community_of = |id: Id| communities.iter().find(|&&(member, _)| member == id).unwrap().1;
+    assert!(ids[1..4].iter().all(|&id| community_of(id) == community_of(ids[0])));
+    assert!(community_of(ids[4]) != community_of(ids[0])); // e has no roads
+    let degrees = graph.degree_distribution("city", "road", EdgeDirection::Outbound).wait().unwrap().unwrap();
+    assert_eq!(sorted(degrees), sorted(vec![(ids[0], 2), (ids[1], 1), (ids[2], 1), (ids[3], 1), (ids[4], 0)]));
+    assert_eq!(graph.count_triangles("city", "road").wait().unwrap().unwrap(), 2); // abc, acd
+    assert_eq!(graph.triangles_at(&ids[0], "road").wait().unwrap().unwrap(), 2);
+    assert_eq!(graph.triangles_at(&ids[1], "road").wait().unwrap().unwrap(), 1);
+    assert_eq!(graph.triangles_at(&ids[4], "road").wait().unwrap().unwrap(), 0);
+    let (a, b, e) = (ids[0], ids[1], ids[4]);
+    let common = graph.graph_transaction(move |txn| {
+        Ok(txn.common_neighbours(&a, &b, "road", EdgeDirection::Outbound)?.unwrap())
+    }).wait().unwrap();
+    assert_eq!(common, vec![ids[2]]);
+    let (similarity, isolated) = graph.graph_transaction(move |txn| {
+        Ok((txn.jaccard(&a, &b, "road", EdgeDirection::Outbound)?.unwrap(),
+            txn.jaccard(&e, &e, "road", EdgeDirection::Outbound)?.unwrap()))
+    }).wait().unwrap();
+    assert_eq!(similarity, 0.5); // {c} of {b, c}
+    assert_eq!(isolated, 0.0);
+}
+
+#[test]
+pub fn adjacency() {
+    let (server, _, ids) = road_network(4011, "adjacency");
+    let graph = &server.graph;
+    let batch_ids = vec![ids[4], ids[0], ids[2]];
+    let batch = graph.graph_transaction(move |txn| {
+        Ok(txn.neighbourhoods_many(&batch_ids, "road", EdgeDirection::Outbound)?.unwrap()
+            .into_iter().map(|(id, edges)| (id, edges.len())).collect::<Vec<_>>())
+    }).wait().unwrap();
+    assert_eq!(batch, vec![(ids[4], 0), (ids[0], 2), (ids[2], 1)]);
+    let (a, b, c) = (ids[0], ids[1], ids[2]);
+    let (outbound, inbound, total) = graph.graph_transaction(move |txn| {
+        let adjacency = txn.adjacency(&a, "road")?.unwrap();
+        assert!(adjacency.undirected.is_empty());
+        Ok((adjacency.degree(EdgeDirection::Outbound), adjacency.degree(EdgeDirection::Inbound), adjacency.total_degree()))
+    }).wait().unwrap();
+    assert_eq!((outbound, inbound, total), (2, 1, 3));
+    let oriented = graph.graph_transaction(move |txn| {
+        Ok(txn.oriented_edges(&c, "road", EdgeDirection::Inbound)?.unwrap())
+    }).wait().unwrap();
+    let mut inbound: Vec<_> = oriented.iter().map(|&(from, to, _)| (from, to)).collect();
+    inbound.sort();
+    let mut expected = vec![(ids[0], ids[2]), (ids[1], ids[2])];
+    expected.sort();
+    assert_eq!(inbound, expected);
+    for &(from, to, ref edge) in &oriented {
+        assert_eq!(edge.endpoints(), (from, to));
+        assert_eq!(edge.reversed_endpoints(), (to, from));
+        assert_eq!(edge.reversed().unwrap().endpoints(), (to, from));
+        assert_eq!(edge.relation_to(&c), Some(EdgeDirection::Inbound));
+        assert_eq!(edge.relation_to(&from), Some(EdgeDirection::Outbound));
+        assert_eq!(edge.relation_to(&ids[4]), None);
+    }
+    graph.link_unique(&a, "road", &b, None).wait().unwrap().unwrap(); // already linked
+    let page = graph.graph_transaction(move |txn| {
+        Ok(txn.neighbour_ids_page(&a, "road", EdgeDirection::Outbound, 1, 5)?.unwrap())
+    }).wait().unwrap();
+    assert_eq!(page, (vec![c], 2));
+    let latest = graph.graph_transaction(move |txn| {
+        Ok(txn.latest_neighbour_ids(&a, "road", EdgeDirection::Outbound, 1)?.unwrap())
+    }).wait().unwrap();
+    assert_eq!(latest, vec![c]);
+    let neighbours = graph.graph_transaction(move |txn| {
+        Ok(txn.neighbour_ids(&a, "road", EdgeDirection::Outbound)?.unwrap())
+    }).wait().unwrap();
+    assert_eq!(neighbours, vec![b, c]);
+    let b_out = graph.read_transaction(move |txn| {
+        Ok(txn.degree(&b, "road", EdgeDirection::Outbound)?.unwrap())
+    }).wait().unwrap();
+    assert_eq!(b_out, 1);
+}
+
+#[test]
+pub fn vertex_lifecycle() {
+    let (server, _, ids) = road_network(4012, "vertex_lifecycle");
+    let graph = &server.graph;
+    let missing = Id::new(0, 1);
+    let read: Vec<_> = graph.read_vertices(&[ids[1], missing, ids[0]]).wait().unwrap()
+        .into_iter().map(|v| v.map(|v| v.cell.id())).collect();
+    assert_eq!(read, vec![Some(ids[1]), None, Some(ids[0])]);
+    assert!(graph.vertex_by_with_consistency(&ids[1], ReadConsistency::Eventual).wait().unwrap().is_some());
+    assert!(graph.vertex_by_with_consistency(&missing, ReadConsistency::default()).wait().unwrap().is_none());
+    let upserted = graph.upsert_vertex("city", "f", data_map!{ name: "f" }, |_| None)
+        .wait().unwrap().unwrap();
+    let updated = graph.upsert_vertex("city", "f", data_map!{ name: "f" }, |vertex| Some(vertex))
+        .wait().unwrap().unwrap();
+    assert_eq!(upserted.cell.id(), updated.cell.id());
+    assert_eq!(graph.count_vertices("city").wait().unwrap().unwrap(), 6);
+    graph.remove_vertex(&ids[3]).wait().unwrap().unwrap(); // its roads go with it
+    let (a, c, d) = (ids[0], ids[2], ids[3]);
+    let (c_out, a_in, d_exists) = graph.graph_transaction(move |txn| {
+        Ok((
+            txn.degree(&c, "road", EdgeDirection::Outbound)?.unwrap(),
+            txn.degree(&a, "road", EdgeDirection::Inbound)?.unwrap(),
+            txn.read_vertex(&d)?.is_some()
+        ))
+    }).wait().unwrap();
+    assert_eq!((c_out, a_in, d_exists), (0, 0, false));
+    let (written, renamed, removed) = graph.graph_transaction(|txn| {
+        let z = txn.new_vertex("city", data_map!{ name: "z" })?.unwrap();
+        let written = txn.read_vertex(&z)?.is_some();
+        txn.update_vertex(&z, |mut vertex| {
+            vertex["name"] = Value::String("zz".to_string());
+            Some(vertex)
+        })?.unwrap();
+        let renamed = txn.read_vertex(&z)?.unwrap()["name"].String().unwrap() == "zz";
+        txn.remove_vertex(&z)?.unwrap();
+        Ok((written, renamed, txn.read_vertex(&z)?.is_none()))
+    }).wait().unwrap();
+    assert!(written && renamed && removed); // later reads see earlier writes of the same transaction
+    let exists = graph.graph_transaction(move |txn| {
+        Ok((txn.vertex_exists(&a)?, txn.vertex_exists(&missing)?))
+    }).wait().unwrap();
+    assert_eq!(exists, (true, false));
+}
+
+#[test]
+pub fn import_export() {
+    let (server, _, ids) = road_network(4013, "import_export");
+    let graph = &server.graph;
+    let (a, b, c) = (ids[0], ids[1], ids[2]);
+    let csv = "name,population\ng,100\nh,200\n";
+    let field_map = vec![("name".to_string(), key_hash(&String::from("name")))];
+    assert_eq!(graph.import_vertices_csv("city", csv.as_bytes(), &field_map, 1).wait().unwrap(), 2);
+    let dot = graph.export_dot(&[a, b, c], "road").wait().unwrap().unwrap();
+    assert!(dot.starts_with("digraph {"));
+    assert_eq!(dot.matches("->").count(), 3); // a -> b, a -> c, b -> c
+    let (csr_vertices, offsets, columns) = graph.export_csr("city", "road").wait().unwrap().unwrap();
+    assert_eq!(offsets.len(), csr_vertices.len() + 1);
+    assert_eq!(*offsets.last().unwrap(), columns.len());
+    let row_of = |id: &Id| {
+        let index = csr_vertices.iter().position(|v| v == id).unwrap();
+        columns[offsets[index]..offsets[index + 1]].iter()
+            .map(|&column| csr_vertices[column as usize])
+            .collect::<Vec<_>>()
+    };
+    let mut a_row = row_of(&a);
+    a_row.sort();
+    let mut expected = vec![b, c];
+    expected.sort();
+    assert_eq!(a_row, expected);
+    let csv = "from,to\ng,h\ng,zz\n";
+    let report = graph.import_edges_csv("road", csv.as_bytes(), 0, 1, "city", 10).wait().unwrap();
+    assert_eq!(report.linked, 1);
+    assert_eq!(report.missing_vertices, vec![(3, "zz".to_string())]);
+    let g = graph.find_vertices("city", |v| v.get_str("name") == Some("g")).wait().unwrap().unwrap()[0].cell.id();
+    let h = graph.find_vertices("city", |v| v.get_str("name") == Some("h")).wait().unwrap().unwrap()[0].cell.id();
+    graph.graph_transaction(move |txn| txn.neb_txn.remove(&h)).wait().unwrap(); // leaves g -> h dangling
+    assert!(graph.neighbourhoods::<_, _, String>(&g, "road", EdgeDirection::Outbound, &None)
+        .wait().unwrap().is_err());
+    let (resolved, orphans) = graph.neighbourhoods_skip_missing::<_, _, String>(&g, "road", EdgeDirection::Outbound, &None)
+        .wait().unwrap().unwrap();
+    assert!(resolved.is_empty());
+    assert_eq!(orphans, vec![h]);
+}
+
+#[test]
+pub fn drop_groups() {
+    let (server, road_schema_id, _) = road_network(4014, "drop_groups");
+    let graph = &server.graph;
+    match graph.drop_edge_group("city").wait() {
+        Err(SchemaError::SchemaTypeMismatch(SchemaType::Vertex)) => {},
+        other => panic!("{:?}", other)
+    }
+    match graph.drop_vertex_group("city").wait() {
+        Err(SchemaError::SchemaInUse) => {},
+        other => panic!("{:?}", other)
+    }
+    match graph.drop_edge_group("road").wait() {
+        Err(SchemaError::SchemaInUse) => {}, // city vertices still list roads
+        other => panic!("{:?}", other)
+    }
+    let town_schema = MorpheusSchema::new("town", None, &EMPTY_FIELDS, true);
+    let lane_schema = MorpheusSchema::new("lane", None, &EMPTY_FIELDS, false);
+    graph.new_vertex_group(town_schema).wait().unwrap();
+    graph.new_edge_group(lane_schema, EdgeAttributes::new(EdgeType::Directed, false)).wait().unwrap();
+    graph.drop_edge_group("lane").wait().unwrap();
+    graph.drop_vertex_group("town").wait().unwrap();
+    let schemas = graph.list_schemas();
+    assert!(schemas.contains(&(road_schema_id.0, "road".to_string(),
+                               SchemaType::Edge(EdgeAttributes::new(EdgeType::Directed, false)))));
+    assert!(!schemas.iter().any(|&(_, ref name, _)| name == "town" || name == "lane"));
+}
+
+#[test]
+pub fn segmented_edge_lists() {
+    let (server, _, _) = road_network(4015, "segmented_edge_lists");
+    let graph = &server.graph;
+    let hub_schema = MorpheusSchema::new("hub", Some(&vec!["name".to_string()]), &vec! [
+        Field::new("name", TypeId::String as u32, false, false, None)
+    ], false);
+    let hub_schema_id = graph.new_vertex_group_with_options(
+        hub_schema, VertexGroupOptions { id_list_capacity: Some(2) }
+    ).wait().unwrap();
+    assert_eq!(server.schema_container.id_list_capacity(hub_schema_id.0), Some(2));
+    let hubs: Vec<Id> = ["h0", "h1", "h2", "h3", "h4", "h5"].iter()
+        .map(|name| graph.new_vertex("hub", data_map!{ name: *name }).wait().unwrap().cell.id())
+        .collect();
+    for hub in &hubs[1..] {
+        graph.link(&hubs[0], "road", hub, None).wait().unwrap().unwrap();
+    }
+    let neighbours = graph.graph_transaction({
+        let center = hubs[0];
+        move |txn| Ok(txn.neighbour_ids(&center, "road", EdgeDirection::Outbound)?.unwrap())
+    }).wait().unwrap();
+    assert_eq!(neighbours, hubs[1..].to_vec()); // spread over three segments
+}
+
+#[test]
+pub fn registry_sets() {
+    let (server, _, ids) = road_network(4016, "registry_sets");
+    let graph = &server.graph;
+    graph.remove_vertex(&ids[1]).wait().unwrap().unwrap();
+    graph.remove_vertex(&ids[2]).wait().unwrap().unwrap();
+    assert_eq!(graph.count_vertices("city").wait().unwrap().unwrap(), 3);
+    let f = graph.new_vertex("city", data_map!{ name: "f" }).wait().unwrap().cell.id();
+    assert_eq!(graph.count_vertices("city").wait().unwrap().unwrap(), 4);
+    match graph.new_vertex("city", data_map!{ name: "a" }).wait() {
+        Err(NewVertexError::WriteError(_)) => {},
+        other => panic!("the key is taken, got {:?}", other)
+    }
+    assert_eq!(graph.count_vertices("city").wait().unwrap().unwrap(), 4);
+    let backfilled = graph.backfill_vertex_registry("city", vec![ids[0], f], 1);
+    assert_eq!(backfilled.wait().unwrap().unwrap(), 0); // covered schemas are not walked again
+    match graph.drop_edge_group("road").wait() {
+        Err(SchemaError::SchemaInUse) => {}, // d still links to a
+        other => panic!("{:?}", other)
+    }
+    graph.unlink(&ids[3], "road", &ids[0]).wait().unwrap().unwrap();
+    graph.drop_edge_group("road").wait().unwrap();
+}
+
+#[test]
+pub fn merge_edges() {
+    let server = start_server(4017, "merge_edges");
+    let graph = &server.graph;
+    let people_schema = MorpheusSchema::new("people", Some(&vec!["name".to_string()]), &vec! [
+        Field::new("name", TypeId::String as u32, false, false, None)
+    ], true);
+    let rated_schema = MorpheusSchema::new("rated", None, &vec! [
+        Field::new("score", TypeId::U32 as u32, false, false, None),
+        Field::new("note", TypeId::String as u32, true, false, None)
+    ], true);
+    let knows_schema = MorpheusSchema::new("knows", None, &EMPTY_FIELDS, false);
+    graph.new_vertex_group(people_schema).wait().unwrap();
+    graph.new_edge_group(rated_schema, EdgeAttributes::new(EdgeType::Directed, true)).wait().unwrap();
+    graph.new_edge_group(knows_schema, EdgeAttributes::new(EdgeType::Directed, false)).wait().unwrap();
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap().cell.id();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap().cell.id();
+    let carol = graph.new_vertex("people", data_map!{ name: "Carol" }).wait().unwrap().cell.id();
+    graph.link(&bob, "rated", &alice, Some(data_map!{ score: 5 as u32 })).wait().unwrap().unwrap();
+    graph.link(&carol, "rated", &alice, Some(data_map!{ score: 3 as u32, note: "kind" })).wait().unwrap().unwrap();
+    graph.link(&carol, "knows", &alice, None).wait().unwrap().unwrap();
+    // every edge schema of carol is found without being listed
+    graph.merge_vertices(&bob, &carol).wait().unwrap().unwrap();
+    assert!(graph.are_linked(&bob, "knows", &alice, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    let body = graph.graph_transaction(move |txn| {
+        let edge = txn.find_edge(&bob, "rated", &alice)?.unwrap().unwrap();
+        Ok((edge.get_i64("score"), edge.get_str("note").map(|note| note.to_string())))
+    }).wait().unwrap();
+    assert_eq!(body.0, Some(5)); // bob's own edge wins
+    assert_eq!(body.1, Some("kind".to_string())); // carol's body fills what it lacks
+    assert_eq!(graph.degree(&alice, "rated", EdgeDirection::Inbound).wait().unwrap().unwrap(), 1);
+}
+
+#[test]
+pub fn rekey_edges() {
+    let server = start_server(4018, "rekey_edges");
+    let graph = &server.graph;
+    let people_schema = MorpheusSchema::new("people", Some(&vec!["name".to_string()]), &vec! [
+        Field::new("name", TypeId::String as u32, false, false, None)
+    ], true);
+    let rated_schema = MorpheusSchema::new("rated", None, &vec! [
+        Field::new("score", TypeId::U32 as u32, false, false, None)
+    ], true);
+    let knows_schema = MorpheusSchema::new("knows", None, &EMPTY_FIELDS, false);
+    graph.new_vertex_group(people_schema).wait().unwrap();
+    graph.new_edge_group(rated_schema, EdgeAttributes::new(EdgeType::Directed, true)).wait().unwrap();
+    graph.new_edge_group(knows_schema, EdgeAttributes::new(EdgeType::Directed, false)).wait().unwrap();
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap().cell.id();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap().cell.id();
+    graph.link(&bob, "rated", &alice, Some(data_map!{ score: 4 as u32 })).wait().unwrap().unwrap();
+    graph.link(&alice, "knows", &bob, None).wait().unwrap().unwrap();
+    let robert = graph.rekey_vertex(&bob, "people", &Value::String("Robert".to_string()))
+        .wait().unwrap().unwrap();
+    assert!(graph.vertex_by(&bob).wait().unwrap().is_none());
+    assert_eq!(graph.vertex_by_key("people", "Robert").wait().unwrap().unwrap().cell.id(), robert);
+    assert!(graph.are_linked(&alice, "knows", &robert, EdgeDirection::Outbound).wait().unwrap().unwrap());
+    let score = graph.graph_transaction(move |txn| {
+        Ok(txn.find_edge(&robert, "rated", &alice)?.unwrap().unwrap().get_i64("score"))
+    }).wait().unwrap();
+    assert_eq!(score, Some(4));
+    assert_eq!(graph.degree(&alice, "rated", EdgeDirection::Inbound).wait().unwrap().unwrap(), 1);
+}
+
+#[test]
+pub fn vertex_batches() {
+    let server = start_server(4019, "vertex_batches");
+    let graph = &server.graph;
+    let city_schema = MorpheusSchema::new("city", Some(&vec!["name".to_string()]), &vec! [
+        Field::new("name", TypeId::String as u32, false, false, None)
+    ], true);
+    graph.new_vertex_group(city_schema).wait().unwrap();
+    let created = graph.new_vertices(vec![
+        ("city", data_map!{ name: "a" }), ("city", data_map!{ name: "b" })
+    ]).wait().unwrap();
+    assert_eq!(created.len(), 2);
+    // the second item repeats the first key, so the whole batch is rolled back
+    match graph.new_vertices(vec![
+        ("city", data_map!{ name: "c" }), ("city", data_map!{ name: "c" }), ("city", data_map!{ name: "d" })
+    ]).wait() {
+        Err(NewVertexError::BatchItemError(1, ref error)) => match **error {
+            NewVertexError::WriteError(WriteError::CellAlreadyExisted) => {},
+            ref other => panic!("{:?}", other)
+        },
+        other => panic!("{:?}", other)
+    }
+    assert!(graph.vertex_by_key("city", "c").wait().unwrap().is_none());
+    assert!(graph.vertex_by_key("city", "d").wait().unwrap().is_none());
+    assert_eq!(graph.count_vertices("city").wait().unwrap().unwrap(), 2);
+}
+
+#[test]
+pub fn edge_updates() {
+    let server = start_server(4020, "edge_updates");
+    let graph = &server.graph;
+    let people_schema = MorpheusSchema::new("people", Some(&vec!["name".to_string()]), &vec! [
+        Field::new("name", TypeId::String as u32, false, false, None)
+    ], true);
+    let rated_schema = MorpheusSchema::new("rated", None, &vec! [
+        Field::new("score", TypeId::U32 as u32, false, false, None)
+    ], true);
+    graph.new_vertex_group(people_schema).wait().unwrap();
+    let rated_schema_id = graph.new_edge_group(rated_schema, EdgeAttributes::new(EdgeType::Directed, true)).wait().unwrap();
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap().cell.id();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap().cell.id();
+    let carol = graph.new_vertex("people", data_map!{ name: "Carol" }).wait().unwrap().cell.id();
+    let edge = graph.link(&alice, "rated", &bob, Some(data_map!{ score: 1 as u32 })).wait().unwrap().unwrap();
+    let edge_id = edge.id().unwrap();
+    match graph.update_edge(&edge_id, rated_schema_id, |mut edge| {
+        edge["score"] = Value::String("high".to_string());
+        Some(edge)
+    }).wait().unwrap() {
+        Err(EdgeError::InvalidBody(EdgeBodyError::FieldTypeMismatch { .. })) => {},
+        other => panic!("{:?}", other)
+    }
+    match graph.update_edge(&edge_id, rated_schema_id, move |mut edge| {
+        edge["_inbound"] = Value::Id(carol);
+        Some(edge)
+    }).wait().unwrap() {
+        Err(EdgeError::EndpointChanged(field)) => assert_eq!(field, Graph::field_id("_inbound")),
+        other => panic!("{:?}", other)
+    }
+    match graph.update_edge(&edge_id, rated_schema_id, |edge| match edge {
+        Edge::Directed(mut edge) => { edge.cell = None; Some(Edge::Directed(edge)) },
+        edge => Some(edge)
+    }).wait().unwrap() {
+        Err(EdgeError::BodyRemoved) => {},
+        other => panic!("{:?}", other)
+    }
+    graph.update_edge(&edge_id, rated_schema_id, |mut edge| {
+        edge["score"] = Value::U32(7);
+        Some(edge)
+    }).wait().unwrap().unwrap();
+    let found = graph.graph_transaction(move |txn| {
+        Ok(txn.find_edge(&alice, "rated", &bob)?.unwrap().unwrap().get_i64("score"))
+    }).wait().unwrap();
+    assert_eq!(found, Some(7));
+}
+
+#[test]
+pub fn weighted_paths() {
+    let server = start_server(4021, "weighted_paths");
+    let graph = &server.graph;
+    let town_schema = MorpheusSchema::new("town", Some(&vec!["name".to_string()]), &vec! [
+        Field::new("name", TypeId::String as u32, false, false, None)
+    ], true);
+    let lane_schema = MorpheusSchema::new("lane", None, &vec! [
+        Field::new("length", TypeId::F64 as u32, false, false, None)
+    ], true);
+    graph.new_vertex_group(town_schema).wait().unwrap();
+    graph.new_edge_group(lane_schema, EdgeAttributes::new(EdgeType::Directed, true)).wait().unwrap();
+    let ids: Vec<Id> = ["a", "b", "c", "d", "e"].iter()
+        .map(|name| graph.new_vertex("town", data_map!{ name: *name }).wait().unwrap().cell.id())
+        .collect();
+    // a -> b -> d costs 2, a -> d costs 3 and a -> c -> d costs 5.5
+    for &(from, to, length) in &[(0, 1, 1f64), (1, 3, 1f64), (0, 3, 3f64), (0, 2, 5f64), (2, 3, 0.5f64)] {
+        graph.link(&ids[from], "lane", &ids[to], Some(data_map!{ length: length })).wait().unwrap().unwrap();
+    }
+    let length_field = Graph::field_id("length");
+    assert_eq!(
+        graph.shortest_path_weighted(&ids[0], "lane", &ids[3], EdgeDirection::Outbound, length_field)
+            .wait().unwrap().unwrap(),
+        Some((vec![ids[0], ids[1], ids[3]], 2f64))
+    );
+    graph.link(&ids[3], "lane", &ids[4], Some(data_map!{ length: -1f64 })).wait().unwrap().unwrap();
+    match graph.shortest_path_weighted(&ids[0], "lane", &ids[4], EdgeDirection::Outbound, length_field).wait().unwrap() {
+        Err(EdgeError::InvalidWeight(field)) => assert_eq!(field, length_field),
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+pub fn unlinked_removals() {
+    let (server, _, ids) = road_network(4022, "unlinked_removals");
+    let graph = &server.graph;
+    // f and e never had an edge, so they have no edge lists at all
+    let f = graph.new_vertex("city", data_map!{ name: "f" }).wait().unwrap().cell.id();
+    graph.remove_vertex(&f).wait().unwrap().unwrap();
+    graph.remove_vertex(&ids[4]).wait().unwrap().unwrap();
+    graph.remove_vertex(&ids[4]).wait().unwrap().unwrap(); // already gone
+    let (a, e) = (ids[0], ids[4]);
+    let (f_exists, e_exists, a_out) = graph.graph_transaction(move |txn| {
+        Ok((txn.vertex_exists(&f)?, txn.vertex_exists(&e)?, txn.degree(&a, "road", EdgeDirection::Outbound)?.unwrap()))
+    }).wait().unwrap();
+    assert_eq!((f_exists, e_exists, a_out), (false, false, 2));
+    assert_eq!(graph.count_vertices("city").wait().unwrap().unwrap(), 4);
+    match graph.graph_transaction(move |txn| txn.remove_vertex(&e)).wait().unwrap() {
+        Err(RemoveError::NotFound) => {}, // the transaction level call still tells absent vertices apart
+        other => panic!("{:?}", other)
+    }
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+pub fn metrics_counters() {
+    let (server, _, ids) = road_network(4023, "metrics_counters");
+    let graph = &server.graph;
+    let before = graph.metrics();
+    let f = graph.new_vertex("city", data_map!{ name: "f" }).wait().unwrap().cell.id();
+    graph.link(&ids[4], "road", &f, None).wait().unwrap().unwrap();
+    let a = ids[0];
+    graph.graph_transaction(move |txn| {
+        let g = txn.new_vertex("city", data_map!{ name: "g" })?.unwrap();
+        txn.link(&a, "road", &g.cell.id(), None)?.unwrap();
+        Ok(())
+    }).wait().unwrap();
+    // the writes of an aborted transaction are not counted
+    assert!(graph.graph_transaction(|txn| {
+        txn.new_vertex("city", data_map!{ name: "h" })?.unwrap();
+        Err::<(), _>(TxnError::Aborted(None))
+    }).wait().is_err());
+    assert!(graph.vertex_by(&f).wait().unwrap().is_some());
+    let after = graph.metrics();
+    assert_eq!(after.vertices_created - before.vertices_created, 2);
+    assert_eq!(after.edges_linked - before.edges_linked, 2);
+    assert_eq!(after.transactions_aborted - before.transactions_aborted, 1);
+    assert_eq!(after.read_errors, before.read_errors);
+}
+
+#[test]
+pub fn dfs_order() {
+    let (server, _, ids) = road_network(4051, "dfs_order");
+    let graph = &server.graph;
+    // a branches to b and c, d leads back to a
+    let visits = Rc::new(cell::RefCell::new(Vec::new()));
+    let visits_ref = visits.clone();
+    graph.dfs(&ids[0], "road", EdgeDirection::Outbound, move |id, depth| {
+        visits_ref.borrow_mut().push((*id, depth));
+        true
+    }).wait().unwrap().unwrap();
+    assert_eq!(*visits.borrow(), vec![(ids[0], 0), (ids[1], 1), (ids[2], 2), (ids[3], 3)]);
+    // without going past b, c is reached from a instead
+    let pruned = Rc::new(cell::RefCell::new(Vec::new()));
+    let (pruned_ref, b) = (pruned.clone(), ids[1]);
+    graph.dfs(&ids[0], "road", EdgeDirection::Outbound, move |id, depth| {
+        pruned_ref.borrow_mut().push((*id, depth));
+        *id != b
+    }).wait().unwrap().unwrap();
+    assert_eq!(*pruned.borrow(), vec![(ids[0], 0), (ids[1], 1), (ids[2], 1), (ids[3], 2)]);
+}
+
+#[test]
+pub fn neighbourhood_iter() {
+    let (server, _, ids) = road_network(4052, "neighbourhood_iter");
+    let (a, b, c) = (ids[0], ids[1], ids[2]);
+    let steps = server.graph.graph_transaction(move |txn| {
+        let mut edges = txn.neighbourhood_iter(&a, "road", EdgeDirection::Outbound)?.unwrap();
+        // edges come one at a time in the order they were linked
+        let first = edges.next().unwrap()?.unwrap().endpoints();
+        let second = edges.next().unwrap()?.unwrap().endpoints();
+        let exhausted = edges.next().is_none();
+        let partial = txn.neighbourhood_iter(&a, "road", EdgeDirection::Outbound)?.unwrap()
+            .take(1).count();
+        Ok((first, second, exhausted, partial))
+    }).wait().unwrap();
+    assert_eq!(steps, ((a, b), (a, c), true, 1));
+}
+
+#[test]
+pub fn neighbourhoods_where() {
+    let (server, _, ids) = road_network(4053, "neighbourhoods_where");
+    let (a, c) = (ids[0], ids[2]);
+    let (to_c, none, all) = server.graph.graph_transaction(move |txn| {
+        let to_c = txn.neighbourhoods_where(&a, "road", EdgeDirection::Outbound, |edge| edge.endpoints().1 == c)?.unwrap();
+        let none = txn.neighbourhoods_where(&a, "road", EdgeDirection::Outbound, |_| false)?.unwrap();
+        let all = txn.neighbourhoods_where(&a, "road", EdgeDirection::Outbound, |_| true)?.unwrap();
+        Ok((
+            to_c.iter().map(|edge| edge.endpoints()).collect::<Vec<_>>(),
+            none.len(),
+            all.len()
+        ))
+    }).wait().unwrap();
+    assert_eq!(to_c, vec![(a, c)]);
+    assert_eq!(none, 0);
+    assert_eq!(all, 2);
+}
+
+#[test]
+pub fn bilateral_edges() {
+    let (server, _, _) = people_network(4054, "bilateral_edges");
+    let graph = &server.graph;
+    let partners_schema = MorpheusSchema::new("partners", None, &vec! [
+        Field::new("since", TypeId::U32 as u32, false, false, None)
+    ], true);
+    graph.new_edge_group(partners_schema, EdgeAttributes::bilateral()).wait().unwrap();
+    let alice = graph.new_vertex("people", data_map!{ name: "Alice" }).wait().unwrap().cell.id();
+    let bob = graph.new_vertex("people", data_map!{ name: "Bob" }).wait().unwrap().cell.id();
+    match graph.link_bilateral(&alice, "knows", &bob, data_map!{ since: 2010 as u32 }).wait().unwrap() {
+        Err(LinkVerticesError::NotBilateralEdge) => {},
+        other => panic!("knows is directed, got {:?}", other)
+    }
+    graph.link_bilateral(&alice, "partners", &bob, data_map!{ since: 2010 as u32 }).wait().unwrap().unwrap();
+    assert!(graph.are_linked(&alice, "partners", &bob, EdgeDirection::Undirected).wait().unwrap().unwrap());
+    assert!(graph.are_linked(&bob, "partners", &alice, EdgeDirection::Undirected).wait().unwrap().unwrap());
+    // one body cell, read back from either end
+    let since = graph.graph_transaction(move |txn| {
+        let from_alice = txn.find_edge(&alice, "partners", &bob)?.unwrap().unwrap();
+        let from_bob = txn.find_edge(&bob, "partners", &alice)?.unwrap().unwrap();
+        Ok((from_alice.get_i64("since"), from_bob.get_i64("since"), from_alice.id() == from_bob.id()))
+    }).wait().unwrap();
+    assert_eq!(since, (Some(2010), Some(2010), true));
+}
+
+#[test]
+pub fn flatten() {
+    let done: Result<Result<u32, EdgeError>, TxnError> = Ok(Ok(1));
+    assert_eq!(GraphTransaction::flatten(done).unwrap(), 1);
+    let failed: Result<Result<u32, EdgeError>, TxnError> = Ok(Err(EdgeError::EdgeNotFound));
+    match GraphTransaction::flatten(failed) {
+        Err(GraphOpError::OpError(EdgeError::EdgeNotFound)) => {},
+        other => panic!("{:?}", other)
+    }
+    let aborted: Result<Result<u32, EdgeError>, TxnError> = Err(TxnError::Aborted(None));
+    match GraphTransaction::flatten(aborted) {
+        Err(GraphOpError::TxnError(TxnError::Aborted(None))) => {},
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+pub fn async_graph() {
+    let (server, _, ids) = road_network(4055, "async_graph");
+    let graph = AsyncGraph::new(&server.graph, CpuPool::new(2));
+    let f = graph.new_vertex("city", data_map!{ name: "f" }).wait().unwrap().cell.id();
+    graph.link(&ids[0], "road", &f, None).wait().unwrap().unwrap();
+    let neighbours = graph.neighbourhoods::<_, _, String>(&ids[0], "road", EdgeDirection::Outbound, None)
+        .wait().unwrap().unwrap();
+    assert_eq!(neighbours.len(), 3);
+    graph.update_vertex(&f, |mut city| {
+        city["name"] = Value::String("g".to_string());
+        Some(city)
+    }).wait().unwrap().unwrap();
+    assert_eq!(graph.vertex_by(&f).wait().unwrap().unwrap().get_str("name"), Some("g"));
+    let degree = graph.graph_transaction(move |txn| {
+        Ok(txn.degree(&f, "road", EdgeDirection::Inbound)?.unwrap())
+    }).wait().unwrap();
+    assert_eq!(degree, 1);
+    graph.remove_vertex(&f).wait().unwrap().unwrap();
+    assert!(graph.vertex_by(&f).wait().unwrap().is_none());
+}
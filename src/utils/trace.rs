@@ -0,0 +1,73 @@
+use neb::client::transaction::TxnError;
+use futures::prelude::*;
+
+#[cfg(feature = "tracing")]
+use tracing;
+
+pub type Outcome<I, E> = fn(&Result<I, E>) -> &'static str;
+
+pub fn result_outcome<I, E>(result: &Result<I, E>) -> &'static str {
+    if result.is_ok() { "succeeded" } else { "errored" }
+}
+
+pub fn txn_outcome<I>(result: &Result<I, TxnError>) -> &'static str {
+    match result {
+        &Ok(_) => "succeeded",
+        &Err(TxnError::Aborted(_)) => "aborted",
+        &Err(_) => "errored"
+    }
+}
+
+pub fn nested_outcome<T, E>(result: &Result<Result<T, E>, TxnError>) -> &'static str {
+    match result {
+        &Ok(Ok(_)) => "succeeded",
+        &Ok(Err(_)) => "errored",
+        &Err(TxnError::Aborted(_)) => "aborted",
+        &Err(_) => "errored"
+    }
+}
+
+// The span is entered on every poll, so events logged while the operation runs, such as
+// those of a transaction body, belong to it. It ends when the future resolves.
+#[cfg(feature = "tracing")]
+pub struct Traced<F> where F: Future {
+    span: tracing::Span,
+    future: F,
+    outcome: Outcome<F::Item, F::Error>
+}
+
+#[cfg(feature = "tracing")]
+impl <F> Future for Traced<F> where F: Future {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let _enter = self.span.enter();
+        let result = match self.future.poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(item)) => Ok(item),
+            Err(e) => Err(e)
+        };
+        self.span.record("outcome", &(self.outcome)(&result));
+        result.map(Async::Ready)
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub fn traced<F>(op: &'static str, schema_id: Option<u32>, future: F, outcome: Outcome<F::Item, F::Error>)
+    -> impl Future<Item = F::Item, Error = F::Error>
+    where F: Future
+{
+    let span = tracing::info_span!(
+        "graph_op", op = op, schema_id = schema_id.unwrap_or(0), outcome = tracing::field::Empty
+    );
+    Traced { span, future, outcome }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn traced<F>(_op: &'static str, _schema_id: Option<u32>, future: F, _outcome: Outcome<F::Item, F::Error>)
+    -> impl Future<Item = F::Item, Error = F::Error>
+    where F: Future
+{
+    future
+}
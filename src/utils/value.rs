@@ -0,0 +1,144 @@
+use neb::ram::types::{Value, Map, Id, TypeId, key_hash};
+use serde_json::{self, Value as JsonValue, Number};
+
+use std::collections::BTreeMap;
+
+pub fn to_f64(value: &Value) -> Option<f64> {
+    match value {
+        &Value::I8(v) => Some(v as f64),
+        &Value::I16(v) => Some(v as f64),
+        &Value::I32(v) => Some(v as f64),
+        &Value::I64(v) => Some(v as f64),
+        &Value::U8(v) => Some(v as f64),
+        &Value::U16(v) => Some(v as f64),
+        &Value::U32(v) => Some(v as f64),
+        &Value::U64(v) => Some(v as f64),
+        &Value::F32(v) => Some(v as f64),
+        &Value::F64(v) => Some(v),
+        _ => None
+    }
+}
+
+// Integer values that fit an i64, unsigned values beyond i64::MAX are rejected
+pub fn to_i64(value: &Value) -> Option<i64> {
+    match value {
+        &Value::I8(v) => Some(v as i64),
+        &Value::I16(v) => Some(v as i64),
+        &Value::I32(v) => Some(v as i64),
+        &Value::I64(v) => Some(v),
+        &Value::U8(v) => Some(v as i64),
+        &Value::U16(v) => Some(v as i64),
+        &Value::U32(v) => Some(v as i64),
+        &Value::U64(v) if v <= i64::max_value() as u64 => Some(v as i64),
+        _ => None
+    }
+}
+
+pub fn as_str(value: &Value) -> Option<&str> {
+    match value {
+        &Value::String(ref v) => Some(v.as_str()),
+        _ => None
+    }
+}
+
+pub fn as_id(value: &Value) -> Option<Id> {
+    match value {
+        &Value::Id(id) => Some(id),
+        _ => None
+    }
+}
+
+pub fn id_to_json(id: &Id) -> JsonValue {
+    JsonValue::String(format!("{}-{}", id.higher, id.lower))
+}
+
+pub fn to_json(value: &Value) -> JsonValue {
+    match value {
+        &Value::Null => JsonValue::Null,
+        &Value::Bool(v) => JsonValue::Bool(v),
+        &Value::I8(v) => JsonValue::from(v as i64),
+        &Value::I16(v) => JsonValue::from(v as i64),
+        &Value::I32(v) => JsonValue::from(v as i64),
+        &Value::I64(v) => JsonValue::from(v),
+        &Value::U8(v) => JsonValue::from(v as u64),
+        &Value::U16(v) => JsonValue::from(v as u64),
+        &Value::U32(v) => JsonValue::from(v as u64),
+        &Value::U64(v) => JsonValue::from(v),
+        &Value::F32(_) | &Value::F64(_) => to_f64(value)
+            .and_then(Number::from_f64)
+            .map(JsonValue::Number)
+            .unwrap_or(JsonValue::Null), // NaN and infinities have no json form
+        &Value::String(ref v) => JsonValue::String(v.clone()),
+        &Value::Id(ref id) => id_to_json(id),
+        &Value::Array(ref array) => JsonValue::Array(array.iter().map(to_json).collect()),
+        &Value::Map(ref map) => map_to_json(map, &[]),
+        other => JsonValue::String(format!("{:?}", other))
+    }
+}
+
+// Keys are field names when the map knows them, otherwise the key id.
+// Top level keys listed in `hidden` are left out.
+pub fn map_to_json(map: &Map, hidden: &[u64]) -> JsonValue {
+    let names: BTreeMap<u64, &String> = map.fields
+        .iter()
+        .map(|name| (key_hash(name), name))
+        .collect();
+    let mut object = serde_json::Map::new();
+    for (key_id, value) in map.map.iter() {
+        if hidden.contains(key_id) { continue; }
+        let key = match names.get(key_id) {
+            Some(name) => name.to_string(),
+            None => key_id.to_string()
+        };
+        object.insert(key, to_json(value));
+    }
+    JsonValue::Object(object)
+}
+
+// Type id of a scalar value, None for nulls, arrays and maps
+pub fn type_id_of(value: &Value) -> Option<u32> {
+    let type_id = match value {
+        &Value::Bool(_) => TypeId::Bool,
+        &Value::I8(_) => TypeId::I8,
+        &Value::I16(_) => TypeId::I16,
+        &Value::I32(_) => TypeId::I32,
+        &Value::I64(_) => TypeId::I64,
+        &Value::U8(_) => TypeId::U8,
+        &Value::U16(_) => TypeId::U16,
+        &Value::U32(_) => TypeId::U32,
+        &Value::U64(_) => TypeId::U64,
+        &Value::F32(_) => TypeId::F32,
+        &Value::F64(_) => TypeId::F64,
+        &Value::String(_) => TypeId::String,
+        &Value::Id(_) => TypeId::Id,
+        _ => return None
+    };
+    Some(type_id as u32)
+}
+
+fn parse_id(text: &str) -> Option<Id> {
+    let mut parts = text.trim().splitn(2, '-');
+    let higher = parts.next()?.parse().ok()?;
+    let lower = parts.next()?.parse().ok()?;
+    Some(Id::new(higher, lower))
+}
+
+// Parses text for a field of the given type, falling back to a string for other types.
+pub fn parse_typed(text: &str, type_id: u32) -> Option<Value> {
+    macro_rules! parse {
+        ($variant: ident) => { text.trim().parse().ok().map(Value::$variant) };
+    }
+    if type_id == TypeId::Bool as u32 { parse!(Bool) }
+    else if type_id == TypeId::I8 as u32 { parse!(I8) }
+    else if type_id == TypeId::I16 as u32 { parse!(I16) }
+    else if type_id == TypeId::I32 as u32 { parse!(I32) }
+    else if type_id == TypeId::I64 as u32 { parse!(I64) }
+    else if type_id == TypeId::U8 as u32 { parse!(U8) }
+    else if type_id == TypeId::U16 as u32 { parse!(U16) }
+    else if type_id == TypeId::U32 as u32 { parse!(U32) }
+    else if type_id == TypeId::U64 as u32 { parse!(U64) }
+    else if type_id == TypeId::F32 as u32 { parse!(F32) }
+    else if type_id == TypeId::F64 as u32 { parse!(F64) }
+    else if type_id == TypeId::Id as u32 { parse_id(text).map(Value::Id) }
+    else { Some(Value::String(text.to_string())) }
+}